@@ -1,8 +1,11 @@
-use criterion::{criterion_group, criterion_main, Criterion};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use flatgeobuf::packed_r_tree::NodeItem;
 use flatgeobuf::*;
+use geo_types::{line_string, point, Geometry};
 use geozero::ProcessorSink;
 use seek_bufread::BufReader;
 use std::fs::File;
+use std::io::Cursor;
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
@@ -53,6 +56,52 @@ fn read_bbox_seq() -> Result<()> {
     Ok(())
 }
 
+/// Pseudo-random coordinate in `[0, 1000)`
+fn coord(i: u64, seed: u64) -> f64 {
+    let v = (i.wrapping_add(seed)).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    (v >> 11) as f64 / (1u64 << 53) as f64 * 1000.0
+}
+
+/// Write 50'000 points or long thin segments with the given sort order
+fn write_dataset(lines: bool, sort: SortOrder) -> Result<Vec<u8>> {
+    let geometry_type = if lines {
+        GeometryType::LineString
+    } else {
+        GeometryType::Point
+    };
+    let mut fgb = FgbWriter::create_with_options(
+        "sort",
+        geometry_type,
+        FgbWriterOptions {
+            sort,
+            ..Default::default()
+        },
+    )?;
+    for i in 0..50_000 {
+        let (x, y) = (coord(i, 1), coord(i, 2));
+        if lines {
+            // Roads running mostly east-west, up to 50 units long
+            let geom = line_string![(x: x, y: y), (x: x + coord(i, 3) / 20.0, y: y + 0.5)];
+            fgb.add_feature_geom(Geometry::from(geom), |_| {})?;
+        } else {
+            fgb.add_feature_geom(Geometry::from(point!(x: x, y: y)), |_| {})?;
+        }
+    }
+    let mut out = Vec::new();
+    fgb.write(&mut out)?;
+    Ok(out)
+}
+
+/// 100 queries of 10x10 units
+fn read_bboxes(data: &[u8]) -> Result<()> {
+    for i in 0..100 {
+        let (x, y) = (coord(i, 4), coord(i, 5));
+        let mut fgb = FgbReader::open(Cursor::new(data))?.select_bbox(x, y, x + 10.0, y + 10.0)?;
+        while fgb.next()?.is_some() {}
+    }
+    Ok(())
+}
+
 // fn read_header(fname: &str) -> Result<(File, FgbReader)> {
 //     let fin = File::open(fname)?;
 //     let mut filein = BufReader::new(fin);
@@ -72,6 +121,30 @@ fn criterion_benchmark(c: &mut Criterion) {
     c.bench_function("read_fgb_seq", |b| b.iter(read_fgb_seq));
     c.bench_function("read_bbox", |b| b.iter(read_bbox));
     c.bench_function("read_bbox_seq", |b| b.iter(read_bbox_seq));
+
+    let orders = [
+        ("hilbert", SortOrder::Hilbert),
+        ("zorder", SortOrder::ZOrder),
+        ("str", SortOrder::Str),
+        ("input", SortOrder::Input),
+        // Row-major order of 10x10 cells
+        (
+            "key",
+            SortOrder::Key(|node: &NodeItem, _: &NodeItem| {
+                ((node.min_y / 10.0) as u64) << 32 | (node.min_x / 10.0) as u64
+            }),
+        ),
+    ];
+    for (lines, dataset) in [(false, "points"), (true, "lines")] {
+        let mut group = c.benchmark_group(format!("read_bbox_sort_{dataset}"));
+        for (name, sort) in orders {
+            let data = write_dataset(lines, sort).unwrap();
+            group.bench_with_input(BenchmarkId::from_parameter(name), &data, |b, data| {
+                b.iter(|| read_bboxes(data))
+            });
+        }
+        group.finish();
+    }
     // c.bench_function("select_bbox", move |b| {
     //     b.iter_with_setup(
     //         || read_header("../../test/data/countries.fgb").unwrap(),
@@ -6,7 +6,7 @@ use geozero::geojson::GeoJsonWriter;
 use geozero::FeatureProcessor;
 use geozero::GeozeroDatasource;
 use std::fs::File;
-use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, Write};
 
 pub struct GeoJsonReaderStream<'a, R: Read>(pub &'a mut R);
 
@@ -160,6 +160,26 @@ fn info(mut input: impl BufRead, args: &Info) -> Result<()> {
     Ok(())
 }
 
+fn info_file(path: &str, args: &Info) -> Result<()> {
+    let mut input = BufReader::new(File::open(path)?);
+    let mut fgb = FgbReader::open(&mut input)?;
+    let header = fgb.header();
+    match args.dump {
+        // Random access to feature with index
+        Some(fno) if !args.index && header.index_node_size() > 0 && header.features_count() > 0 => {
+            println!("{:#?}", header);
+            if let Some(feature) = fgb.feature(fno)? {
+                println!("{:#?}", feature.fbs_feature());
+            }
+            Ok(())
+        }
+        _ => {
+            input.rewind()?;
+            info(input, args)
+        }
+    }
+}
+
 fn main() -> Result<()> {
     let cli = Cli::parse();
     match &cli.command {
@@ -180,13 +200,10 @@ fn main() -> Result<()> {
                 args.index,
             )?;
         }
-        Commands::Info(args) => {
-            let input: Box<dyn BufRead> = match &args.input {
-                Some(x) => Box::new(BufReader::new(File::open(x)?)),
-                None => Box::new(BufReader::new(std::io::stdin())),
-            };
-            info(input, args)?;
-        }
+        Commands::Info(args) => match &args.input {
+            Some(x) => info_file(x, args)?,
+            None => info(BufReader::new(std::io::stdin()), args)?,
+        },
     }
     Ok(())
 }
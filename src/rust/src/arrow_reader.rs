@@ -0,0 +1,737 @@
+//! Conversion of FlatGeobuf features into Arrow record batches.
+
+use crate::error::{Error, Result};
+use crate::feature_writer::convert_value;
+use crate::header_generated::{ColumnType, GeometryType, Header};
+use crate::{FeatureIter, FgbFeature};
+use arrow_array::builder::{
+    ArrayBuilder, BinaryBuilder, BooleanBuilder, Float32Builder, Float64Builder, Int16Builder,
+    Int32Builder, Int64Builder, Int8Builder, StringBuilder, UInt16Builder, UInt32Builder,
+    UInt64Builder, UInt8Builder,
+};
+use arrow_array::{ArrayRef, FixedSizeListArray, Float64Array, ListArray, RecordBatch};
+use arrow_buffer::{NullBuffer, OffsetBuffer, ScalarBuffer};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use fallible_streaming_iterator::FallibleStreamingIterator;
+use geozero::error::GeozeroError;
+use geozero::wkb::{WkbDialect, WkbWriter};
+use geozero::{
+    ColumnValue, CoordDimensions, FeatureProperties, GeomProcessor, GeozeroGeometry,
+    PropertyProcessor,
+};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// Arrow field metadata key of extension types
+const EXTENSION_NAME: &str = "ARROW:extension:name";
+/// Arrow field metadata key of extension type parameters
+const EXTENSION_METADATA: &str = "ARROW:extension:metadata";
+
+/// Encoding of the geometry column
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GeometryEncoding {
+    /// GeoArrow native encoding with interleaved coordinates, e.g. `geoarrow.polygon`.
+    ///
+    /// Requires a single geometry type of Point, LineString, Polygon, MultiPoint,
+    /// MultiLineString or MultiPolygon in the header.
+    #[default]
+    Native,
+    /// GeoArrow `geoarrow.wkb` encoding, supporting all geometry types
+    Wkb,
+}
+
+/// Options for [`FeatureIter::record_batches`]
+#[derive(Clone, Debug)]
+pub struct ArrowOptions {
+    /// Maximal number of rows per record batch
+    pub batch_size: usize,
+    /// Geometry column encoding
+    pub geometry_encoding: GeometryEncoding,
+}
+
+impl Default for ArrowOptions {
+    fn default() -> Self {
+        ArrowOptions {
+            batch_size: 8192,
+            geometry_encoding: GeometryEncoding::Native,
+        }
+    }
+}
+
+/// Iterator over Arrow record batches of a [`FeatureIter`]
+///
+/// # Usage example:
+///
+/// ```
+/// use flatgeobuf::*;
+/// # use std::fs::File;
+/// # use std::io::BufReader;
+///
+/// # fn read_fbg() -> std::result::Result<(), Box<dyn std::error::Error>> {
+/// let mut filein = BufReader::new(File::open("countries.fgb")?);
+/// let batches = FgbReader::open(&mut filein)?
+///     .select_all_seq()?
+///     .record_batches(ArrowOptions::default())?;
+/// for batch in batches {
+///     let batch = batch?;
+///     println!("{}", batch.num_rows());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct RecordBatchIter<R, S> {
+    iter: FeatureIter<R, S>,
+    builder: BatchBuilder,
+    finished: bool,
+}
+
+impl<R, S> FeatureIter<R, S>
+where
+    FeatureIter<R, S>: FallibleStreamingIterator<Item = FgbFeature, Error = Error>,
+{
+    /// Convert into an iterator of Arrow record batches.
+    ///
+    /// Properties are mapped to fields of the header column types, restricted to the columns
+    /// selected with [`FeatureIter::columns`]. Values which cannot be converted to the column
+    /// type are null. A batch with a missing or null value in a non-nullable column fails with
+    /// [`Error::Arrow`]. The geometry is added as last field named `geometry`.
+    ///
+    /// The schema is fixed by the header columns. Properties of features with their own
+    /// columns (see [`FgbWriterOptions::feature_columns`](crate::FgbWriterOptions)) which are
+    /// not declared in the header are skipped, with a warning logged once per column name.
+    pub fn record_batches(self, options: ArrowOptions) -> Result<RecordBatchIter<R, S>> {
+        let builder = BatchBuilder::new(&self.fbs, options)?;
+        Ok(RecordBatchIter {
+            iter: self,
+            builder,
+            finished: false,
+        })
+    }
+}
+
+impl<R, S> RecordBatchIter<R, S>
+where
+    FeatureIter<R, S>: FallibleStreamingIterator<Item = FgbFeature, Error = Error>,
+{
+    /// Schema of all record batches
+    pub fn schema(&self) -> SchemaRef {
+        self.builder.schema.clone()
+    }
+
+    fn next_batch(&mut self) -> Result<Option<RecordBatch>> {
+        while !self.builder.is_full() {
+            match self.iter.next()? {
+                Some(feature) => self.builder.push(feature)?,
+                None => {
+                    self.finished = true;
+                    break;
+                }
+            }
+        }
+        self.builder.finish()
+    }
+}
+
+impl<R, S> Iterator for RecordBatchIter<R, S>
+where
+    FeatureIter<R, S>: FallibleStreamingIterator<Item = FgbFeature, Error = Error>,
+{
+    type Item = Result<RecordBatch>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let batch = self.next_batch();
+        if batch.is_err() {
+            self.finished = true;
+        }
+        batch.transpose()
+    }
+}
+
+#[cfg(feature = "http")]
+mod http {
+    use super::{ArrowOptions, BatchBuilder};
+    use crate::{AsyncFeatureIter, Result};
+    use arrow_array::RecordBatch;
+    use arrow_schema::SchemaRef;
+    use http_range_client::AsyncHttpRangeClient;
+
+    /// Arrow record batches of an [`AsyncFeatureIter`]
+    pub struct AsyncRecordBatchIter<T: AsyncHttpRangeClient = reqwest::Client> {
+        iter: AsyncFeatureIter<T>,
+        builder: BatchBuilder,
+        finished: bool,
+    }
+
+    impl<T: AsyncHttpRangeClient> AsyncFeatureIter<T> {
+        /// Convert into Arrow record batches.
+        ///
+        /// See [`FeatureIter::record_batches`](crate::FeatureIter::record_batches).
+        pub fn record_batches(self, options: ArrowOptions) -> Result<AsyncRecordBatchIter<T>> {
+            let builder = BatchBuilder::new(self.cur_feature(), options)?;
+            Ok(AsyncRecordBatchIter {
+                iter: self,
+                builder,
+                finished: false,
+            })
+        }
+    }
+
+    impl<T: AsyncHttpRangeClient> AsyncRecordBatchIter<T> {
+        /// Schema of all record batches
+        pub fn schema(&self) -> SchemaRef {
+            self.builder.schema.clone()
+        }
+
+        /// Read next record batch
+        pub async fn next(&mut self) -> Result<Option<RecordBatch>> {
+            if self.finished {
+                return Ok(None);
+            }
+            while !self.builder.is_full() {
+                match self.iter.next().await {
+                    Ok(Some(feature)) => self.builder.push(feature)?,
+                    Ok(None) => {
+                        self.finished = true;
+                        break;
+                    }
+                    Err(e) => {
+                        self.finished = true;
+                        return Err(e);
+                    }
+                }
+            }
+            self.builder.finish()
+        }
+    }
+}
+#[cfg(feature = "http")]
+pub use http::*;
+
+/// Arrow schema of the selected columns of `feature` and the geometry field
+fn arrow_schema(feature: &FgbFeature, geometry_field: Field) -> Schema {
+    let header = feature.header();
+    let mut fields = Vec::new();
+    if let Some(columns) = header.columns() {
+        let indices = match &feature.projection {
+            Some(projection) => projection.column_indices(),
+            None => (0..columns.len()).collect(),
+        };
+        for column_idx in indices {
+            let column = columns.get(column_idx);
+            let mut metadata = HashMap::new();
+            if column.type_() == ColumnType::Json {
+                metadata.insert(EXTENSION_NAME.to_string(), "arrow.json".to_string());
+            }
+            if let Some(description) = column.description() {
+                metadata.insert("description".to_string(), description.to_string());
+            }
+            fields.push(
+                Field::new(column.name(), arrow_type(column.type_()), column.nullable())
+                    .with_metadata(metadata),
+            );
+        }
+    }
+    fields.push(geometry_field);
+    let mut metadata = HashMap::new();
+    if let Some(name) = header.name() {
+        metadata.insert("name".to_string(), name.to_string());
+    }
+    Schema::new_with_metadata(fields, metadata)
+}
+
+/// Arrow data type of property values. Date time values are kept as ISO 8601 strings.
+fn arrow_type(column_type: ColumnType) -> DataType {
+    match column_type {
+        ColumnType::Byte => DataType::Int8,
+        ColumnType::UByte => DataType::UInt8,
+        ColumnType::Bool => DataType::Boolean,
+        ColumnType::Short => DataType::Int16,
+        ColumnType::UShort => DataType::UInt16,
+        ColumnType::Int => DataType::Int32,
+        ColumnType::UInt => DataType::UInt32,
+        ColumnType::Long => DataType::Int64,
+        ColumnType::ULong => DataType::UInt64,
+        ColumnType::Float => DataType::Float32,
+        ColumnType::Double => DataType::Float64,
+        ColumnType::Binary => DataType::Binary,
+        _ => DataType::Utf8,
+    }
+}
+
+/// GeoArrow extension metadata with the header CRS
+fn geoarrow_metadata(header: Header, extension: &str) -> HashMap<String, String> {
+    let crs = header
+        .crs()
+        .filter(|crs| crs.code() != 0)
+        .map(|crs| {
+            format!(
+                r#"{{"crs":"{}:{}","crs_type":"authority_code"}}"#,
+                crs.org().unwrap_or("EPSG"),
+                crs.code()
+            )
+        })
+        .unwrap_or_else(|| "{}".to_string());
+    HashMap::from([
+        (EXTENSION_NAME.to_string(), extension.to_string()),
+        (EXTENSION_METADATA.to_string(), crs),
+    ])
+}
+
+/// Accumulates features into the arrays of a record batch
+pub(crate) struct BatchBuilder {
+    schema: SchemaRef,
+    batch_size: usize,
+    columns: Vec<ColumnBuilder>,
+    geometry: GeometryBuilder,
+    rows: usize,
+    /// Names of skipped properties without column in the schema
+    skipped: HashSet<String>,
+}
+
+impl BatchBuilder {
+    pub(crate) fn new(feature: &FgbFeature, options: ArrowOptions) -> Result<Self> {
+        let header = feature.header();
+        let geometry = GeometryBuilder::new(header, options.geometry_encoding)?;
+        let schema = Arc::new(arrow_schema(feature, geometry.field(header)));
+        let columns = schema.fields()[..schema.fields().len() - 1]
+            .iter()
+            .map(|field| ColumnBuilder::new(field.data_type()))
+            .collect();
+        Ok(BatchBuilder {
+            schema,
+            batch_size: options.batch_size.max(1),
+            columns,
+            geometry,
+            rows: 0,
+            skipped: HashSet::new(),
+        })
+    }
+
+    fn is_full(&self) -> bool {
+        self.rows >= self.batch_size
+    }
+
+    pub(crate) fn push(&mut self, feature: &FgbFeature) -> Result<()> {
+        feature
+            .process_properties(self)
+            .and_then(|_| self.geometry.push(feature))
+            .map_err(|e| Error::IO(std::io::Error::new(std::io::ErrorKind::InvalidData, e)))?;
+        self.rows += 1;
+        for column in &mut self.columns {
+            if column.len() < self.rows {
+                column.append_null();
+            }
+        }
+        Ok(())
+    }
+
+    /// Build a record batch of the pushed features, `None` if empty
+    pub(crate) fn finish(&mut self) -> Result<Option<RecordBatch>> {
+        if self.rows == 0 {
+            return Ok(None);
+        }
+        self.rows = 0;
+        let mut arrays = self
+            .columns
+            .iter_mut()
+            .map(ColumnBuilder::finish)
+            .collect::<Vec<_>>();
+        arrays.push(self.geometry.finish()?);
+        Ok(Some(RecordBatch::try_new(self.schema.clone(), arrays)?))
+    }
+}
+
+impl PropertyProcessor for BatchBuilder {
+    fn property(
+        &mut self,
+        i: usize,
+        colname: &str,
+        colval: &ColumnValue,
+    ) -> geozero::error::Result<bool> {
+        // Indices of features with their own columns differ from the schema
+        let fields = &self.schema.fields()[..self.columns.len()];
+        let idx = match fields.get(i) {
+            Some(field) if field.name() == colname => Some(i),
+            _ => fields.iter().position(|field| field.name() == colname),
+        };
+        match idx.and_then(|idx| self.columns.get_mut(idx)) {
+            // Ignore repeated properties
+            Some(column) if column.len() == self.rows => column.append(colval),
+            Some(_) => {}
+            None => {
+                if !self.skipped.contains(colname) {
+                    warn!("Property `{colname}` is not a column of the header - skipping");
+                    self.skipped.insert(colname.to_string());
+                }
+            }
+        }
+        Ok(false)
+    }
+}
+
+/// Array builder of a property column
+enum ColumnBuilder {
+    Byte(Int8Builder),
+    UByte(UInt8Builder),
+    Bool(BooleanBuilder),
+    Short(Int16Builder),
+    UShort(UInt16Builder),
+    Int(Int32Builder),
+    UInt(UInt32Builder),
+    Long(Int64Builder),
+    ULong(UInt64Builder),
+    Float(Float32Builder),
+    Double(Float64Builder),
+    String(StringBuilder),
+    Binary(BinaryBuilder),
+}
+
+macro_rules! with_builder {
+    ($column:expr, $builder:ident => $e:expr) => {
+        match $column {
+            ColumnBuilder::Byte($builder) => $e,
+            ColumnBuilder::UByte($builder) => $e,
+            ColumnBuilder::Bool($builder) => $e,
+            ColumnBuilder::Short($builder) => $e,
+            ColumnBuilder::UShort($builder) => $e,
+            ColumnBuilder::Int($builder) => $e,
+            ColumnBuilder::UInt($builder) => $e,
+            ColumnBuilder::Long($builder) => $e,
+            ColumnBuilder::ULong($builder) => $e,
+            ColumnBuilder::Float($builder) => $e,
+            ColumnBuilder::Double($builder) => $e,
+            ColumnBuilder::String($builder) => $e,
+            ColumnBuilder::Binary($builder) => $e,
+        }
+    };
+}
+
+impl ColumnBuilder {
+    fn new(data_type: &DataType) -> Self {
+        match data_type {
+            DataType::Int8 => ColumnBuilder::Byte(Int8Builder::new()),
+            DataType::UInt8 => ColumnBuilder::UByte(UInt8Builder::new()),
+            DataType::Boolean => ColumnBuilder::Bool(BooleanBuilder::new()),
+            DataType::Int16 => ColumnBuilder::Short(Int16Builder::new()),
+            DataType::UInt16 => ColumnBuilder::UShort(UInt16Builder::new()),
+            DataType::Int32 => ColumnBuilder::Int(Int32Builder::new()),
+            DataType::UInt32 => ColumnBuilder::UInt(UInt32Builder::new()),
+            DataType::Int64 => ColumnBuilder::Long(Int64Builder::new()),
+            DataType::UInt64 => ColumnBuilder::ULong(UInt64Builder::new()),
+            DataType::Float32 => ColumnBuilder::Float(Float32Builder::new()),
+            DataType::Float64 => ColumnBuilder::Double(Float64Builder::new()),
+            DataType::Binary => ColumnBuilder::Binary(BinaryBuilder::new()),
+            _ => ColumnBuilder::String(StringBuilder::new()),
+        }
+    }
+
+    fn column_type(&self) -> ColumnType {
+        match self {
+            ColumnBuilder::Byte(_) => ColumnType::Byte,
+            ColumnBuilder::UByte(_) => ColumnType::UByte,
+            ColumnBuilder::Bool(_) => ColumnType::Bool,
+            ColumnBuilder::Short(_) => ColumnType::Short,
+            ColumnBuilder::UShort(_) => ColumnType::UShort,
+            ColumnBuilder::Int(_) => ColumnType::Int,
+            ColumnBuilder::UInt(_) => ColumnType::UInt,
+            ColumnBuilder::Long(_) => ColumnType::Long,
+            ColumnBuilder::ULong(_) => ColumnType::ULong,
+            ColumnBuilder::Float(_) => ColumnType::Float,
+            ColumnBuilder::Double(_) => ColumnType::Double,
+            ColumnBuilder::String(_) => ColumnType::String,
+            ColumnBuilder::Binary(_) => ColumnType::Binary,
+        }
+    }
+
+    fn len(&self) -> usize {
+        with_builder!(self, builder => builder.len())
+    }
+
+    fn append_null(&mut self) {
+        with_builder!(self, builder => builder.append_null())
+    }
+
+    /// Append a value, converted to the column type if required. Values without lossless
+    /// conversion are appended as null.
+    fn append(&mut self, colval: &ColumnValue) {
+        if !self.append_value(colval) {
+            match convert_value(colval, self.column_type()) {
+                Some(converted) if self.append_value(&converted) => {}
+                _ => self.append_null(),
+            }
+        }
+    }
+
+    fn append_value(&mut self, colval: &ColumnValue) -> bool {
+        match (self, colval) {
+            (ColumnBuilder::Byte(b), ColumnValue::Byte(v)) => b.append_value(*v),
+            (ColumnBuilder::UByte(b), ColumnValue::UByte(v)) => b.append_value(*v),
+            (ColumnBuilder::Bool(b), ColumnValue::Bool(v)) => b.append_value(*v),
+            (ColumnBuilder::Short(b), ColumnValue::Short(v)) => b.append_value(*v),
+            (ColumnBuilder::UShort(b), ColumnValue::UShort(v)) => b.append_value(*v),
+            (ColumnBuilder::Int(b), ColumnValue::Int(v)) => b.append_value(*v),
+            (ColumnBuilder::UInt(b), ColumnValue::UInt(v)) => b.append_value(*v),
+            (ColumnBuilder::Long(b), ColumnValue::Long(v)) => b.append_value(*v),
+            (ColumnBuilder::ULong(b), ColumnValue::ULong(v)) => b.append_value(*v),
+            (ColumnBuilder::Float(b), ColumnValue::Float(v)) => b.append_value(*v),
+            (ColumnBuilder::Double(b), ColumnValue::Double(v)) => b.append_value(*v),
+            (
+                ColumnBuilder::String(b),
+                ColumnValue::String(v) | ColumnValue::Json(v) | ColumnValue::DateTime(v),
+            ) => b.append_value(v),
+            (ColumnBuilder::Binary(b), ColumnValue::Binary(v)) => b.append_value(v),
+            _ => return false,
+        }
+        true
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        with_builder!(self, builder => Arc::new(builder.finish()))
+    }
+}
+
+/// Array builder of the geometry column
+enum GeometryBuilder {
+    Native(NativeBuilder),
+    Wkb {
+        dims: CoordDimensions,
+        builder: BinaryBuilder,
+    },
+}
+
+impl GeometryBuilder {
+    fn new(header: Header, encoding: GeometryEncoding) -> Result<Self> {
+        let dims = CoordDimensions {
+            z: header.has_z(),
+            m: header.has_m(),
+            t: false,
+            tm: false,
+        };
+        Ok(match encoding {
+            GeometryEncoding::Native => {
+                GeometryBuilder::Native(NativeBuilder::new(header.geometry_type(), dims)?)
+            }
+            GeometryEncoding::Wkb => GeometryBuilder::Wkb {
+                dims,
+                builder: BinaryBuilder::new(),
+            },
+        })
+    }
+
+    fn field(&self, header: Header) -> Field {
+        match self {
+            GeometryBuilder::Native(native) => native.field(header),
+            GeometryBuilder::Wkb { .. } => Field::new("geometry", DataType::Binary, true)
+                .with_metadata(geoarrow_metadata(header, "geoarrow.wkb")),
+        }
+    }
+
+    fn push(&mut self, feature: &FgbFeature) -> geozero::error::Result<()> {
+        match self {
+            GeometryBuilder::Native(native) => native.push(feature),
+            GeometryBuilder::Wkb { dims, builder } => {
+                if feature.geometry().is_none() {
+                    builder.append_null();
+                    return Ok(());
+                }
+                let mut wkb = Vec::new();
+                let mut writer =
+                    WkbWriter::with_opts(&mut wkb, WkbDialect::Wkb, *dims, None, Vec::new());
+                feature.process_geom(&mut writer)?;
+                builder.append_value(wkb);
+                Ok(())
+            }
+        }
+    }
+
+    fn finish(&mut self) -> Result<ArrayRef> {
+        match self {
+            GeometryBuilder::Native(native) => native.finish(),
+            GeometryBuilder::Wkb { builder, .. } => Ok(Arc::new(builder.finish())),
+        }
+    }
+}
+
+/// GeoArrow native geometry builder with interleaved coordinates
+struct NativeBuilder {
+    geometry_type: GeometryType,
+    dims: CoordDimensions,
+    /// Field names of the nested lists, outermost first
+    list_names: &'static [&'static str],
+    coords: Vec<f64>,
+    /// Offsets of the nested lists, outermost first
+    offsets: Vec<Vec<i32>>,
+    validity: Vec<bool>,
+}
+
+impl NativeBuilder {
+    fn new(geometry_type: GeometryType, dims: CoordDimensions) -> Result<Self> {
+        let list_names: &[&str] = match geometry_type {
+            GeometryType::Point => &[],
+            GeometryType::LineString => &["vertices"],
+            GeometryType::MultiPoint => &["points"],
+            GeometryType::Polygon => &["rings", "vertices"],
+            GeometryType::MultiLineString => &["linestrings", "vertices"],
+            GeometryType::MultiPolygon => &["polygons", "rings", "vertices"],
+            _ => {
+                return Err(Error::UnsupportedGeometryType(format!(
+                    "No GeoArrow native encoding for geometry type {geometry_type:?}, use GeometryEncoding::Wkb"
+                )))
+            }
+        };
+        Ok(NativeBuilder {
+            geometry_type,
+            dims,
+            list_names,
+            coords: Vec::new(),
+            offsets: vec![vec![0]; list_names.len()],
+            validity: Vec::new(),
+        })
+    }
+
+    fn dim(&self) -> usize {
+        2 + self.dims.z as usize + self.dims.m as usize
+    }
+
+    fn coord_field(&self) -> Field {
+        let name = match (self.dims.z, self.dims.m) {
+            (false, false) => "xy",
+            (true, false) => "xyz",
+            (false, true) => "xym",
+            (true, true) => "xyzm",
+        };
+        Field::new(name, DataType::Float64, false)
+    }
+
+    /// Data type of the coordinates and the nested lists, from level `level` inwards
+    fn data_type(&self, level: usize) -> DataType {
+        match self.list_names.get(level) {
+            Some(name) => DataType::List(Arc::new(Field::new(
+                *name,
+                self.data_type(level + 1),
+                false,
+            ))),
+            None => DataType::FixedSizeList(Arc::new(self.coord_field()), self.dim() as i32),
+        }
+    }
+
+    fn field(&self, header: Header) -> Field {
+        let extension = match self.geometry_type {
+            GeometryType::Point => "geoarrow.point",
+            GeometryType::LineString => "geoarrow.linestring",
+            GeometryType::Polygon => "geoarrow.polygon",
+            GeometryType::MultiPoint => "geoarrow.multipoint",
+            GeometryType::MultiLineString => "geoarrow.multilinestring",
+            _ => "geoarrow.multipolygon",
+        };
+        Field::new("geometry", self.data_type(0), true)
+            .with_metadata(geoarrow_metadata(header, extension))
+    }
+
+    fn push(&mut self, feature: &FgbFeature) -> geozero::error::Result<()> {
+        if feature.geometry().is_some() {
+            feature.process_geom(self)?;
+            self.validity.push(true);
+        } else {
+            if self.list_names.is_empty() {
+                let dim = self.dim();
+                self.coords.extend(std::iter::repeat_n(f64::NAN, dim));
+            } else {
+                self.end_list(0);
+            }
+            self.validity.push(false);
+        }
+        Ok(())
+    }
+
+    /// Close the current list of nesting level `level`
+    fn end_list(&mut self, level: usize) {
+        let len = match self.offsets.get(level + 1) {
+            Some(inner) => inner.len() - 1,
+            None => self.coords.len() / self.dim(),
+        };
+        self.offsets[level].push(len as i32);
+    }
+
+    /// Close the current list `depth` levels above the coordinates
+    fn end_inner_list(&mut self, depth: usize) -> geozero::error::Result<()> {
+        let level = self
+            .list_names
+            .len()
+            .checked_sub(depth)
+            .ok_or(GeozeroError::GeometryFormat)?;
+        self.end_list(level);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<ArrayRef> {
+        let coords = Arc::new(Float64Array::from(std::mem::take(&mut self.coords)));
+        let validity = NullBuffer::from(std::mem::take(&mut self.validity));
+        let nulls = Some(validity).filter(|nulls| nulls.null_count() > 0);
+        let depth = self.list_names.len();
+        let mut array: ArrayRef = Arc::new(FixedSizeListArray::try_new(
+            Arc::new(self.coord_field()),
+            self.dim() as i32,
+            coords,
+            if depth == 0 { nulls.clone() } else { None },
+        )?);
+        for level in (0..depth).rev() {
+            let offsets = std::mem::replace(&mut self.offsets[level], vec![0]);
+            let field = Field::new(self.list_names[level], self.data_type(level + 1), false);
+            array = Arc::new(ListArray::try_new(
+                Arc::new(field),
+                OffsetBuffer::new(ScalarBuffer::from(offsets)),
+                array,
+                if level == 0 { nulls.clone() } else { None },
+            )?);
+        }
+        Ok(array)
+    }
+}
+
+impl GeomProcessor for NativeBuilder {
+    fn dimensions(&self) -> CoordDimensions {
+        self.dims
+    }
+    fn xy(&mut self, x: f64, y: f64, _idx: usize) -> geozero::error::Result<()> {
+        self.coords.extend([x, y]);
+        Ok(())
+    }
+    fn coordinate(
+        &mut self,
+        x: f64,
+        y: f64,
+        z: Option<f64>,
+        m: Option<f64>,
+        _t: Option<f64>,
+        _tm: Option<u64>,
+        _idx: usize,
+    ) -> geozero::error::Result<()> {
+        self.coords.extend([x, y]);
+        if self.dims.z {
+            self.coords.push(z.unwrap_or(f64::NAN));
+        }
+        if self.dims.m {
+            self.coords.push(m.unwrap_or(f64::NAN));
+        }
+        Ok(())
+    }
+    fn multipoint_end(&mut self, _idx: usize) -> geozero::error::Result<()> {
+        self.end_inner_list(1)
+    }
+    fn linestring_end(&mut self, _tagged: bool, _idx: usize) -> geozero::error::Result<()> {
+        self.end_inner_list(1)
+    }
+    fn multilinestring_end(&mut self, _idx: usize) -> geozero::error::Result<()> {
+        self.end_inner_list(2)
+    }
+    fn polygon_end(&mut self, _tagged: bool, _idx: usize) -> geozero::error::Result<()> {
+        self.end_inner_list(2)
+    }
+    fn multipolygon_end(&mut self, _idx: usize) -> geozero::error::Result<()> {
+        self.end_inner_list(3)
+    }
+}
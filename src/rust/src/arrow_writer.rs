@@ -0,0 +1,420 @@
+//! Conversion of Arrow record batches into FlatGeobuf features.
+
+use crate::error::{Error, Result, SchemaError};
+use crate::feature_writer::{convert_value, FeatureWriter};
+use crate::file_writer::ColumnDef;
+use crate::header_generated::{ColumnType, GeometryType};
+use arrow_array::cast::AsArray;
+use arrow_array::types::{
+    Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, Int8Type, UInt16Type, UInt32Type,
+    UInt64Type, UInt8Type,
+};
+use arrow_array::{Array, ArrayRef, ListArray, RecordBatch};
+use arrow_schema::{ArrowError, DataType, Field};
+use geozero::wkb::Wkb;
+use geozero::{ColumnValue, GeomProcessor, GeozeroGeometry, PropertyProcessor};
+use std::ops::Range;
+
+/// Features of a record batch, with a GeoArrow or WKB geometry column
+pub(crate) struct ArrowFeatures<'b> {
+    geometry: GeometryColumn<'b>,
+    /// Property arrays with field and column type
+    properties: Vec<(&'b ArrayRef, &'b Field, ColumnType)>,
+    /// Types of the declared columns the properties are written to
+    column_types: Vec<ColumnType>,
+}
+
+impl<'b> ArrowFeatures<'b> {
+    /// The geometry column is the first field with a GeoArrow extension type, or a binary field
+    /// named `geometry` with WKB values.
+    pub(crate) fn new(batch: &'b RecordBatch) -> Result<Self> {
+        let schema = batch.schema_ref();
+        let geometry_idx = schema
+            .fields()
+            .iter()
+            .position(|field| {
+                field
+                    .extension_type_name()
+                    .is_some_and(|name| name.starts_with("geoarrow."))
+            })
+            .or_else(|| {
+                schema.fields().iter().position(|field| {
+                    field.name() == "geometry"
+                        && matches!(
+                            field.data_type(),
+                            DataType::Binary | DataType::LargeBinary | DataType::BinaryView
+                        )
+                })
+            })
+            .ok_or_else(|| invalid_argument("No GeoArrow or WKB geometry column".to_string()))?;
+        let geometry = GeometryColumn::new(
+            batch.column(geometry_idx),
+            schema.field(geometry_idx).extension_type_name(),
+        )?;
+        let properties = schema
+            .fields()
+            .iter()
+            .zip(batch.columns())
+            .enumerate()
+            .filter(|(idx, _)| *idx != geometry_idx)
+            .map(|(_, (field, array))| Ok((array, field.as_ref(), column_type(field)?)))
+            .collect::<Result<Vec<_>>>()?;
+        let column_types = properties.iter().map(|(_, _, type_)| *type_).collect();
+        Ok(ArrowFeatures {
+            geometry,
+            properties,
+            column_types,
+        })
+    }
+
+    /// Match the property fields by position with the declared columns.
+    ///
+    /// Fails if the number of fields or a field name differs. Values of fields with a differing
+    /// type are converted to the column type when written.
+    pub(crate) fn match_columns(&mut self, columns: &[ColumnDef]) -> Result<()> {
+        if self.properties.len() != columns.len() {
+            return Err(invalid_argument(format!(
+                "Expected {} property fields, found {}",
+                columns.len(),
+                self.properties.len()
+            )));
+        }
+        for (i, ((_, field, _), column)) in self.properties.iter().zip(columns).enumerate() {
+            if field.name() != &column.name {
+                return Err(Error::Schema(SchemaError::ColumnName {
+                    index: i,
+                    expected: column.name.clone(),
+                    found: field.name().clone(),
+                }));
+            }
+        }
+        self.column_types = columns.iter().map(|column| column.type_).collect();
+        Ok(())
+    }
+
+    /// Fields and column types of the properties
+    pub(crate) fn columns(&self) -> impl Iterator<Item = (&'b Field, ColumnType)> + '_ {
+        self.properties
+            .iter()
+            .map(|(_, field, column_type)| (*field, *column_type))
+    }
+
+    /// Process geometry and properties of a row.
+    ///
+    /// Fails with [`SchemaError::ColumnType`] for a value which cannot be converted to the type
+    /// of its column.
+    pub(crate) fn process_row(&self, row: usize, writer: &mut FeatureWriter) -> Result<()> {
+        self.geometry
+            .process(row, writer)
+            .map_err(|e| Error::IO(std::io::Error::new(std::io::ErrorKind::InvalidData, e)))?;
+        for (i, ((array, field, value_type), column_type)) in
+            self.properties.iter().zip(&self.column_types).enumerate()
+        {
+            let Some(value) = column_value(array, row, *value_type) else {
+                continue;
+            };
+            let value = if value_type == column_type {
+                value
+            } else {
+                convert_value(&value, *column_type).ok_or_else(|| {
+                    Error::Schema(SchemaError::ColumnType {
+                        column: field.name().clone(),
+                        expected: *column_type,
+                        found: *value_type,
+                    })
+                })?
+            };
+            writer
+                .property(i, field.name(), &value)
+                .map_err(|e| Error::IO(std::io::Error::new(std::io::ErrorKind::InvalidData, e)))?;
+        }
+        Ok(())
+    }
+}
+
+fn invalid_argument(msg: String) -> Error {
+    Error::Arrow(ArrowError::InvalidArgumentError(msg))
+}
+
+/// Column type of an Arrow field
+fn column_type(field: &Field) -> Result<ColumnType> {
+    Ok(match field.data_type() {
+        DataType::Int8 => ColumnType::Byte,
+        DataType::UInt8 => ColumnType::UByte,
+        DataType::Boolean => ColumnType::Bool,
+        DataType::Int16 => ColumnType::Short,
+        DataType::UInt16 => ColumnType::UShort,
+        DataType::Int32 => ColumnType::Int,
+        DataType::UInt32 => ColumnType::UInt,
+        DataType::Int64 => ColumnType::Long,
+        DataType::UInt64 => ColumnType::ULong,
+        DataType::Float32 => ColumnType::Float,
+        DataType::Float64 => ColumnType::Double,
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View
+            if field.extension_type_name() == Some("arrow.json") =>
+        {
+            ColumnType::Json
+        }
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View => ColumnType::String,
+        DataType::Binary | DataType::LargeBinary | DataType::BinaryView => ColumnType::Binary,
+        data_type => {
+            return Err(invalid_argument(format!(
+                "Unsupported data type {data_type} of field `{}`",
+                field.name()
+            )))
+        }
+    })
+}
+
+/// Property value of a row, `None` if null
+fn column_value(array: &ArrayRef, row: usize, column_type: ColumnType) -> Option<ColumnValue<'_>> {
+    if array.is_null(row) {
+        return None;
+    }
+    let string = |v| match column_type {
+        ColumnType::Json => ColumnValue::Json(v),
+        _ => ColumnValue::String(v),
+    };
+    Some(match array.data_type() {
+        DataType::Int8 => ColumnValue::Byte(array.as_primitive::<Int8Type>().value(row)),
+        DataType::UInt8 => ColumnValue::UByte(array.as_primitive::<UInt8Type>().value(row)),
+        DataType::Boolean => ColumnValue::Bool(array.as_boolean().value(row)),
+        DataType::Int16 => ColumnValue::Short(array.as_primitive::<Int16Type>().value(row)),
+        DataType::UInt16 => ColumnValue::UShort(array.as_primitive::<UInt16Type>().value(row)),
+        DataType::Int32 => ColumnValue::Int(array.as_primitive::<Int32Type>().value(row)),
+        DataType::UInt32 => ColumnValue::UInt(array.as_primitive::<UInt32Type>().value(row)),
+        DataType::Int64 => ColumnValue::Long(array.as_primitive::<Int64Type>().value(row)),
+        DataType::UInt64 => ColumnValue::ULong(array.as_primitive::<UInt64Type>().value(row)),
+        DataType::Float32 => ColumnValue::Float(array.as_primitive::<Float32Type>().value(row)),
+        DataType::Float64 => ColumnValue::Double(array.as_primitive::<Float64Type>().value(row)),
+        DataType::Utf8 => string(array.as_string::<i32>().value(row)),
+        DataType::LargeUtf8 => string(array.as_string::<i64>().value(row)),
+        DataType::Utf8View => string(array.as_string_view().value(row)),
+        DataType::Binary => ColumnValue::Binary(array.as_binary::<i32>().value(row)),
+        DataType::LargeBinary => ColumnValue::Binary(array.as_binary::<i64>().value(row)),
+        DataType::BinaryView => ColumnValue::Binary(array.as_binary_view().value(row)),
+        _ => return None,
+    })
+}
+
+/// Geometry column
+enum GeometryColumn<'b> {
+    Native {
+        geometry_type: GeometryType,
+        array: &'b ArrayRef,
+        /// Nested lists, outermost first
+        lists: Vec<&'b ListArray>,
+        coords: Coords<'b>,
+    },
+    Wkb(&'b ArrayRef),
+}
+
+impl<'b> GeometryColumn<'b> {
+    fn new(array: &'b ArrayRef, extension: Option<&str>) -> Result<Self> {
+        let (geometry_type, depth) = match extension {
+            Some("geoarrow.point") => (GeometryType::Point, 0),
+            Some("geoarrow.linestring") => (GeometryType::LineString, 1),
+            Some("geoarrow.polygon") => (GeometryType::Polygon, 2),
+            Some("geoarrow.multipoint") => (GeometryType::MultiPoint, 1),
+            Some("geoarrow.multilinestring") => (GeometryType::MultiLineString, 2),
+            Some("geoarrow.multipolygon") => (GeometryType::MultiPolygon, 3),
+            Some("geoarrow.wkb") | None => return Ok(GeometryColumn::Wkb(array)),
+            Some(extension) => {
+                return Err(invalid_argument(format!(
+                    "Unsupported geometry extension type {extension}"
+                )))
+            }
+        };
+        let mut lists = Vec::with_capacity(depth);
+        let mut values = array;
+        for _ in 0..depth {
+            let list = values.as_list_opt::<i32>().ok_or_else(|| {
+                invalid_argument(format!(
+                    "Unexpected data type {} of GeoArrow geometry",
+                    values.data_type()
+                ))
+            })?;
+            lists.push(list);
+            values = list.values();
+        }
+        Ok(GeometryColumn::Native {
+            geometry_type,
+            array,
+            lists,
+            coords: Coords::new(values)?,
+        })
+    }
+
+    fn process(&self, row: usize, writer: &mut FeatureWriter) -> geozero::error::Result<()> {
+        let (geometry_type, array, lists, coords) = match self {
+            GeometryColumn::Wkb(array) => {
+                let wkb = match array.data_type() {
+                    _ if array.is_null(row) => None,
+                    DataType::LargeBinary => Some(array.as_binary::<i64>().value(row)),
+                    DataType::BinaryView => Some(array.as_binary_view().value(row)),
+                    _ => array.as_binary_opt::<i32>().map(|array| array.value(row)),
+                };
+                return match wkb {
+                    Some(wkb) => Wkb(wkb).process_geom(writer),
+                    None => {
+                        writer.null_geometry();
+                        Ok(())
+                    }
+                };
+            }
+            GeometryColumn::Native {
+                geometry_type,
+                array,
+                lists,
+                coords,
+            } => (*geometry_type, array, lists, coords),
+        };
+        if array.is_null(row) {
+            writer.null_geometry();
+            return Ok(());
+        }
+        match geometry_type {
+            GeometryType::Point => {
+                writer.point_begin(0)?;
+                coords.extend(row..row + 1, writer);
+                writer.point_end(0)?;
+            }
+            GeometryType::LineString => {
+                let range = list_range(lists[0], row);
+                writer.linestring_begin(true, range.len(), 0)?;
+                coords.extend(range, writer);
+                writer.linestring_end(true, 0)?;
+            }
+            GeometryType::MultiPoint => {
+                let range = list_range(lists[0], row);
+                writer.multipoint_begin(range.len(), 0)?;
+                coords.extend(range, writer);
+                writer.multipoint_end(0)?;
+            }
+            GeometryType::Polygon => {
+                let rings = list_range(lists[0], row);
+                writer.polygon_begin(true, rings.len(), 0)?;
+                coords.extend_parts(lists[1], rings, writer)?;
+                writer.polygon_end(true, 0)?;
+            }
+            GeometryType::MultiLineString => {
+                let parts = list_range(lists[0], row);
+                writer.multilinestring_begin(parts.len(), 0)?;
+                coords.extend_parts(lists[1], parts, writer)?;
+                writer.multilinestring_end(0)?;
+            }
+            _ => {
+                let polygons = list_range(lists[0], row);
+                writer.multipolygon_begin(polygons.len(), 0)?;
+                for (idx, polygon) in polygons.enumerate() {
+                    let rings = list_range(lists[1], polygon);
+                    writer.polygon_begin(false, rings.len(), idx)?;
+                    coords.extend_parts(lists[2], rings, writer)?;
+                    writer.polygon_end(false, idx)?;
+                }
+                writer.multipolygon_end(0)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn list_range(list: &ListArray, idx: usize) -> Range<usize> {
+    let offsets = list.value_offsets();
+    offsets[idx] as usize..offsets[idx + 1] as usize
+}
+
+/// GeoArrow coordinates
+enum Coords<'b> {
+    /// `FixedSizeList` with Z and M ordinate positions
+    Interleaved {
+        values: &'b [f64],
+        dim: usize,
+        z: Option<usize>,
+        m: Option<usize>,
+    },
+    /// `Struct` of ordinate arrays
+    Separated {
+        x: &'b [f64],
+        y: &'b [f64],
+        z: Option<&'b [f64]>,
+        m: Option<&'b [f64]>,
+    },
+}
+
+impl<'b> Coords<'b> {
+    fn new(array: &'b ArrayRef) -> Result<Self> {
+        let unexpected = || {
+            invalid_argument(format!(
+                "Unexpected data type {} of GeoArrow coordinates",
+                array.data_type()
+            ))
+        };
+        let ordinates = |array: &'b ArrayRef| {
+            array
+                .as_primitive_opt::<Float64Type>()
+                .map(|array| array.values().as_ref())
+                .ok_or_else(unexpected)
+        };
+        match array.data_type() {
+            DataType::FixedSizeList(field, dim) => {
+                let dim = *dim as usize;
+                let (z, m) = match (field.name().as_str(), dim) {
+                    ("xym", 3) => (None, Some(2)),
+                    (_, 3) => (Some(2), None),
+                    (_, 4) => (Some(2), Some(3)),
+                    (_, 2) => (None, None),
+                    _ => return Err(unexpected()),
+                };
+                Ok(Coords::Interleaved {
+                    values: ordinates(array.as_fixed_size_list().values())?,
+                    dim,
+                    z,
+                    m,
+                })
+            }
+            DataType::Struct(_) => {
+                let array = array.as_struct();
+                let ordinate = |name| array.column_by_name(name).map(ordinates).transpose();
+                Ok(Coords::Separated {
+                    x: ordinate("x")?.ok_or_else(unexpected)?,
+                    y: ordinate("y")?.ok_or_else(unexpected)?,
+                    z: ordinate("z")?,
+                    m: ordinate("m")?,
+                })
+            }
+            _ => Err(unexpected()),
+        }
+    }
+
+    /// Append the coordinates in `range`
+    fn extend(&self, range: Range<usize>, writer: &mut FeatureWriter) {
+        match self {
+            Coords::Interleaved { values, dim, z, m } => {
+                writer.extend_interleaved(&values[range.start * dim..range.end * dim], *dim, *z, *m)
+            }
+            Coords::Separated { x, y, z, m } => writer.extend_separated(
+                &x[range.clone()],
+                &y[range.clone()],
+                z.map(|z| &z[range.clone()]),
+                m.map(|m| &m[range]),
+            ),
+        }
+    }
+
+    /// Append the coordinates of rings or line strings
+    fn extend_parts(
+        &self,
+        list: &ListArray,
+        parts: Range<usize>,
+        writer: &mut FeatureWriter,
+    ) -> geozero::error::Result<()> {
+        for (idx, part) in parts.enumerate() {
+            let range = list_range(list, part);
+            writer.linestring_begin(false, range.len(), idx)?;
+            self.extend(range, writer);
+            writer.linestring_end(false, idx)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,347 @@
+use crate::feature_generated::*;
+use crate::file_index::FgbIndex;
+use crate::file_reader::check_feature_size;
+use crate::geometry_predicates::{SpatialFilter, SpatialPredicate};
+use crate::header_generated::*;
+use crate::packed_r_tree::{PackedRTree, SearchResultItem};
+use crate::properties_reader::{FgbFeature, Projection};
+use crate::property_filter::{Expr, PropertyFilter};
+use crate::{check_magic_bytes, HEADER_MAX_BUFFER_SIZE};
+use crate::{Error, Result};
+use geo_traits::{GeometryTrait, PointTrait};
+use std::io::{self, SeekFrom};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
+
+/// FlatGeobuf dataset reader on an async stream, like a `tokio::fs::File`
+pub struct AsyncFgbReader<R> {
+    reader: R,
+    // feature reading requires header access, therefore
+    // header_buf is included in the FgbFeature struct.
+    fbs: FgbFeature,
+    /// Index replacing the index of the dataset
+    external_index: Option<FgbIndex>,
+}
+
+pub struct AsyncFgbFeatureIter<R> {
+    reader: R,
+    // feature reading requires header access, therefore
+    // header_buf is included in the FgbFeature struct.
+    fbs: FgbFeature,
+    /// Selected features or None if no bbox filter
+    item_filter: Option<Vec<SearchResultItem>>,
+    /// Exact geometry test applied to the selected features
+    spatial_filter: Option<SpatialFilter>,
+    /// Property filter applied to the selected features
+    property_filter: Option<PropertyFilter>,
+    /// Number of selected features (None for undefined feature count)
+    count: Option<usize>,
+    /// Current feature number
+    feat_no: usize,
+    /// File offset within feature section
+    cur_pos: u64,
+    finished: bool,
+}
+
+impl<R: AsyncRead + AsyncSeek + Unpin> AsyncFgbReader<R> {
+    /// Open dataset by reading the header information
+    pub async fn open(mut reader: R) -> Result<AsyncFgbReader<R>> {
+        let mut magic_buf: [u8; 8] = [0; 8];
+        reader.read_exact(&mut magic_buf).await?;
+        if !check_magic_bytes(&magic_buf) {
+            return Err(Error::MissingMagicBytes);
+        }
+
+        let mut size_buf: [u8; 4] = [0; 4];
+        reader.read_exact(&mut size_buf).await?;
+        let header_size = u32::from_le_bytes(size_buf) as usize;
+        if header_size > HEADER_MAX_BUFFER_SIZE || header_size < 8 {
+            // minimum size check avoids panic in FlatBuffers header decoding
+            return Err(Error::IllegalHeaderSize(header_size));
+        }
+        let mut header_buf = Vec::with_capacity(header_size + 4);
+        header_buf.extend_from_slice(&size_buf);
+        header_buf.resize(header_buf.capacity(), 0);
+        reader.read_exact(&mut header_buf[4..]).await?;
+
+        // verify flatbuffer
+        let _header = size_prefixed_root_as_header(&header_buf)?;
+
+        Ok(AsyncFgbReader {
+            reader,
+            fbs: FgbFeature {
+                header_buf,
+                feature_buf: Vec::new(),
+                projection: None,
+                explicit_nulls: false,
+            },
+            external_index: None,
+        })
+    }
+
+    /// Use an index built with [`FgbIndex`] for bbox queries, e.g. for datasets without index.
+    ///
+    /// Fails if the index does not match the features count of the header.
+    pub fn with_index(mut self, index: FgbIndex) -> Result<Self> {
+        index.check_features_count(self.fbs.header())?;
+        self.external_index = Some(index);
+        Ok(self)
+    }
+
+    /// Header information
+    pub fn header(&self) -> Header<'_> {
+        self.fbs.header()
+    }
+
+    fn header_len(&self) -> u64 {
+        8 + self.fbs.header_buf.len() as u64
+    }
+
+    /// Offset of the first feature, after the index of the dataset
+    fn feature_begin(&self) -> Result<u64> {
+        let header = self.fbs.header();
+        let features_count = header.features_count() as usize;
+        let index_size = if header.index_node_size() > 0 && features_count > 0 {
+            PackedRTree::validate_num_items(features_count)?;
+            PackedRTree::index_size(features_count, header.index_node_size()) as u64
+        } else {
+            0
+        };
+        Ok(self.header_len() + index_size)
+    }
+
+    /// Select all features.
+    pub async fn select_all(mut self) -> Result<AsyncFgbFeatureIter<R>> {
+        // skip index
+        let feature_begin = self.feature_begin()?;
+        self.reader.seek(SeekFrom::Start(feature_begin)).await?;
+        Ok(AsyncFgbFeatureIter::new(self.reader, self.fbs, None))
+    }
+
+    /// Select features within a bounding box.
+    pub async fn select_bbox(
+        mut self,
+        min_x: f64,
+        min_y: f64,
+        max_x: f64,
+        max_y: f64,
+    ) -> Result<AsyncFgbFeatureIter<R>> {
+        if let Some(index) = self.external_index.take() {
+            let list = index.search(min_x, min_y, max_x, max_y)?;
+            let feature_begin = self.feature_begin()?;
+            self.reader.seek(SeekFrom::Start(feature_begin)).await?;
+            return Ok(AsyncFgbFeatureIter::new(self.reader, self.fbs, Some(list)));
+        }
+        // Read R-Tree index and build filter for features within bbox
+        let header = self.fbs.header();
+        if header.index_node_size() == 0 || header.features_count() == 0 {
+            return Err(Error::NoIndex);
+        }
+        let index_begin = self.header_len();
+        self.reader.seek(SeekFrom::Start(index_begin)).await?;
+        let header = self.fbs.header();
+        let list = PackedRTree::async_stream_search(
+            &mut self.reader,
+            header.features_count() as usize,
+            header.index_node_size(),
+            min_x,
+            min_y,
+            max_x,
+            max_y,
+        )
+        .await?;
+        Ok(AsyncFgbFeatureIter::new(self.reader, self.fbs, Some(list)))
+    }
+
+    /// Select features intersecting a geometry.
+    ///
+    /// See [`FgbReader::select_intersecting`](crate::FgbReader::select_intersecting).
+    pub async fn select_intersecting(
+        self,
+        geom: &impl GeometryTrait<T = f64>,
+    ) -> Result<AsyncFgbFeatureIter<R>> {
+        self.select_spatial(SpatialFilter::new(SpatialPredicate::Intersects, geom)?)
+            .await
+    }
+
+    /// Select features within a polygon or multipolygon.
+    ///
+    /// See [`FgbReader::select_within`](crate::FgbReader::select_within).
+    pub async fn select_within(
+        self,
+        polygon: &impl GeometryTrait<T = f64>,
+    ) -> Result<AsyncFgbFeatureIter<R>> {
+        self.select_spatial(SpatialFilter::new(SpatialPredicate::Within, polygon)?)
+            .await
+    }
+
+    /// Select features containing a point.
+    ///
+    /// See [`FgbReader::select_containing`](crate::FgbReader::select_containing).
+    pub async fn select_containing(
+        self,
+        point: &impl PointTrait<T = f64>,
+    ) -> Result<AsyncFgbFeatureIter<R>> {
+        self.select_spatial(SpatialFilter::new(SpatialPredicate::Contains, point)?)
+            .await
+    }
+
+    async fn select_spatial(self, filter: SpatialFilter) -> Result<AsyncFgbFeatureIter<R>> {
+        let bbox = filter.bbox();
+        let mut iter = self
+            .select_bbox(bbox.min_x, bbox.min_y, bbox.max_x, bbox.max_y)
+            .await?;
+        iter.spatial_filter = Some(filter);
+        Ok(iter)
+    }
+}
+
+impl<R: AsyncRead + AsyncSeek + Unpin> AsyncFgbFeatureIter<R> {
+    fn new(
+        reader: R,
+        fbs: FgbFeature,
+        item_filter: Option<Vec<SearchResultItem>>,
+    ) -> AsyncFgbFeatureIter<R> {
+        let count = match &item_filter {
+            Some(list) => Some(list.len()),
+            None => Some(fbs.header().features_count() as usize).filter(|count| *count > 0),
+        };
+        AsyncFgbFeatureIter {
+            reader,
+            fbs,
+            item_filter,
+            spatial_filter: None,
+            property_filter: None,
+            count,
+            feat_no: 0,
+            cur_pos: 0,
+            finished: false,
+        }
+    }
+
+    /// Header information
+    pub fn header(&self) -> Header<'_> {
+        self.fbs.header()
+    }
+
+    /// Number of selected features (None for undefined feature count)
+    pub fn features_count(&self) -> Option<usize> {
+        if self.spatial_filter.is_some() || self.property_filter.is_some() {
+            None
+        } else {
+            self.count
+        }
+    }
+
+    /// Skip features not matching the property filter expression.
+    ///
+    /// See [`FeatureIter::filter_properties`](crate::FeatureIter::filter_properties).
+    pub fn filter_properties(mut self, expr: Expr) -> Result<Self> {
+        let filter = PropertyFilter::new(expr, self.fbs.header())?;
+        self.property_filter = Some(match self.property_filter.take() {
+            Some(existing) => existing.and(filter),
+            None => filter,
+        });
+        Ok(self)
+    }
+
+    /// Decode only the named columns.
+    ///
+    /// See [`FeatureIter::columns`](crate::FeatureIter::columns).
+    pub fn columns(mut self, names: &[&str]) -> Result<Self> {
+        self.fbs.projection = Some(Projection::new(self.fbs.header(), names)?);
+        Ok(self)
+    }
+
+    /// Report columns without value in a feature as JSON `null` values.
+    ///
+    /// See [`FeatureIter::explicit_nulls`](crate::FeatureIter::explicit_nulls).
+    pub fn explicit_nulls(mut self) -> Self {
+        self.fbs.explicit_nulls = true;
+        self
+    }
+
+    /// Read next feature
+    pub async fn next(&mut self) -> Result<Option<&FgbFeature>> {
+        loop {
+            if !self.read_feature().await? {
+                return Ok(None);
+            }
+            if let Some(filter) = &self.property_filter {
+                if !filter.matches(self.fbs.header(), self.fbs.fbs_feature())? {
+                    continue;
+                }
+            }
+            match &self.spatial_filter {
+                Some(filter) if !filter.matches(&self.fbs)? => {}
+                _ => return Ok(Some(&self.fbs)),
+            }
+        }
+    }
+
+    /// Return current feature
+    pub fn cur_feature(&self) -> &FgbFeature {
+        &self.fbs
+    }
+
+    /// Read the next selected feature, false if end of selection reached
+    async fn read_feature(&mut self) -> Result<bool> {
+        if self.finished || self.count.is_some_and(|count| self.feat_no >= count) {
+            self.finished = true;
+            return Ok(false);
+        }
+        if let Some(filter) = &self.item_filter {
+            let offset = filter[self.feat_no].offset as u64;
+            if offset > self.cur_pos {
+                // skip features
+                self.reader
+                    .seek(SeekFrom::Current((offset - self.cur_pos) as i64))
+                    .await?;
+                self.cur_pos = offset;
+            }
+        }
+        let mut size_buf: [u8; 4] = [0; 4];
+        match self.reader.read_exact(&mut size_buf).await {
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof && self.count.is_none() => {
+                self.finished = true;
+                return Ok(false);
+            }
+            Err(e) => return Err(e.into()),
+        }
+        let feature_size = u32::from_le_bytes(size_buf) as usize;
+        check_feature_size(feature_size)?;
+        let feature_buf = &mut self.fbs.feature_buf;
+        feature_buf.clear();
+        feature_buf.extend_from_slice(&size_buf);
+        feature_buf.resize(feature_size + 4, 0);
+        self.reader.read_exact(&mut feature_buf[4..]).await?;
+        // verify flatbuffer
+        let _feature = size_prefixed_root_as_feature(feature_buf)?;
+        self.feat_no += 1;
+        self.cur_pos += 4 + feature_size as u64;
+        Ok(true)
+    }
+}
+
+mod geozero_api {
+    use crate::AsyncFgbFeatureIter;
+    use geozero::{error::Result, FeatureAccess, FeatureProcessor};
+    use tokio::io::{AsyncRead, AsyncSeek};
+
+    impl<R: AsyncRead + AsyncSeek + Unpin> AsyncFgbFeatureIter<R> {
+        /// Read and process all selected features
+        pub async fn process_features<W: FeatureProcessor>(&mut self, out: &mut W) -> Result<()> {
+            out.dataset_begin(self.fbs.header().name())?;
+            let mut cnt = 0;
+            while let Some(feature) = self
+                .next()
+                .await
+                .map_err(|e| geozero::error::GeozeroError::Feature(e.to_string()))?
+            {
+                feature.process(out, cnt)?;
+                cnt += 1;
+            }
+            out.dataset_end()
+        }
+    }
+}
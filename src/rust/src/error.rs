@@ -1,3 +1,4 @@
+use crate::header_generated::ColumnType;
 use flatbuffers::InvalidFlatbuffer;
 use std::fmt::{Display, Formatter};
 
@@ -11,6 +12,13 @@ pub enum Error {
     InvalidFlatbuffer(InvalidFlatbuffer),
     IO(std::io::Error),
     UnsupportedGeometryType(String),
+    UnknownColumn(String),
+    MissingValue(String),
+    Schema(SchemaError),
+    #[cfg(feature = "arrow")]
+    Arrow(arrow_schema::ArrowError),
+    #[cfg(feature = "serde")]
+    Serde(String),
 }
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -25,6 +33,15 @@ impl Display for Error {
             Error::InvalidFlatbuffer(invalid_flatbuffer) => invalid_flatbuffer.fmt(f),
             Error::IO(io) => io.fmt(f),
             Error::UnsupportedGeometryType(s) => f.write_str(s),
+            Error::UnknownColumn(name) => write!(f, "Unknown column: {name}"),
+            Error::MissingValue(name) => {
+                write!(f, "Missing value of non-nullable column: {name}")
+            }
+            Error::Schema(schema) => schema.fmt(f),
+            #[cfg(feature = "arrow")]
+            Error::Arrow(arrow) => arrow.fmt(f),
+            #[cfg(feature = "serde")]
+            Error::Serde(msg) => f.write_str(msg),
         }
     }
 }
@@ -49,3 +66,81 @@ impl From<http_range_client::HttpError> for Error {
         Error::HttpClient(value)
     }
 }
+
+#[cfg(feature = "arrow")]
+impl From<arrow_schema::ArrowError> for Error {
+    fn from(value: arrow_schema::ArrowError) -> Self {
+        Error::Arrow(value)
+    }
+}
+
+/// Property not matching the declared columns
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemaError {
+    /// Property without column declaration
+    UnknownColumn { index: usize, name: String },
+    /// Property name differs from the name of the column at its index
+    ColumnName {
+        index: usize,
+        expected: String,
+        found: String,
+    },
+    /// Value type differs from the declared column type and cannot be converted
+    ColumnType {
+        column: String,
+        expected: ColumnType,
+        found: ColumnType,
+    },
+    /// Property index lower than the index of the previous property
+    OutOfOrder { column: String },
+    /// Property written twice in the same feature
+    Duplicate { column: String },
+    /// String or binary value longer than the column width
+    Width {
+        column: String,
+        width: i32,
+        len: usize,
+    },
+}
+
+impl Display for SchemaError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchemaError::UnknownColumn { index, name } => {
+                write!(f, "Undeclared column `{name}` at index {index}")
+            }
+            SchemaError::ColumnName {
+                index,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Property `{found}` does not match column `{expected}` at index {index}"
+            ),
+            SchemaError::ColumnType {
+                column,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Value of type {found:?} does not match type {expected:?} of column `{column}`"
+            ),
+            SchemaError::OutOfOrder { column } => {
+                write!(f, "Property `{column}` out of column order")
+            }
+            SchemaError::Duplicate { column } => write!(f, "Duplicate property `{column}`"),
+            SchemaError::Width { column, width, len } => write!(
+                f,
+                "Value of length {len} exceeds width {width} of column `{column}`"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+impl From<SchemaError> for Error {
+    fn from(value: SchemaError) -> Self {
+        Error::Schema(value)
+    }
+}
@@ -0,0 +1,372 @@
+//! External merge sort of index leaf nodes, for writing datasets larger than memory.
+
+use crate::file_writer::SortOrder;
+use crate::packed_r_tree::{
+    hilbert_value, morton_value, str_slice_size, str_x_key, str_y_key, NodeItem,
+};
+use crate::Result;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, Write};
+use std::mem::size_of;
+
+/// Size of a [`SortItem`] in memory and in temporary files
+const SORT_ITEM_SIZE: usize = size_of::<NodeItem>() + size_of::<u64>();
+
+/// Maximal number of runs merged at once
+const MAX_MERGE_RUNS: usize = 64;
+
+/// Leaf node of a feature in the temporary feature file
+pub(crate) struct SortItem {
+    /// Feature bounds, `offset` is the feature offset in the temporary file
+    pub(crate) node: NodeItem,
+    /// Feature size in bytes
+    pub(crate) size: u64,
+}
+
+impl SortItem {
+    fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        self.node.write(out)?;
+        out.write_u64::<LittleEndian>(self.size)
+    }
+
+    fn read(mut rdr: impl Read) -> Result<Self> {
+        Ok(SortItem {
+            node: NodeItem::from_reader(&mut rdr)?,
+            size: rdr.read_u64::<LittleEndian>()?,
+        })
+    }
+
+    /// Key in `order`, ties in insertion order. STR is sorted by x before slicing.
+    fn sort_key(&self, order: &SortOrder, extent: &NodeItem) -> (u64, u64) {
+        let key = match order {
+            SortOrder::Hilbert => u64::from(u32::MAX - hilbert_value(&self.node, extent)),
+            SortOrder::ZOrder => u64::from(morton_value(&self.node, extent)),
+            SortOrder::Str => str_x_key(&self.node),
+            SortOrder::Input => 0,
+            SortOrder::Key(key) => key(&self.node, extent),
+        };
+        (key, self.node.offset)
+    }
+}
+
+/// Leaf nodes collected within a memory budget, spilled to a temporary file when exceeded.
+pub(crate) struct ExternalNodeSort {
+    order: SortOrder,
+    max_items: usize,
+    items: Vec<SortItem>,
+    spill: Option<BufWriter<File>>,
+    len: usize,
+    next_offset: u64,
+    extent: NodeItem,
+}
+
+impl ExternalNodeSort {
+    pub(crate) fn new(memory_budget: usize, order: SortOrder) -> Self {
+        ExternalNodeSort {
+            order,
+            max_items: (memory_budget / SORT_ITEM_SIZE).max(2),
+            items: Vec::new(),
+            spill: None,
+            len: 0,
+            next_offset: 0,
+            extent: NodeItem::create(0),
+        }
+    }
+
+    /// Add the leaf node of the next feature with `size` bytes.
+    pub(crate) fn push(&mut self, mut node: NodeItem, size: u64) -> Result<()> {
+        self.extent.expand(&node);
+        node.offset = self.next_offset;
+        self.next_offset += size;
+        self.items.push(SortItem { node, size });
+        self.len += 1;
+        if self.items.len() >= self.max_items {
+            self.spill_items()?;
+        }
+        Ok(())
+    }
+
+    fn spill_items(&mut self) -> Result<()> {
+        let spill = match &mut self.spill {
+            Some(spill) => spill,
+            None => self.spill.insert(BufWriter::new(tempfile::tempfile()?)),
+        };
+        for item in self.items.drain(..) {
+            item.write(spill)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Extent of all pushed nodes
+    pub(crate) fn extent(&self) -> NodeItem {
+        self.extent.clone()
+    }
+
+    /// Sort nodes of a tree with `node_size` in the same order as [`SortOrder::sort`].
+    ///
+    /// Nodes which fit into the memory budget are sorted in memory. Otherwise sorted runs of the
+    /// budget size are written to temporary files and merged.
+    pub(crate) fn sort(self, node_size: u16) -> Result<SortedNodes> {
+        let order = self.order;
+        let len = self.len;
+        let sorted = self.sort_runs()?;
+        match order {
+            SortOrder::Str => sort_slices(sorted, str_slice_size(len, node_size)),
+            _ => Ok(sorted),
+        }
+    }
+
+    fn sort_runs(mut self) -> Result<SortedNodes> {
+        let order = self.order;
+        let extent = self.extent.clone();
+        if self.spill.is_none() {
+            let mut items = self.items;
+            items.sort_by_cached_key(|item| item.sort_key(&order, &extent));
+            return Ok(SortedNodes::Memory { items, pos: 0 });
+        }
+        self.spill_items()?;
+        let mut spill = into_reader(self.spill.take().expect("spilled nodes"))?;
+
+        // Sorted runs
+        let mut runs = Vec::new();
+        let mut remaining = self.len;
+        let mut items = Vec::with_capacity(self.max_items.min(self.len));
+        while remaining > 0 {
+            let count = remaining.min(self.max_items);
+            for _ in 0..count {
+                items.push(SortItem::read(&mut spill)?);
+            }
+            remaining -= count;
+            items.sort_by_cached_key(|item| item.sort_key(&order, &extent));
+            let mut run = BufWriter::new(tempfile::tempfile()?);
+            for item in items.drain(..) {
+                item.write(&mut run)?;
+            }
+            runs.push((into_reader(run)?, count));
+        }
+        drop(items);
+
+        while runs.len() > 1 {
+            let mut merged = Vec::with_capacity(runs.len().div_ceil(MAX_MERGE_RUNS));
+            while !runs.is_empty() {
+                let group = runs.drain(..runs.len().min(MAX_MERGE_RUNS)).collect();
+                merged.push(merge_runs(group, &order, &extent)?);
+            }
+            runs = merged;
+        }
+        let (file, len) = runs.pop().expect("at least one run");
+        Ok(SortedNodes::File {
+            file,
+            len,
+            remaining: len,
+        })
+    }
+}
+
+/// Sort consecutive slices of `slice_size` nodes by center y, the second pass of STR packing
+fn sort_slices(sorted: SortedNodes, slice_size: usize) -> Result<SortedNodes> {
+    let (mut file, len) = match sorted {
+        SortedNodes::Memory { mut items, pos } => {
+            for slice in items.chunks_mut(slice_size) {
+                slice.sort_by_cached_key(|item| str_y_key(&item.node));
+            }
+            return Ok(SortedNodes::Memory { items, pos });
+        }
+        SortedNodes::File { file, len, .. } => (file, len),
+    };
+    let mut out = BufWriter::new(tempfile::tempfile()?);
+    let mut slice = Vec::with_capacity(slice_size.min(len));
+    let mut remaining = len;
+    while remaining > 0 {
+        let count = remaining.min(slice_size);
+        for _ in 0..count {
+            slice.push(SortItem::read(&mut file)?);
+        }
+        remaining -= count;
+        slice.sort_by_cached_key(|item| str_y_key(&item.node));
+        for item in slice.drain(..) {
+            item.write(&mut out)?;
+        }
+    }
+    Ok(SortedNodes::File {
+        file: into_reader(out)?,
+        len,
+        remaining: len,
+    })
+}
+
+fn into_reader(writer: BufWriter<File>) -> Result<BufReader<File>> {
+    let mut file = writer.into_inner().map_err(|e| e.into_error())?;
+    file.rewind()?;
+    Ok(BufReader::new(file))
+}
+
+/// Merge sorted runs into a single sorted run
+fn merge_runs(
+    mut runs: Vec<(BufReader<File>, usize)>,
+    order: &SortOrder,
+    extent: &NodeItem,
+) -> Result<(BufReader<File>, usize)> {
+    let mut heap = BinaryHeap::with_capacity(runs.len());
+    for (idx, (run, remaining)) in runs.iter_mut().enumerate() {
+        if *remaining > 0 {
+            *remaining -= 1;
+            let item = SortItem::read(run)?;
+            heap.push(Reverse((item.sort_key(order, extent), idx, HeapItem(item))));
+        }
+    }
+    let mut out = BufWriter::new(tempfile::tempfile()?);
+    let mut len = 0;
+    while let Some(Reverse((_, idx, HeapItem(item)))) = heap.pop() {
+        item.write(&mut out)?;
+        len += 1;
+        let (run, remaining) = &mut runs[idx];
+        if *remaining > 0 {
+            *remaining -= 1;
+            let item = SortItem::read(run)?;
+            heap.push(Reverse((item.sort_key(order, extent), idx, HeapItem(item))));
+        }
+    }
+    Ok((into_reader(out)?, len))
+}
+
+/// Heap entry payload, ordered by the preceding sort key only
+struct HeapItem(SortItem);
+
+impl PartialEq for HeapItem {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for HeapItem {}
+
+impl PartialOrd for HeapItem {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for HeapItem {
+    fn cmp(&self, _other: &Self) -> std::cmp::Ordering {
+        std::cmp::Ordering::Equal
+    }
+}
+
+/// Sorted leaf nodes, which can be iterated repeatedly
+pub(crate) enum SortedNodes {
+    Memory {
+        items: Vec<SortItem>,
+        pos: usize,
+    },
+    File {
+        file: BufReader<File>,
+        len: usize,
+        remaining: usize,
+    },
+}
+
+impl SortedNodes {
+    /// Restart iteration at the first node
+    pub(crate) fn rewind(&mut self) -> Result<()> {
+        match self {
+            SortedNodes::Memory { pos, .. } => *pos = 0,
+            SortedNodes::File {
+                file,
+                len,
+                remaining,
+            } => {
+                file.rewind()?;
+                *remaining = *len;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for SortedNodes {
+    type Item = Result<SortItem>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            SortedNodes::Memory { items, pos } => {
+                let item = items.get(*pos)?;
+                *pos += 1;
+                Some(Ok(SortItem {
+                    node: item.node.clone(),
+                    size: item.size,
+                }))
+            }
+            SortedNodes::File {
+                file, remaining, ..
+            } => {
+                if *remaining == 0 {
+                    return None;
+                }
+                *remaining -= 1;
+                Some(SortItem::read(file))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packed_r_tree::calc_extent;
+
+    fn nodes(count: usize) -> Vec<NodeItem> {
+        (0..count)
+            .map(|i| {
+                // Repeating positions to get equal Hilbert values
+                let x = ((i * 7919) % 101) as f64;
+                let y = ((i * 104729) % 37) as f64;
+                NodeItem::bounds(x, y, x + 1.0, y + 1.0)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn external_sort_matches_sort_order() -> Result<()> {
+        let orders = [
+            SortOrder::Hilbert,
+            SortOrder::ZOrder,
+            SortOrder::Str,
+            SortOrder::Input,
+            SortOrder::Key(|node, extent| (node.max_y - extent.min_y) as u64),
+        ];
+        for order in orders {
+            let mut expected = nodes(1000);
+            let extent = calc_extent(&expected);
+            for (i, node) in expected.iter_mut().enumerate() {
+                node.offset = i as u64 * 10;
+            }
+            order.sort(&mut expected, &extent, 16);
+
+            // In memory, single run and multi-pass merge
+            for budget in [usize::MAX, 500 * SORT_ITEM_SIZE, 2 * SORT_ITEM_SIZE] {
+                let mut sort = ExternalNodeSort::new(budget, order);
+                for node in nodes(1000) {
+                    sort.push(node, 10)?;
+                }
+                assert_eq!(sort.len(), 1000);
+                assert_eq!(sort.extent(), extent);
+                let mut sorted = sort.sort(16)?;
+                for _ in 0..2 {
+                    let items = sorted.by_ref().collect::<Result<Vec<_>>>()?;
+                    let nodes = items.into_iter().map(|item| item.node).collect::<Vec<_>>();
+                    assert_eq!(nodes, expected, "{order:?}");
+                    sorted.rewind()?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,5 +1,7 @@
+use crate::error::SchemaError;
 use crate::feature_generated::*;
-use crate::header_generated::{ColumnType, GeometryType};
+use crate::file_writer::{ColumnDef, SchemaValidation};
+use crate::header_generated::{Column, ColumnArgs, ColumnType, GeometryType};
 use crate::packed_r_tree::NodeItem;
 use byteorder::{ByteOrder, LittleEndian};
 use geozero::error::{GeozeroError, Result};
@@ -32,6 +34,22 @@ pub struct FeatureWriter<'a> {
     parts: Vec<flatbuffers::WIPOffset<Geometry<'a>>>,
     geom_state: GeomState,
     properties: Vec<u8>,
+    // Declared columns of the dataset
+    pub(crate) columns: Vec<ColumnDef>,
+    // Validation of properties against `columns`
+    pub(crate) validation: SchemaValidation,
+    // Columns with a value in the current feature
+    has_value: Vec<bool>,
+    // Index of the previous property in the current feature
+    last_property: Option<usize>,
+    // First schema violation in the current feature
+    schema_error: Option<SchemaError>,
+    // Write the columns of each feature with the feature instead of `columns`
+    pub(crate) per_feature_columns: bool,
+    // Columns of the current feature, in order of first appearance
+    feature_columns: Vec<(String, ColumnType)>,
+    // Write the current feature without geometry
+    null_geometry: bool,
     fbb: flatbuffers::FlatBufferBuilder<'a>,
     pub(crate) bbox: NodeItem,
 }
@@ -90,10 +108,32 @@ impl<'a> FeatureWriter<'a> {
             parts: Vec::new(),
             geom_state: GeomState::Normal,
             properties: Vec::new(),
+            columns: Vec::new(),
+            validation: SchemaValidation::Off,
+            has_value: Vec::new(),
+            last_property: None,
+            schema_error: None,
+            per_feature_columns: false,
+            feature_columns: Vec::new(),
+            null_geometry: false,
             fbb: flatbuffers::FlatBufferBuilder::new(),
             bbox: NodeItem::create(0),
         }
     }
+    /// Writer with the same dataset settings and columns, e.g. for encoding on another thread
+    #[cfg(feature = "rayon")]
+    pub(crate) fn fork<'b>(&self) -> FeatureWriter<'b> {
+        let mut writer = FeatureWriter::with_dims(
+            self.dataset_type,
+            self.detect_type,
+            self.promote_to_multi,
+            self.dims,
+        );
+        writer.columns = self.columns.clone();
+        writer.validation = self.validation;
+        writer.per_feature_columns = self.per_feature_columns;
+        writer
+    }
     fn set_type(&mut self, geometry_type: GeometryType) -> Result<()> {
         if geometry_type != self.dataset_type {
             match self.dataset_type {
@@ -204,30 +244,269 @@ impl<'a> FeatureWriter<'a> {
         );
         self.parts.push(g);
     }
+    /// Whether the dataset type is replaced with the type of the next geometry
+    #[cfg(feature = "rayon")]
+    pub(crate) fn detecting_type(&self) -> bool {
+        self.detect_type && self.dataset_type == GeometryType::Unknown
+    }
+    /// Write the current feature without geometry
+    #[cfg(feature = "arrow")]
+    pub(crate) fn null_geometry(&mut self) {
+        self.null_geometry = true;
+        self.bbox = NodeItem::create(0);
+    }
+    /// Append interleaved coordinates with `dim` ordinates each and Z and M at the given
+    /// ordinate positions.
+    ///
+    /// Like with [`GeomProcessor::coordinate`], Z and M values are only written if the
+    /// dimensions of the dataset include them.
+    #[cfg(feature = "arrow")]
+    pub(crate) fn extend_interleaved(
+        &mut self,
+        coords: &[f64],
+        dim: usize,
+        z: Option<usize>,
+        m: Option<usize>,
+    ) {
+        self.xy.reserve(coords.len() / dim * 2);
+        for coord in coords.chunks_exact(dim) {
+            self.xy.extend_from_slice(&coord[..2]);
+            self.bbox.expand_xy(coord[0], coord[1]);
+        }
+        if let Some(z) = z.filter(|_| self.dims.z) {
+            self.z
+                .extend(coords.chunks_exact(dim).map(|coord| coord[z]));
+        }
+        if let Some(m) = m.filter(|_| self.dims.m) {
+            self.m
+                .extend(coords.chunks_exact(dim).map(|coord| coord[m]));
+        }
+    }
+    /// Append coordinates from separate ordinate arrays.
+    ///
+    /// Z and M values are only written if the dimensions of the dataset include them.
+    #[cfg(feature = "arrow")]
+    pub(crate) fn extend_separated(
+        &mut self,
+        x: &[f64],
+        y: &[f64],
+        z: Option<&[f64]>,
+        m: Option<&[f64]>,
+    ) {
+        self.xy.reserve(x.len() * 2);
+        for (x, y) in x.iter().zip(y) {
+            self.xy.extend([*x, *y]);
+            self.bbox.expand_xy(*x, *y);
+        }
+        if let Some(z) = z.filter(|_| self.dims.z) {
+            self.z.extend_from_slice(z);
+        }
+        if let Some(m) = m.filter(|_| self.dims.m) {
+            self.m.extend_from_slice(m);
+        }
+    }
+    /// Discard the properties of the current feature
+    #[cfg(feature = "serde")]
+    pub(crate) fn reset_properties(&mut self) {
+        self.properties.truncate(0);
+        self.has_value.clear();
+        self.last_property = None;
+        self.schema_error = None;
+        self.feature_columns.clear();
+    }
+    /// Whether column `i` has a value in the current feature
+    pub(crate) fn has_value(&self, i: usize) -> bool {
+        self.has_value.get(i).copied().unwrap_or(false)
+    }
+    /// Whether the declared column `i` has a value in the current feature, looked up by name
+    /// with per-feature columns
+    fn has_column_value(&self, i: usize, name: &str) -> bool {
+        if self.per_feature_columns {
+            self.feature_columns
+                .iter()
+                .any(|(colname, _)| colname == name)
+        } else {
+            self.has_value(i)
+        }
+    }
+    /// Whether a property refers to a declared column.
+    ///
+    /// Always true with strict validation, which reports undeclared columns itself, and with
+    /// per-feature columns.
+    pub(crate) fn is_declared(&self, i: usize, colname: &str) -> bool {
+        if self.per_feature_columns {
+            return true;
+        }
+        match self.validation {
+            SchemaValidation::Off => i < self.columns.len(),
+            SchemaValidation::Lenient => self.columns.iter().any(|column| column.name == colname),
+            SchemaValidation::Strict => true,
+        }
+    }
+    /// Fail with the first schema violation or missing value of a non-nullable column.
+    ///
+    /// The current feature is discarded on failure.
+    pub(crate) fn check_properties(&mut self) -> crate::Result<()> {
+        let error = match self.schema_error.take() {
+            Some(schema_error) => Some(crate::Error::Schema(schema_error)),
+            None => self
+                .columns
+                .iter()
+                .enumerate()
+                .find(|(i, column)| !column.nullable && !self.has_column_value(*i, &column.name))
+                .map(|(_, column)| crate::Error::MissingValue(column.name.clone())),
+        };
+        match error {
+            Some(error) => {
+                self.discard_feature();
+                Err(error)
+            }
+            None => Ok(()),
+        }
+    }
+    /// Column index and converted value to write after validation, `None` for skipped properties
+    fn validate<'v>(
+        &self,
+        i: usize,
+        colname: &str,
+        colval: &ColumnValue<'v>,
+    ) -> std::result::Result<Option<(usize, Option<ColumnValue<'v>>)>, SchemaError> {
+        let idx = match self.columns.get(i) {
+            Some(column) if column.name == colname => i,
+            Some(column) if self.validation == SchemaValidation::Strict => {
+                return Err(SchemaError::ColumnName {
+                    index: i,
+                    expected: column.name.clone(),
+                    found: colname.to_string(),
+                })
+            }
+            _ if self.validation == SchemaValidation::Strict => {
+                return Err(SchemaError::UnknownColumn {
+                    index: i,
+                    name: colname.to_string(),
+                })
+            }
+            _ => match self
+                .columns
+                .iter()
+                .position(|column| column.name == colname)
+            {
+                Some(idx) => idx,
+                None => {
+                    info!("Undefined property index {i}, column: `{colname}` - skipping");
+                    return Ok(None);
+                }
+            },
+        };
+        let column = &self.columns[idx];
+        if self.validation == SchemaValidation::Strict {
+            if self.has_value(idx) {
+                return Err(SchemaError::Duplicate {
+                    column: column.name.clone(),
+                });
+            }
+            if self.last_property.is_some_and(|last| idx < last) {
+                return Err(SchemaError::OutOfOrder {
+                    column: column.name.clone(),
+                });
+            }
+        }
+        Ok(Some((idx, self.check_value(column, colval)?)))
+    }
+    /// Check a value against the type and width of its column, returning the value converted to
+    /// the column type if it differs
+    fn check_value<'v>(
+        &self,
+        column: &ColumnDef,
+        colval: &ColumnValue<'v>,
+    ) -> std::result::Result<Option<ColumnValue<'v>>, SchemaError> {
+        let value_type = prop_type(colval);
+        let converted = if value_type == column.type_ {
+            None
+        } else {
+            match convert_value(colval, column.type_) {
+                Some(value) if self.validation == SchemaValidation::Lenient => Some(value),
+                _ => {
+                    return Err(SchemaError::ColumnType {
+                        column: column.name.clone(),
+                        expected: column.type_,
+                        found: value_type,
+                    })
+                }
+            }
+        };
+        check_width(column, converted.as_ref().unwrap_or(colval))?;
+        Ok(converted)
+    }
+    /// Reset the state of the current feature, e.g. after an error while processing it
+    pub(crate) fn discard_feature(&mut self) {
+        self.ends.clear();
+        self.xy.clear();
+        self.z.clear();
+        self.m.clear();
+        self.t.clear();
+        self.tm.clear();
+        self.parts.clear();
+        self.geom_state = GeomState::Normal;
+        self.properties.clear();
+        self.has_value.clear();
+        self.last_property = None;
+        self.schema_error = None;
+        self.feature_columns.clear();
+        self.null_geometry = false;
+        self.bbox = NodeItem::create(0);
+        self.fbb.reset();
+    }
     pub(crate) fn finish_to_feature(&mut self) -> Vec<u8> {
-        let g = if self.parts.is_empty() {
+        let g = if std::mem::take(&mut self.null_geometry) {
+            None
+        } else if self.parts.is_empty() {
             self.finish_part();
-            self.parts.pop().expect("push in finish_part")
+            self.parts.pop()
         } else {
             let mut iter = std::mem::take(&mut self.parts).into_iter();
             let parts = self.fbb.create_vector_from_iter(&mut iter);
-            Geometry::create(
+            Some(Geometry::create(
                 &mut self.fbb,
                 &GeometryArgs {
                     type_: self.type_,
                     parts: Some(parts),
                     ..Default::default()
                 },
-            )
+            ))
         };
         let properties = Some(self.fbb.create_vector(&self.properties));
+        let columns = if self.feature_columns.is_empty() {
+            None
+        } else {
+            let columns = self
+                .feature_columns
+                .iter()
+                .map(|(name, type_)| {
+                    let name = self.fbb.create_string(name);
+                    Column::create(
+                        &mut self.fbb,
+                        &ColumnArgs {
+                            name: Some(name),
+                            type_: *type_,
+                            ..Default::default()
+                        },
+                    )
+                })
+                .collect::<Vec<_>>();
+            Some(self.fbb.create_vector(&columns))
+        };
         self.properties.truncate(0);
+        self.has_value.clear();
+        self.last_property = None;
+        self.schema_error = None;
+        self.feature_columns.clear();
         let f = Feature::create(
             &mut self.fbb,
             &FeatureArgs {
-                geometry: Some(g),
+                geometry: g,
                 properties,
-                ..Default::default()
+                columns,
             },
         );
         self.fbb.finish_size_prefixed(f, None);
@@ -440,12 +719,166 @@ pub(crate) fn prop_type(colval: &ColumnValue) -> ColumnType {
     }
 }
 
+/// Convert a value to the differing type of its declared column, if possible without loss
+pub(crate) fn convert_value<'v>(
+    colval: &ColumnValue<'v>,
+    column_type: ColumnType,
+) -> Option<ColumnValue<'v>> {
+    let int = match *colval {
+        ColumnValue::Byte(v) => Some(v as i128),
+        ColumnValue::UByte(v) => Some(v as i128),
+        ColumnValue::Short(v) => Some(v as i128),
+        ColumnValue::UShort(v) => Some(v as i128),
+        ColumnValue::Int(v) => Some(v as i128),
+        ColumnValue::UInt(v) => Some(v as i128),
+        ColumnValue::Long(v) => Some(v as i128),
+        ColumnValue::ULong(v) => Some(v as i128),
+        ColumnValue::Float(v) if v.fract() == 0.0 => Some(v as i128),
+        ColumnValue::Double(v) if v.fract() == 0.0 => Some(v as i128),
+        _ => None,
+    };
+    let float = match *colval {
+        ColumnValue::Float(v) => Some(v as f64),
+        ColumnValue::Double(v) => Some(v),
+        _ => int.map(|v| v as f64),
+    };
+    match column_type {
+        ColumnType::Byte => int
+            .and_then(|v| i8::try_from(v).ok())
+            .map(ColumnValue::Byte),
+        ColumnType::UByte => int
+            .and_then(|v| u8::try_from(v).ok())
+            .map(ColumnValue::UByte),
+        ColumnType::Short => int
+            .and_then(|v| i16::try_from(v).ok())
+            .map(ColumnValue::Short),
+        ColumnType::UShort => int
+            .and_then(|v| u16::try_from(v).ok())
+            .map(ColumnValue::UShort),
+        ColumnType::Int => int
+            .and_then(|v| i32::try_from(v).ok())
+            .map(ColumnValue::Int),
+        ColumnType::UInt => int
+            .and_then(|v| u32::try_from(v).ok())
+            .map(ColumnValue::UInt),
+        ColumnType::Long => int
+            .and_then(|v| i64::try_from(v).ok())
+            .map(ColumnValue::Long),
+        ColumnType::ULong => int
+            .and_then(|v| u64::try_from(v).ok())
+            .map(ColumnValue::ULong),
+        ColumnType::Float => float
+            .filter(|v| v.is_nan() || (*v as f32) as f64 == *v)
+            .map(|v| ColumnValue::Float(v as f32)),
+        ColumnType::Double => float.map(ColumnValue::Double),
+        ColumnType::Bool => int
+            .filter(|v| *v == 0 || *v == 1)
+            .map(|v| ColumnValue::Bool(v == 1)),
+        ColumnType::String | ColumnType::Json | ColumnType::DateTime => match *colval {
+            ColumnValue::String(v) | ColumnValue::Json(v) | ColumnValue::DateTime(v) => {
+                Some(match column_type {
+                    ColumnType::Json => ColumnValue::Json(v),
+                    ColumnType::DateTime => ColumnValue::DateTime(v),
+                    _ => ColumnValue::String(v),
+                })
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Fail with [`SchemaError::Width`] if a string or binary value is longer than the column width
+pub(crate) fn check_width(
+    column: &ColumnDef,
+    value: &ColumnValue,
+) -> std::result::Result<(), SchemaError> {
+    if column.width <= 0 {
+        return Ok(());
+    }
+    let len = match value {
+        ColumnValue::String(v) | ColumnValue::Json(v) | ColumnValue::DateTime(v) => {
+            v.chars().count()
+        }
+        ColumnValue::Binary(v) => v.len(),
+        _ => 0,
+    };
+    if len > column.width as usize {
+        return Err(SchemaError::Width {
+            column: column.name.clone(),
+            width: column.width,
+            len,
+        });
+    }
+    Ok(())
+}
+
 impl PropertyProcessor for FeatureWriter<'_> {
-    fn property(&mut self, i: usize, _colname: &str, colval: &ColumnValue) -> Result<bool> {
+    /// Write a property value.
+    ///
+    /// Schema violations are reported when the feature is written, so the remaining properties
+    /// of the feature are still processed.
+    fn property(&mut self, i: usize, colname: &str, colval: &ColumnValue) -> Result<bool> {
+        if self.per_feature_columns {
+            if self.feature_columns.iter().any(|(name, _)| name == colname) {
+                info!("Repeated property index {i}, column: `{colname}` - skipping");
+                return Ok(false);
+            }
+            // Values of declared dataset columns are validated against them
+            let declared = match self.columns.iter().find(|column| column.name == colname) {
+                Some(column) if self.validation != SchemaValidation::Off => self
+                    .check_value(column, colval)
+                    .map(|converted| Some((column.type_, converted))),
+                _ => Ok(None),
+            };
+            match declared {
+                Ok(Some((column_type, converted))) => {
+                    self.feature_columns
+                        .push((colname.to_string(), column_type));
+                    self.write_property(
+                        self.feature_columns.len() - 1,
+                        converted.as_ref().unwrap_or(colval),
+                    );
+                }
+                Ok(None) => {
+                    self.feature_columns
+                        .push((colname.to_string(), prop_type(colval)));
+                    self.write_property(self.feature_columns.len() - 1, colval);
+                }
+                Err(e) => {
+                    self.schema_error.get_or_insert(e);
+                }
+            }
+            return Ok(false);
+        }
+        if self.validation == SchemaValidation::Off {
+            self.write_property(i, colval);
+            return Ok(false);
+        }
+        if self.schema_error.is_some() {
+            return Ok(false);
+        }
+        match self.validate(i, colname, colval) {
+            Ok(Some((idx, converted))) => {
+                self.write_property(idx, converted.as_ref().unwrap_or(colval))
+            }
+            Ok(None) => {}
+            Err(e) => self.schema_error = Some(e),
+        }
+        Ok(false)
+    }
+}
+
+impl FeatureWriter<'_> {
+    fn write_property(&mut self, i: usize, colval: &ColumnValue) {
         let ofs = self.properties.len();
         self.properties
             .resize(ofs + size_of::<u16>() + prop_size(colval), 0);
         LittleEndian::write_u16(&mut self.properties[ofs..], i as u16);
+        if self.has_value.len() <= i {
+            self.has_value.resize(i + 1, false);
+        }
+        self.has_value[i] = true;
         let prop = &mut self.properties[ofs + size_of::<u16>()..];
         match colval {
             ColumnValue::Byte(v) => prop[0] = *v as u8,
@@ -467,8 +900,8 @@ impl PropertyProcessor for FeatureWriter<'_> {
                 LittleEndian::write_u32(prop, v.len() as u32);
                 prop[4..].copy_from_slice(v);
             }
-        };
-        Ok(false)
+        }
+        self.last_property = Some(i);
     }
 }
 
@@ -515,6 +948,8 @@ mod test {
         let feat = FgbFeature {
             header_buf: header(fgb_writer.dataset_type),
             feature_buf: fgb_writer.finish_to_feature(),
+            projection: None,
+            explicit_nulls: false,
         };
         // dbg!(&feat.fbs_feature());
         feat.process(&mut GeoJsonWriter::new(&mut out), 0)?;
@@ -532,6 +967,8 @@ mod test {
         let f = FgbFeature {
             header_buf: header(geometry_type),
             feature_buf: fgb_writer.finish_to_feature(),
+            projection: None,
+            explicit_nulls: false,
         };
         let mut json_writer = GeoJsonWriter::with_dims(&mut out, dims);
         dbg!(f
@@ -650,6 +1087,8 @@ mod test {
         let feat = FgbFeature {
             header_buf: header(fgb_writer.dataset_type),
             feature_buf: fgb_writer.finish_to_feature(),
+            projection: None,
+            explicit_nulls: false,
         };
         assert_eq!(
             fgb_writer.bbox,
@@ -0,0 +1,394 @@
+use crate::error::{Error, Result};
+use crate::external_sort::ExternalNodeSort;
+use crate::feature_writer::FeatureWriter;
+use crate::file_writer::{ColumnDef, SchemaValidation, SortOrder};
+use crate::header_generated::*;
+use crate::packed_r_tree::{NodeItem, PackedRTree};
+use crate::{FgbReader, FgbWriter, MAGIC_BYTES};
+use flatbuffers::FlatBufferBuilder;
+use geozero::CoordDimensions;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::mem::size_of;
+use std::path::{Path, PathBuf};
+
+/// Appends features to an existing FlatGeobuf file
+///
+/// Created with [`FgbWriter::append`]. Features must match the dataset schema: properties are
+/// assigned to the header columns by name and converted to the column type if possible.
+///
+/// # Usage example:
+///
+/// ```
+/// use flatgeobuf::*;
+/// use geozero::geojson::GeoJson;
+///
+/// # fn append() -> std::result::Result<(), Box<dyn std::error::Error>> {
+/// let mut fgb = FgbWriter::append("countries.fgb")?;
+/// let geojson = GeoJson(r#"{"type": "Feature", "properties": {"name": "Atlantis"}, "geometry": {"type": "Point", "coordinates": [-30, 30]}}"#);
+/// fgb.add_feature(geojson)?;
+/// fgb.finish()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct FgbAppender<'a> {
+    path: PathBuf,
+    file: File,
+    header_buf: Vec<u8>,
+    feat_writer: FeatureWriter<'a>,
+    /// Output of new features: the dataset file for unindexed datasets, a temporary file otherwise
+    out: BufWriter<File>,
+    /// Leaf nodes of new features, if the dataset is indexed
+    node_sort: Option<ExternalNodeSort>,
+    extent: NodeItem,
+    features_count: u64,
+}
+
+impl FgbWriter<'_> {
+    /// Open an existing FlatGeobuf file for appending features.
+    ///
+    /// Unindexed files are extended in place. Indexed files are rewritten with the new features
+    /// following the existing ones and a rebuilt index. The existing features are copied without
+    /// decoding and keep their order, while the new features are sorted in Hilbert order.
+    pub fn append<'a>(path: impl AsRef<Path>) -> Result<FgbAppender<'a>> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = File::options().read(true).write(true).open(&path)?;
+        let header_buf = FgbReader::open(&mut file)?.into_header_buf();
+        // SAFETY: verified by FgbReader::open
+        let header = unsafe { size_prefixed_root_as_header_unchecked(&header_buf) };
+
+        let columns = header
+            .columns()
+            .map(|columns| {
+                columns
+                    .iter()
+                    .map(|column| ColumnDef::from_column(&column))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let dims = CoordDimensions {
+            z: header.has_z(),
+            m: header.has_m(),
+            t: header.has_t(),
+            tm: header.has_tm(),
+        };
+        let mut feat_writer = FeatureWriter::with_dims(header.geometry_type(), false, true, dims);
+        feat_writer.columns = columns;
+        feat_writer.validation = SchemaValidation::Lenient;
+
+        // A zero features count means unknown, unless there are no features after the header
+        let feature_begin = (MAGIC_BYTES.len() + header_buf.len()) as u64;
+        let empty = file.metadata()?.len() == feature_begin;
+        let indexed = header.index_node_size() > 0 && (header.features_count() > 0 || empty);
+        let (out, node_sort) = if indexed {
+            let out = BufWriter::new(tempfile::tempfile()?);
+            (
+                out,
+                Some(ExternalNodeSort::new(usize::MAX, SortOrder::Hilbert)),
+            )
+        } else {
+            file.seek(SeekFrom::End(0))?;
+            (BufWriter::new(file.try_clone()?), None)
+        };
+
+        Ok(FgbAppender {
+            path,
+            file,
+            header_buf,
+            feat_writer,
+            out,
+            node_sort,
+            extent: NodeItem::create(0),
+            features_count: 0,
+        })
+    }
+}
+
+impl FgbAppender<'_> {
+    fn header(&self) -> Header<'_> {
+        // SAFETY: verified when opening
+        unsafe { size_prefixed_root_as_header_unchecked(&self.header_buf) }
+    }
+
+    fn write_feature(&mut self) -> Result<()> {
+        self.feat_writer.check_properties()?;
+        let node = self.feat_writer.bbox.clone();
+        let feat_buf = self.feat_writer.finish_to_feature();
+        self.extent.expand(&node);
+        if let Some(node_sort) = &mut self.node_sort {
+            node_sort.push(node, feat_buf.len() as u64)?;
+        }
+        self.out.write_all(&feat_buf)?;
+        self.features_count += 1;
+        Ok(())
+    }
+
+    /// Number of appended features
+    pub fn features_count(&self) -> u64 {
+        self.features_count
+    }
+
+    /// Write the appended features and update header and index.
+    pub fn finish(mut self) -> Result<()> {
+        self.out.flush()?;
+        if self.features_count == 0 {
+            return Ok(());
+        }
+        match self.node_sort.take() {
+            Some(node_sort) => self.rebuild_indexed(node_sort),
+            None => self.patch_header(),
+        }
+    }
+
+    /// Update features count and envelope of an unindexed dataset in place.
+    ///
+    /// Header fields missing in the file are left unset, since adding them would change the
+    /// header size. A missing or zero features count means unknown.
+    fn patch_header(&mut self) -> Result<()> {
+        let header = self.header();
+        let table = header._tab;
+        let table_offset = table.buf().as_ptr() as usize - self.header_buf.as_ptr() as usize;
+        let header_pos = |offset: usize| (MAGIC_BYTES.len() + offset) as u64;
+
+        let mut patches = Vec::new();
+        let field = table.vtable().get(Header::VT_FEATURES_COUNT) as usize;
+        if field > 0 && header.features_count() > 0 {
+            let features_count = header.features_count() + self.features_count;
+            let pos = header_pos(table_offset + table.loc() + field);
+            patches.push((pos, features_count.to_le_bytes().to_vec()));
+        }
+        if let Some(envelope) = header.envelope().filter(|envelope| envelope.len() == 4) {
+            let mut extent = self.extent.clone();
+            extent.expand(&NodeItem::bounds(
+                envelope.get(0),
+                envelope.get(1),
+                envelope.get(2),
+                envelope.get(3),
+            ));
+            let offset = envelope.bytes().as_ptr() as usize - self.header_buf.as_ptr() as usize;
+            let bytes = [extent.min_x, extent.min_y, extent.max_x, extent.max_y]
+                .iter()
+                .flat_map(|v| v.to_le_bytes())
+                .collect();
+            patches.push((header_pos(offset), bytes));
+        }
+        self.out.get_ref().sync_data()?;
+        for (pos, bytes) in patches {
+            self.file.seek(SeekFrom::Start(pos))?;
+            self.file.write_all(&bytes)?;
+        }
+        self.file.flush()?;
+        Ok(())
+    }
+
+    /// Rewrite an indexed dataset with the new features following the existing ones.
+    ///
+    /// A missing envelope is taken from the root node of the existing index.
+    fn rebuild_indexed(self, node_sort: ExternalNodeSort) -> Result<()> {
+        let header = self.header();
+        let old_count = header.features_count() as usize;
+        let node_size = header.index_node_size();
+        let num_items = old_count + node_sort.len();
+        let index_begin = (MAGIC_BYTES.len() + self.header_buf.len()) as u64;
+        let feature_begin = index_begin + PackedRTree::index_size(old_count, node_size) as u64;
+        let old_data_len = self.file.metadata()?.len() - feature_begin;
+
+        let mut file = &self.file;
+        let mut envelope = self.extent.clone();
+        if let Some(old) = header.envelope().filter(|envelope| envelope.len() == 4) {
+            envelope.expand(&NodeItem::bounds(
+                old.get(0),
+                old.get(1),
+                old.get(2),
+                old.get(3),
+            ));
+        } else if old_count > 0 {
+            // The root node covers all existing features
+            file.seek(SeekFrom::Start(index_begin))?;
+            envelope.expand(&NodeItem::from_reader(&mut file)?);
+        }
+        let mut fbb = FlatBufferBuilder::new();
+        let new_header = copy_header(&mut fbb, header, num_items as u64, &envelope, node_size);
+        fbb.finish_size_prefixed(new_header, None);
+
+        let dir = match self.path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let tmpfile = tempfile::NamedTempFile::new_in(dir)?;
+        let mut out = BufWriter::new(tmpfile.as_file());
+        out.write_all(&MAGIC_BYTES)?;
+        out.write_all(fbb.finished_data())?;
+
+        // Index of existing leaf nodes followed by the new ones
+        if old_count > 0 {
+            let leaf_nodes_offset = PackedRTree::leaf_nodes_offset(old_count, node_size)?;
+            file.seek(SeekFrom::Start(
+                index_begin + (leaf_nodes_offset * size_of::<NodeItem>()) as u64,
+            ))?;
+        }
+        let mut old_nodes = BufReader::new(file);
+        let old_nodes = (0..old_count).map(|_| NodeItem::from_reader(&mut old_nodes));
+        let mut sorted = node_sort.sort(node_size)?;
+        let mut offset = old_data_len;
+        let new_nodes = sorted.by_ref().map(|item| {
+            item.map(|item| {
+                let mut node = item.node;
+                node.offset = offset;
+                offset += item.size;
+                node
+            })
+        });
+        PackedRTree::stream_build(old_nodes.chain(new_nodes), num_items, node_size, &mut out)?;
+
+        // Existing features
+        file.seek(SeekFrom::Start(feature_begin))?;
+        io::copy(&mut file.take(old_data_len), &mut out)?;
+
+        // New features in sort order
+        let mut new_features = self.out.into_inner().map_err(|e| e.into_error())?;
+        new_features.rewind()?;
+        let mut new_features = BufReader::new(new_features);
+        sorted.rewind()?;
+        let mut buf = Vec::with_capacity(2048);
+        for item in sorted {
+            let item = item?;
+            new_features.seek(SeekFrom::Start(item.node.offset))?;
+            buf.resize(item.size as usize, 0);
+            new_features.read_exact(&mut buf)?;
+            out.write_all(&buf)?;
+        }
+        out.flush()?;
+        drop(out);
+        tmpfile
+            .as_file()
+            .set_permissions(self.file.metadata()?.permissions())?;
+        tmpfile
+            .persist(&self.path)
+            .map_err(|e| Error::IO(e.error))?;
+        Ok(())
+    }
+}
+
+/// Copy `header` with updated features count, envelope and index node size
+pub(crate) fn copy_header<'a>(
+    fbb: &mut FlatBufferBuilder<'a>,
+    header: Header,
+    features_count: u64,
+    envelope: &NodeItem,
+    index_node_size: u16,
+) -> flatbuffers::WIPOffset<Header<'a>> {
+    let columns = header.columns().map(|columns| {
+        let columns = columns
+            .iter()
+            .map(|column| {
+                let args = ColumnArgs {
+                    name: Some(fbb.create_string(column.name())),
+                    type_: column.type_(),
+                    title: column.title().map(|v| fbb.create_string(v)),
+                    description: column.description().map(|v| fbb.create_string(v)),
+                    width: column.width(),
+                    precision: column.precision(),
+                    scale: column.scale(),
+                    nullable: column.nullable(),
+                    unique: column.unique(),
+                    primary_key: column.primary_key(),
+                    metadata: column.metadata().map(|v| fbb.create_string(v)),
+                };
+                Column::create(fbb, &args)
+            })
+            .collect::<Vec<_>>();
+        fbb.create_vector(&columns)
+    });
+    let crs = header.crs().map(|crs| {
+        let args = CrsArgs {
+            org: crs.org().map(|v| fbb.create_string(v)),
+            code: crs.code(),
+            name: crs.name().map(|v| fbb.create_string(v)),
+            description: crs.description().map(|v| fbb.create_string(v)),
+            wkt: crs.wkt().map(|v| fbb.create_string(v)),
+            code_string: crs.code_string().map(|v| fbb.create_string(v)),
+        };
+        Crs::create(fbb, &args)
+    });
+    let args = HeaderArgs {
+        name: header.name().map(|v| fbb.create_string(v)),
+        envelope: Some(fbb.create_vector(&[
+            envelope.min_x,
+            envelope.min_y,
+            envelope.max_x,
+            envelope.max_y,
+        ])),
+        geometry_type: header.geometry_type(),
+        has_z: header.has_z(),
+        has_m: header.has_m(),
+        has_t: header.has_t(),
+        has_tm: header.has_tm(),
+        columns,
+        features_count,
+        index_node_size,
+        crs,
+        title: header.title().map(|v| fbb.create_string(v)),
+        description: header.description().map(|v| fbb.create_string(v)),
+        metadata: header.metadata().map(|v| fbb.create_string(v)),
+    };
+    Header::create(fbb, &args)
+}
+
+mod geozero_api {
+    use crate::feature_writer::FeatureWriter;
+    use crate::file_writer::{delegate_geom_processor, feature_error};
+    use crate::FgbAppender;
+    use geozero::{
+        error::Result, ColumnValue, FeatureProcessor, GeozeroDatasource, GeozeroGeometry,
+        PropertyProcessor,
+    };
+
+    impl FgbAppender<'_> {
+        /// Add a new feature.
+        ///
+        /// See [`FgbWriter::add_feature`](crate::FgbWriter::add_feature).
+        ///
+        /// A feature which fails to be added is discarded.
+        pub fn add_feature(&mut self, mut feature: impl GeozeroDatasource) -> Result<()> {
+            let result = feature.process(self);
+            if result.is_err() {
+                self.feat_writer.discard_feature();
+            }
+            result
+        }
+
+        /// Add a new feature from a `GeozeroGeometry`.
+        ///
+        /// Properties are assigned to the dataset columns by name, like with
+        /// [`FgbAppender::add_feature`].
+        /// See [`FgbWriter::add_feature_geom`](crate::FgbWriter::add_feature_geom).
+        pub fn add_feature_geom<F>(&mut self, geom: impl GeozeroGeometry, cfgfn: F) -> Result<()>
+        where
+            F: FnOnce(&mut FeatureWriter),
+        {
+            if let Err(e) = geom.process_geom(&mut self.feat_writer) {
+                self.feat_writer.discard_feature();
+                return Err(e);
+            }
+            cfgfn(&mut self.feat_writer);
+            self.write_feature().map_err(feature_error)
+        }
+    }
+
+    impl FeatureProcessor for FgbAppender<'_> {
+        fn feature_end(&mut self, _idx: u64) -> Result<()> {
+            self.write_feature().map_err(feature_error)
+        }
+    }
+
+    impl PropertyProcessor for FgbAppender<'_> {
+        fn property(&mut self, i: usize, colname: &str, colval: &ColumnValue) -> Result<bool> {
+            // Assigned by name and converted by the feature writer
+            self.feat_writer.property(i, colname, colval)
+        }
+    }
+
+    // Delegate GeomProcessor to self.feat_writer
+    delegate_geom_processor!([] FgbAppender<'_>);
+}
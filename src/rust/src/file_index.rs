@@ -0,0 +1,291 @@
+use crate::error::{Error, Result};
+use crate::feature_generated::Geometry;
+use crate::file_appender::copy_header;
+use crate::header_generated::Header;
+use crate::packed_r_tree::{
+    calc_extent, hilbert_sort, MultiSearchResult, NodeItem, PackedRTree, SearchResultItem,
+};
+#[cfg(feature = "http")]
+use crate::packed_r_tree::{HttpRange, HttpSearchResultItem};
+use crate::{FallibleStreamingIterator, FgbFeature, FgbReader, MAGIC_BYTES};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use flatbuffers::FlatBufferBuilder;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Magic bytes of index sidecar files
+const INDEX_MAGIC_BYTES: [u8; 8] = [b'f', b'g', b'b', b'i', b'd', b'x', 1, 0];
+
+/// Spatial index over the features of a dataset, kept outside of the dataset
+///
+/// Built by scanning a dataset written without index, the features are indexed in file order
+/// without rewriting them. The index can be saved as a `.fgb.idx` sidecar file and used with
+/// [`FgbReader::with_index`], [`AsyncFgbReader::with_index`](crate::AsyncFgbReader::with_index)
+/// or [`HttpFgbReader::with_index`](crate::HttpFgbReader::with_index) for bbox queries.
+///
+/// # Usage example:
+///
+/// ```
+/// use flatgeobuf::*;
+/// # use std::fs::File;
+/// # use std::io::BufReader;
+///
+/// # fn read_fbg() -> std::result::Result<(), Box<dyn std::error::Error>> {
+/// let index = FgbIndex::build(BufReader::new(File::open("unindexed.fgb")?), 16)?;
+/// index.write_sidecar("unindexed.fgb")?;
+///
+/// let index = FgbIndex::read_sidecar("unindexed.fgb")?;
+/// let mut filein = BufReader::new(File::open("unindexed.fgb")?);
+/// let fgb = FgbReader::open(&mut filein)?
+///     .with_index(index)?
+///     .select_bbox(8.8, 47.2, 9.5, 55.3)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct FgbIndex {
+    tree: PackedRTree,
+    features_count: usize,
+    node_size: u16,
+}
+
+impl FgbIndex {
+    /// Build an index with branching factor `node_size` over all features of a dataset.
+    ///
+    /// Fails with [`Error::NoIndex`] for datasets without features.
+    pub fn build(reader: impl Read, node_size: u16) -> Result<Self> {
+        let node_size = node_size.clamp(2, 65535);
+        let (nodes, _) = scan_features(FgbReader::open(reader)?)?;
+        PackedRTree::validate_num_items(nodes.len())?;
+        let tree = PackedRTree::build(&nodes, &calc_extent(&nodes), node_size)?;
+        Ok(FgbIndex {
+            tree,
+            features_count: nodes.len(),
+            node_size,
+        })
+    }
+
+    /// Read an index written with [`FgbIndex::write`].
+    pub fn read(mut reader: impl Read) -> Result<Self> {
+        let mut magic_buf = [0; 8];
+        reader.read_exact(&mut magic_buf)?;
+        if magic_buf != INDEX_MAGIC_BYTES {
+            return Err(invalid_index("missing magic bytes of index file"));
+        }
+        let node_size = reader.read_u16::<LittleEndian>()?;
+        let features_count = reader.read_u64::<LittleEndian>()? as usize;
+        if node_size < 2 {
+            return Err(invalid_index("invalid node size"));
+        }
+        PackedRTree::validate_num_items(features_count)?;
+        let tree = PackedRTree::from_buf(reader, features_count, node_size)?;
+        Ok(FgbIndex {
+            tree,
+            features_count,
+            node_size,
+        })
+    }
+
+    /// Write the index, prefixed with magic bytes, node size and features count.
+    pub fn write(&self, mut out: impl Write) -> Result<()> {
+        out.write_all(&INDEX_MAGIC_BYTES)?;
+        out.write_u16::<LittleEndian>(self.node_size)?;
+        out.write_u64::<LittleEndian>(self.features_count as u64)?;
+        self.tree.stream_write(&mut out)?;
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Path of the index sidecar file of a dataset, e.g. `countries.fgb.idx` for `countries.fgb`
+    pub fn sidecar_path(dataset: impl AsRef<Path>) -> PathBuf {
+        let mut path = dataset.as_ref().as_os_str().to_owned();
+        path.push(".idx");
+        PathBuf::from(path)
+    }
+
+    /// Read the index sidecar file of a dataset.
+    pub fn read_sidecar(dataset: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(Self::sidecar_path(dataset))?;
+        Self::read(BufReader::new(file))
+    }
+
+    /// Write the index as sidecar file of a dataset.
+    pub fn write_sidecar(&self, dataset: impl AsRef<Path>) -> Result<()> {
+        let file = File::create(Self::sidecar_path(dataset))?;
+        self.write(BufWriter::new(file))
+    }
+
+    /// Number of indexed features
+    pub fn features_count(&self) -> usize {
+        self.features_count
+    }
+
+    /// Branching factor of the index
+    pub fn node_size(&self) -> u16 {
+        self.node_size
+    }
+
+    /// Bounds of all indexed features
+    pub fn extent(&self) -> NodeItem {
+        self.tree.extent()
+    }
+
+    /// Fail if the index does not match the features count of a dataset header.
+    pub(crate) fn check_features_count(&self, header: Header) -> Result<()> {
+        let features_count = header.features_count() as usize;
+        if features_count != 0 && features_count != self.features_count {
+            return Err(invalid_index(&format!(
+                "Index of {} features does not match dataset with {features_count} features",
+                self.features_count
+            )));
+        }
+        Ok(())
+    }
+
+    /// Features within a bounding box, with offsets relative to the first feature
+    pub(crate) fn search(
+        &self,
+        min_x: f64,
+        min_y: f64,
+        max_x: f64,
+        max_y: f64,
+    ) -> Result<Vec<SearchResultItem>> {
+        self.tree.search(min_x, min_y, max_x, max_y)
+    }
+
+    /// Features within any of the bounding boxes, with offsets relative to the first feature
+    pub(crate) fn search_many(
+        &self,
+        queries: &[NodeItem],
+    ) -> Result<MultiSearchResult<SearchResultItem>> {
+        self.tree.search_many(queries)
+    }
+
+    /// Byte ranges of search results in a dataset with the first feature at `feature_begin`
+    #[cfg(feature = "http")]
+    pub(crate) fn http_ranges(
+        &self,
+        items: Vec<SearchResultItem>,
+        feature_begin: usize,
+    ) -> Vec<HttpSearchResultItem> {
+        items
+            .into_iter()
+            .map(|item| {
+                let start = feature_begin + item.offset;
+                let range = match self.tree.leaf_offset(item.index + 1) {
+                    Some(end) => HttpRange::Range(start..feature_begin + end as usize),
+                    None => HttpRange::RangeFrom(start..),
+                };
+                HttpSearchResultItem { range }
+            })
+            .collect()
+    }
+
+    /// Copy a dataset to `out` with features in Hilbert order and an embedded index with
+    /// branching factor `node_size`.
+    ///
+    /// Features are copied without decoding. Header fields are kept, except for the features
+    /// count, envelope and index node size. Fails with [`Error::NoIndex`] for datasets without
+    /// features.
+    pub fn rewrite_indexed<R: Read + Seek>(
+        mut input: R,
+        mut out: impl Write,
+        node_size: u16,
+    ) -> Result<()> {
+        let node_size = node_size.clamp(2, 65535);
+        let start = input.stream_position()?;
+        let fgb = FgbReader::open(&mut input)?;
+        let feature_begin = start + fgb.feature_begin();
+        let (mut nodes, sizes) = scan_features(fgb)?;
+        PackedRTree::validate_num_items(nodes.len())?;
+        let extent = calc_extent(&nodes);
+
+        input.seek(SeekFrom::Start(start))?;
+        let fgb = FgbReader::open(&mut input)?;
+        let mut fbb = FlatBufferBuilder::new();
+        let header = copy_header(
+            &mut fbb,
+            fgb.header(),
+            nodes.len() as u64,
+            &extent,
+            node_size,
+        );
+        fbb.finish_size_prefixed(header, None);
+        out.write_all(&MAGIC_BYTES)?;
+        out.write_all(fbb.finished_data())?;
+
+        // Offsets in the input, indexed by feature number
+        let mut offsets = Vec::with_capacity(nodes.len());
+        for (i, node) in nodes.iter_mut().enumerate() {
+            offsets.push(node.offset);
+            node.offset = i as u64;
+        }
+        hilbert_sort(&mut nodes, &extent);
+        let mut offset = 0;
+        let index_nodes = nodes
+            .iter()
+            .map(|node| {
+                let mut index_node = node.clone();
+                index_node.offset = offset;
+                offset += sizes[node.offset as usize];
+                index_node
+            })
+            .collect::<Vec<_>>();
+        PackedRTree::build(&index_nodes, &extent, node_size)?.stream_write(&mut out)?;
+
+        let mut buf = Vec::with_capacity(2048);
+        for node in &nodes {
+            let i = node.offset as usize;
+            input.seek(SeekFrom::Start(feature_begin + offsets[i]))?;
+            buf.resize(sizes[i] as usize, 0);
+            input.read_exact(&mut buf)?;
+            out.write_all(&buf)?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+}
+
+fn invalid_index(msg: &str) -> Error {
+    Error::IO(io::Error::new(io::ErrorKind::InvalidData, msg))
+}
+
+/// Leaf nodes of all features in file order, with offsets relative to the first feature, and
+/// the feature sizes
+fn scan_features<R: Read>(fgb: FgbReader<R>) -> Result<(Vec<NodeItem>, Vec<u64>)> {
+    let mut features = fgb.select_all_seq()?;
+    let mut nodes = Vec::new();
+    let mut sizes = Vec::new();
+    let mut offset = 0;
+    while let Some(feature) = features.next()? {
+        let size = feature.feature_buf.len() as u64;
+        let mut node = feature_bounds(feature);
+        node.offset = offset;
+        nodes.push(node);
+        sizes.push(size);
+        offset += size;
+    }
+    Ok((nodes, sizes))
+}
+
+/// Bounds of the feature geometry, as computed by the writer
+fn feature_bounds(feature: &FgbFeature) -> NodeItem {
+    let mut bbox = NodeItem::create(0);
+    if let Some(geometry) = feature.geometry() {
+        expand_geometry(&mut bbox, geometry);
+    }
+    bbox
+}
+
+fn expand_geometry(bbox: &mut NodeItem, geometry: Geometry) {
+    if let Some(xy) = geometry.xy() {
+        for i in 0..xy.len() / 2 {
+            bbox.expand_xy(xy.get(2 * i), xy.get(2 * i + 1));
+        }
+    }
+    if let Some(parts) = geometry.parts() {
+        for part in parts.iter() {
+            expand_geometry(bbox, part);
+        }
+    }
+}
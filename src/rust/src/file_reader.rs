@@ -1,21 +1,33 @@
 use crate::feature_generated::*;
+use crate::file_index::FgbIndex;
+use crate::geometry_predicates::{SpatialFilter, SpatialPredicate};
 use crate::header_generated::*;
-use crate::packed_r_tree::{self, PackedRTree};
-use crate::properties_reader::FgbFeature;
+use crate::packed_r_tree::{self, NodeItem, PackedRTree};
+use crate::properties_reader::{
+    FeatureContext, FeatureGroups, FgbFeature, OwnedFeature, Projection,
+};
+use crate::property_filter::{Expr, PropertyFilter};
 use crate::{check_magic_bytes, FEATURE_MAX_BUFFER_SIZE, HEADER_MAX_BUFFER_SIZE};
 use crate::{Error, Result};
 use fallible_streaming_iterator::FallibleStreamingIterator;
+use geo_traits::{GeometryTrait, PointTrait};
 use std::io::{self, Read, Seek, SeekFrom};
 use std::marker::PhantomData;
+use std::mem::size_of;
+use std::sync::Arc;
 
 /// FlatGeobuf dataset reader
 pub struct FgbReader<R> {
     reader: R,
     /// FlatBuffers verification
-    verify: bool,
+    pub(crate) verify: bool,
+    /// Reader position relative to the start of the dataset
+    pos: u64,
     // feature reading requires header access, therefore
     // header_buf is included in the FgbFeature struct.
     fbs: FgbFeature,
+    /// Index replacing the index of the dataset
+    external_index: Option<FgbIndex>,
 }
 
 pub struct FeatureIter<R, S> {
@@ -24,9 +36,13 @@ pub struct FeatureIter<R, S> {
     verify: bool,
     // feature reading requires header access, therefore
     // header_buf is included in the FgbFeature struct.
-    fbs: FgbFeature,
+    pub(crate) fbs: FgbFeature,
     /// Selected features or None if no bbox filter
     item_filter: Option<Vec<packed_r_tree::SearchResultItem>>,
+    /// Exact geometry test applied to the selected features
+    spatial_filter: Option<SpatialFilter>,
+    /// Property filter applied to the selected features
+    property_filter: Option<PropertyFilter>,
     /// Number of selected features (None for undefined feature count)
     count: Option<usize>,
     /// Current feature number
@@ -96,13 +112,26 @@ impl<R: Read> FgbReader<R> {
         Ok(FgbReader {
             reader,
             verify,
+            pos: 12 + header_size as u64,
             fbs: FgbFeature {
                 header_buf,
                 feature_buf: Vec::new(),
+                projection: None,
+                explicit_nulls: false,
             },
+            external_index: None,
         })
     }
 
+    /// Use an index built with [`FgbIndex`] for bbox queries, e.g. for datasets without index.
+    ///
+    /// Fails if the index does not match the features count of the header.
+    pub fn with_index(mut self, index: FgbIndex) -> Result<Self> {
+        index.check_features_count(self.fbs.header())?;
+        self.external_index = Some(index);
+        Ok(self)
+    }
+
     /// Select all features without using seek.
     ///
     /// This can be used to read from an input stream.
@@ -125,6 +154,18 @@ impl<R: Read> FgbReader<R> {
         max_x: f64,
         max_y: f64,
     ) -> Result<FeatureIter<R, NotSeekable>> {
+        if let Some(index) = self.external_index.take() {
+            let list = index.search(min_x, min_y, max_x, max_y)?;
+            self.check_index_features_count()?;
+            let index_size = self.index_size();
+            io::copy(&mut (&mut self.reader).take(index_size), &mut io::sink())?;
+            return Ok(FeatureIter::new(
+                self.reader,
+                self.verify,
+                self.fbs,
+                Some(list),
+            ));
+        }
         // Read R-Tree index and build filter for features within bbox
         let header = self.fbs.header();
         if header.index_node_size() == 0 || header.features_count() == 0 {
@@ -156,8 +197,8 @@ impl<R: Read + Seek> FgbReader<R> {
     pub fn select_all(mut self) -> Result<FeatureIter<R, Seekable>> {
         self.check_index_features_count()?;
         // skip index
-        let index_size = self.index_size();
-        self.reader.seek(SeekFrom::Current(index_size as i64))?;
+        let feature_begin = self.feature_begin();
+        self.seek_to(feature_begin)?;
 
         Ok(FeatureIter::new(self.reader, self.verify, self.fbs, None))
     }
@@ -169,16 +210,31 @@ impl<R: Read + Seek> FgbReader<R> {
         max_x: f64,
         max_y: f64,
     ) -> Result<FeatureIter<R, Seekable>> {
+        if let Some(index) = self.external_index.take() {
+            let list = index.search(min_x, min_y, max_x, max_y)?;
+            self.check_index_features_count()?;
+            let feature_begin = self.feature_begin();
+            self.seek_to(feature_begin)?;
+            return Ok(FeatureIter::new(
+                self.reader,
+                self.verify,
+                self.fbs,
+                Some(list),
+            ));
+        }
         // Read R-Tree index and build filter for features within bbox
         let header = self.fbs.header();
         if header.index_node_size() == 0 || header.features_count() == 0 {
             return Err(Error::NoIndex);
         }
         PackedRTree::validate_num_items(header.features_count() as usize)?;
+        let index_begin = self.header_len();
+        self.seek_to(index_begin)?;
+        let header = self.fbs.header();
         let list = PackedRTree::stream_search(
             &mut self.reader,
             header.features_count() as usize,
-            PackedRTree::DEFAULT_NODE_SIZE,
+            header.index_node_size(),
             min_x,
             min_y,
             max_x,
@@ -196,6 +252,152 @@ impl<R: Read + Seek> FgbReader<R> {
             Some(list),
         ))
     }
+
+    /// Select features intersecting a geometry.
+    ///
+    /// The index is searched with the bounding box of `geom` and the candidates are refined with an
+    /// exact intersection test against their geometry. The number of selected features is not
+    /// known in advance.
+    pub fn select_intersecting(
+        self,
+        geom: &impl GeometryTrait<T = f64>,
+    ) -> Result<FeatureIter<R, Seekable>> {
+        self.select_spatial(SpatialFilter::new(SpatialPredicate::Intersects, geom)?)
+    }
+
+    /// Select features within a polygon or multipolygon.
+    ///
+    /// Features touching the polygon boundary from inside are selected, features lying completely
+    /// on the boundary are not. See [`select_intersecting`](Self::select_intersecting) for details.
+    pub fn select_within(
+        self,
+        polygon: &impl GeometryTrait<T = f64>,
+    ) -> Result<FeatureIter<R, Seekable>> {
+        self.select_spatial(SpatialFilter::new(SpatialPredicate::Within, polygon)?)
+    }
+
+    /// Select features containing a point.
+    ///
+    /// Points on the boundary of a feature, like the ring of a polygon, are not contained.
+    /// See [`select_intersecting`](Self::select_intersecting) for details.
+    pub fn select_containing(
+        self,
+        point: &impl PointTrait<T = f64>,
+    ) -> Result<FeatureIter<R, Seekable>> {
+        self.select_spatial(SpatialFilter::new(SpatialPredicate::Contains, point)?)
+    }
+
+    fn select_spatial(self, filter: SpatialFilter) -> Result<FeatureIter<R, Seekable>> {
+        let bbox = filter.bbox();
+        let mut iter = self.select_bbox(bbox.min_x, bbox.min_y, bbox.max_x, bbox.max_y)?;
+        iter.spatial_filter = Some(filter);
+        Ok(iter)
+    }
+
+    /// Select features within several bounding boxes.
+    ///
+    /// The index is traversed once for all bounding boxes and each selected feature is read
+    /// once, also when it lies within more than one bounding box. The features are grouped by
+    /// bounding box in the result.
+    ///
+    /// ```rust
+    /// use flatgeobuf::{packed_r_tree::NodeItem, *};
+    /// # use std::fs::File;
+    /// # use std::io::BufReader;
+    ///
+    /// # fn read_fbg() -> std::result::Result<(), Box<dyn std::error::Error>> {
+    /// # let mut filein = BufReader::new(File::open("countries.fgb")?);
+    /// let tiles = [
+    ///     NodeItem::bounds(0.0, 45.0, 5.0, 50.0),
+    ///     NodeItem::bounds(5.0, 45.0, 10.0, 50.0),
+    /// ];
+    /// let selection = FgbReader::open(&mut filein)?.select_bbox_many(&tiles)?;
+    /// for feature in selection.group(1) {
+    ///     println!("{}", feature.property::<String>("name")?);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn select_bbox_many(mut self, bboxes: &[NodeItem]) -> Result<FeatureGroups> {
+        let result = if let Some(index) = self.external_index.take() {
+            let result = index.search_many(bboxes)?;
+            self.check_index_features_count()?;
+            let feature_begin = self.feature_begin();
+            self.seek_to(feature_begin)?;
+            result
+        } else {
+            let header = self.fbs.header();
+            if header.index_node_size() == 0 || header.features_count() == 0 {
+                return Err(Error::NoIndex);
+            }
+            PackedRTree::validate_num_items(header.features_count() as usize)?;
+            let index_begin = self.header_len();
+            self.seek_to(index_begin)?;
+            let header = self.fbs.header();
+            PackedRTree::stream_search_many(
+                &mut self.reader,
+                header.features_count() as usize,
+                header.index_node_size(),
+                bboxes,
+            )?
+        };
+        let features =
+            FeatureIter::<R, Seekable>::new(self.reader, self.verify, self.fbs, Some(result.items))
+                .into_iter_owned()
+                .collect::<Result<Vec<_>>>()?;
+        Ok(FeatureGroups::new(features, result.groups))
+    }
+
+    /// Read a single feature by its feature number, i.e. its position in the file.
+    ///
+    /// The leaf level of the index is used as an offset table, so any feature can be reached with
+    /// two seeks instead of a linear scan. Returns `None` if `n` is not below the feature count.
+    /// Fails with [`Error::NoIndex`] for files without index.
+    ///
+    /// The reader can still be used for a selection afterwards, but only with the seeking
+    /// [`select_all`](Self::select_all) and [`select_bbox`](Self::select_bbox) variants.
+    pub fn feature(&mut self, n: usize) -> Result<Option<&FgbFeature>> {
+        let header = self.fbs.header();
+        let features_count = header.features_count() as usize;
+        let index_node_size = header.index_node_size();
+        if index_node_size == 0 || features_count == 0 {
+            return Err(Error::NoIndex);
+        }
+        let leaf_nodes_offset = PackedRTree::leaf_nodes_offset(features_count, index_node_size)?;
+        if n >= features_count {
+            return Ok(None);
+        }
+        let index_begin = self.header_len();
+        self.seek_to(index_begin + ((leaf_nodes_offset + n) * size_of::<NodeItem>()) as u64)?;
+        let node = NodeItem::from_reader(&mut self.reader)?;
+        self.pos += size_of::<NodeItem>() as u64;
+
+        self.seek_to(index_begin + self.index_size() + node.offset)?;
+        let mut size_buf: [u8; 4] = [0; 4];
+        self.reader.read_exact(&mut size_buf)?;
+        self.pos += 4;
+        let feature_size = u32::from_le_bytes(size_buf) as usize;
+        check_feature_size(feature_size)?;
+        let feature_buf = &mut self.fbs.feature_buf;
+        feature_buf.clear();
+        feature_buf.extend_from_slice(&size_buf);
+        feature_buf.resize(feature_size + 4, 0);
+        self.reader.read_exact(&mut feature_buf[4..])?;
+        self.pos += feature_size as u64;
+        if self.verify {
+            let _feature = size_prefixed_root_as_feature(feature_buf)?;
+        }
+        Ok(Some(&self.fbs))
+    }
+
+    fn seek_to(&mut self, pos: u64) -> Result<()> {
+        if pos != self.pos {
+            self.reader
+                .seek(SeekFrom::Current(pos as i64 - self.pos as i64))?;
+            self.pos = pos;
+        }
+        Ok(())
+    }
 }
 
 impl<R: Read> FgbReader<R> {
@@ -204,6 +406,20 @@ impl<R: Read> FgbReader<R> {
         self.fbs.header()
     }
 
+    /// Size-prefixed header buffer
+    pub(crate) fn into_header_buf(self) -> Vec<u8> {
+        self.fbs.header_buf
+    }
+
+    fn header_len(&self) -> u64 {
+        8 + self.fbs.header_buf.len() as u64
+    }
+
+    /// Position of the first feature relative to the start of the dataset
+    pub(crate) fn feature_begin(&self) -> u64 {
+        self.header_len() + self.index_size()
+    }
+
     fn index_size(&self) -> u64 {
         let header = self.fbs.header();
         let feat_count = header.features_count() as usize;
@@ -257,22 +473,10 @@ impl<R: Read> FallibleStreamingIterator for FeatureIter<R, NotSeekable> {
     type Error = Error;
 
     fn advance(&mut self) -> Result<()> {
-        if self.advance_finished() {
-            return Ok(());
-        }
-        if let Some(filter) = &self.item_filter {
-            let item = &filter[self.feat_no];
-            if item.offset as u64 > self.cur_pos {
-                if self.state == State::ReadFirstFeatureSize {
-                    self.state = State::Reading;
-                }
-                // skip features
-                let seek_bytes = item.offset as u64 - self.cur_pos;
-                io::copy(&mut (&mut self.reader).take(seek_bytes), &mut io::sink())?;
-                self.cur_pos += seek_bytes;
-            }
-        }
-        self.read_feature()
+        self.advance_selected(|reader, bytes| {
+            io::copy(&mut reader.take(bytes), &mut io::sink())?;
+            Ok(())
+        })
     }
 
     fn get(&self) -> Option<&FgbFeature> {
@@ -312,22 +516,10 @@ impl<R: Read + Seek> FallibleStreamingIterator for FeatureIter<R, Seekable> {
     type Error = Error;
 
     fn advance(&mut self) -> Result<()> {
-        if self.advance_finished() {
-            return Ok(());
-        }
-        if let Some(filter) = &self.item_filter {
-            let item = &filter[self.feat_no];
-            if item.offset as u64 > self.cur_pos {
-                if self.state == State::ReadFirstFeatureSize {
-                    self.state = State::Reading;
-                }
-                // skip features
-                let seek_bytes = item.offset as u64 - self.cur_pos;
-                self.reader.seek(SeekFrom::Current(seek_bytes as i64))?;
-                self.cur_pos += seek_bytes;
-            }
-        }
-        self.read_feature()
+        self.advance_selected(|reader, bytes| {
+            reader.seek(SeekFrom::Current(bytes as i64))?;
+            Ok(())
+        })
     }
 
     fn get(&self) -> Option<&FgbFeature> {
@@ -339,6 +531,74 @@ impl<R: Read + Seek> FallibleStreamingIterator for FeatureIter<R, Seekable> {
     }
 }
 
+impl<R, S> FeatureIter<R, S>
+where
+    Self: FallibleStreamingIterator<Item = FgbFeature, Error = Error>,
+{
+    /// Iterate over owned features.
+    ///
+    /// Unlike the features of [`FallibleStreamingIterator::next`], owned features stay valid
+    /// after the next feature is read, so they can be collected, zipped or sent to other
+    /// threads. The header is shared between the features, only the feature bytes are copied.
+    ///
+    /// ```rust
+    /// use flatgeobuf::*;
+    /// # use std::fs::File;
+    /// # use std::io::BufReader;
+    ///
+    /// # fn read_fbg() -> std::result::Result<(), Box<dyn std::error::Error>> {
+    /// # let mut filein = BufReader::new(File::open("countries.fgb")?);
+    /// let fgb = FgbReader::open(&mut filein)?.select_all()?;
+    /// let features = fgb.into_iter_owned().collect::<Result<Vec<_>>>()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn into_iter_owned(self) -> OwnedFeatureIter<R, S> {
+        OwnedFeatureIter {
+            context: FeatureContext::new(&self.fbs),
+            iter: self,
+            failed: false,
+        }
+    }
+}
+
+/// Iterator over owned features, see [`FeatureIter::into_iter_owned`]
+pub struct OwnedFeatureIter<R, S> {
+    iter: FeatureIter<R, S>,
+    context: Arc<FeatureContext>,
+    /// Stop after the first error
+    failed: bool,
+}
+
+impl<R, S> Iterator for OwnedFeatureIter<R, S>
+where
+    FeatureIter<R, S>: FallibleStreamingIterator<Item = FgbFeature, Error = Error>,
+{
+    type Item = Result<OwnedFeature>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        match self.iter.next().map(|feature| feature.is_some()) {
+            Ok(true) => Some(Ok(OwnedFeature::take(&mut self.iter.fbs, &self.context))),
+            Ok(false) => None,
+            Err(e) => {
+                self.failed = true;
+                Some(Err(e))
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.failed {
+            (0, Some(0))
+        } else {
+            self.iter.size_hint()
+        }
+    }
+}
+
 mod geozero_api {
     use crate::reader_trait::{NotSeekable, Seekable};
     use crate::{FeatureIter, FgbFeature};
@@ -430,6 +690,7 @@ mod geozero_api {
                 let index =
                     PackedRTree::from_buf(&mut self.reader, features_count, index_node_size)
                         .map_err(|_| GeozeroError::GeometryIndex)?;
+                self.pos += index.size() as u64;
                 index.process_index(processor)
             }
         }
@@ -463,6 +724,8 @@ impl<R: Read, S> FeatureIter<R, S> {
             verify,
             fbs,
             item_filter,
+            spatial_filter: None,
+            property_filter: None,
             count: None,
             feat_no: 0,
             cur_pos: 0,
@@ -500,7 +763,96 @@ impl<R: Read, S> FeatureIter<R, S> {
 
     /// Number of selected features (None for undefined feature count)
     pub fn features_count(&self) -> Option<usize> {
-        self.count
+        if self.is_filtered() {
+            None
+        } else {
+            self.count
+        }
+    }
+
+    /// Skip features not matching the property filter expression.
+    ///
+    /// The expression is evaluated on the encoded properties, so non-matching features are not
+    /// decoded. Calling this again combines the expressions with `AND`. Fails with
+    /// [`Error::UnknownColumn`] if the expression refers to a column not in the dataset.
+    /// Columns of features with their own columns are matched by name.
+    pub fn filter_properties(mut self, expr: Expr) -> Result<Self> {
+        let filter = PropertyFilter::new(expr, self.fbs.header())?;
+        self.property_filter = Some(match self.property_filter.take() {
+            Some(existing) => existing.and(filter),
+            None => filter,
+        });
+        Ok(self)
+    }
+
+    /// Decode only the named columns.
+    ///
+    /// Values of other columns are skipped without decoding, and `PropertyProcessor` consumers
+    /// see the selected columns re-indexed in the given order. Property filters still apply to
+    /// all columns. Fails with [`Error::UnknownColumn`] if a column is not in the dataset.
+    /// Columns of features with their own columns are matched by name.
+    pub fn columns(mut self, names: &[&str]) -> Result<Self> {
+        self.fbs.projection = Some(Projection::new(self.fbs.header(), names)?);
+        Ok(self)
+    }
+
+    /// Report columns without value in a feature as JSON `null` values.
+    ///
+    /// By default, `PropertyProcessor` consumers only see the columns with a value, so e.g.
+    /// GeoJSON output omits null properties. With explicit nulls, all (selected) columns are
+    /// processed in column order and missing values are passed as `ColumnValue::Json("null")`.
+    /// See [`FgbFeature::process_properties_with_nulls`] for columns without value with their
+    /// column type.
+    pub fn explicit_nulls(mut self) -> Self {
+        self.fbs.explicit_nulls = true;
+        self
+    }
+
+    fn is_filtered(&self) -> bool {
+        self.spatial_filter.is_some() || self.property_filter.is_some()
+    }
+
+    /// Whether the current feature passes the property and spatial filters
+    fn feature_selected(&self) -> Result<bool> {
+        if let Some(filter) = &self.property_filter {
+            if !filter.matches(self.fbs.header(), self.fbs.fbs_feature())? {
+                return Ok(false);
+            }
+        }
+        match &self.spatial_filter {
+            Some(filter) => filter.matches(&self.fbs),
+            None => Ok(true),
+        }
+    }
+
+    /// Advance to the next selected feature, skipping features rejected by the filters.
+    ///
+    /// `skip` moves the reader forward by the given number of bytes.
+    fn advance_selected(&mut self, skip: fn(&mut R, u64) -> io::Result<()>) -> Result<()> {
+        loop {
+            if self.advance_finished() {
+                return Ok(());
+            }
+            if let Some(filter) = &self.item_filter {
+                let item = &filter[self.feat_no];
+                if item.offset as u64 > self.cur_pos {
+                    if self.state == State::ReadFirstFeatureSize {
+                        self.state = State::Reading;
+                    }
+                    // skip features
+                    let seek_bytes = item.offset as u64 - self.cur_pos;
+                    skip(&mut self.reader, seek_bytes)?;
+                    self.cur_pos += seek_bytes;
+                }
+            }
+            self.read_feature()?;
+            if self.state == State::Finished {
+                return Ok(());
+            }
+            if self.feature_selected()? {
+                return Ok(());
+            }
+        }
     }
 
     fn advance_finished(&mut self) -> bool {
@@ -547,14 +899,7 @@ impl<R: Read, S> FeatureIter<R, S> {
         }
         let sbuf = &self.fbs.feature_buf;
         let feature_size = u32::from_le_bytes([sbuf[0], sbuf[1], sbuf[2], sbuf[3]]) as usize;
-        if feature_size > FEATURE_MAX_BUFFER_SIZE {
-            return Err(Error::IO(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                format!(
-                    "declared feature size {feature_size} exceeds the per-feature buffer budget"
-                ),
-            )));
-        }
+        check_feature_size(feature_size)?;
         self.fbs.feature_buf.resize(feature_size + 4, 0);
         self.reader.read_exact(&mut self.fbs.feature_buf[4..])?;
         if self.verify {
@@ -579,9 +924,23 @@ impl<R: Read, S> FeatureIter<R, S> {
             (0, Some(0))
         } else if let Some(count) = self.count {
             let remaining = count - self.feat_no;
-            (remaining, Some(remaining))
+            if self.is_filtered() {
+                (0, Some(remaining))
+            } else {
+                (remaining, Some(remaining))
+            }
         } else {
             (0, None)
         }
     }
 }
+
+pub(crate) fn check_feature_size(feature_size: usize) -> Result<()> {
+    if feature_size > FEATURE_MAX_BUFFER_SIZE {
+        return Err(Error::IO(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("declared feature size {feature_size} exceeds the per-feature buffer budget"),
+        )));
+    }
+    Ok(())
+}
@@ -1,12 +1,15 @@
-use crate::error::Result;
+use crate::error::{Error, Result};
+use crate::external_sort::ExternalNodeSort;
 use crate::feature_writer::FeatureWriter;
 use crate::header_generated::{ColumnType, Crs, CrsArgs, GeometryType};
-use crate::packed_r_tree::{calc_extent, hilbert_sort, NodeItem, PackedRTree};
+use crate::packed_r_tree::{
+    calc_extent, hilbert_sort, morton_sort, str_sort, NodeItem, PackedRTree,
+};
 use crate::{Column, ColumnArgs, Header, HeaderArgs, MAGIC_BYTES};
 use flatbuffers::FlatBufferBuilder;
 use geozero::CoordDimensions;
 use std::fs::File;
-use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
 
 /// FlatGeobuf dataset writer
 ///
@@ -37,6 +40,9 @@ pub struct FgbWriter<'a> {
     feat_writer: FeatureWriter<'a>,
     feat_offsets: Vec<FeatureOffset>,
     feat_nodes: Vec<NodeItem>,
+    sort: SortOrder,
+    /// Leaf nodes within the memory budget, replacing `feat_offsets` and `feat_nodes`
+    node_sort: Option<ExternalNodeSort>,
 }
 
 /// Options for FlatGeobuf writer
@@ -44,6 +50,8 @@ pub struct FgbWriter<'a> {
 pub struct FgbWriterOptions<'a> {
     /// Write index and sort features accordingly.
     pub write_index: bool,
+    /// Branching factor of the index, clamped to `[2, 65535]`.
+    pub index_node_size: u16,
     /// Detect geometry type when `geometry_type` is Unknown.
     pub detect_type: bool,
     /// Convert single to multi geometries, if `geometry_type` is multi type or Unknown
@@ -64,12 +72,27 @@ pub struct FgbWriterOptions<'a> {
     pub description: Option<&'a str>,
     // Dataset metadata (intended to be application specific and
     pub metadata: Option<&'a str>,
+    /// Memory in bytes for index nodes of features. When exceeded, nodes are spilled to
+    /// temporary files and the index is sorted and built on disk. Unlimited if `None`.
+    pub memory_budget: Option<usize>,
+    /// Validation of properties against the declared columns
+    pub schema_validation: SchemaValidation,
+    /// Write the columns of each feature with the feature instead of declaring dataset columns.
+    ///
+    /// For sources with varying properties like GeoJSON, where dataset columns would drop or
+    /// mistype values. Properties are written with the type of their value. Properties of
+    /// declared dataset columns are validated against them and values of non-nullable dataset
+    /// columns are required.
+    pub feature_columns: bool,
+    /// Order of the features and index leaf nodes, if an index is written
+    pub sort: SortOrder,
 }
 
 impl Default for FgbWriterOptions<'_> {
     fn default() -> Self {
         FgbWriterOptions {
             write_index: true,
+            index_node_size: PackedRTree::DEFAULT_NODE_SIZE,
             detect_type: true,
             promote_to_multi: true,
             crs: Default::default(),
@@ -80,6 +103,61 @@ impl Default for FgbWriterOptions<'_> {
             title: None,
             description: None,
             metadata: None,
+            memory_budget: None,
+            schema_validation: SchemaValidation::default(),
+            feature_columns: false,
+            sort: SortOrder::default(),
+        }
+    }
+}
+
+/// Validation of feature properties against the declared columns
+///
+/// Violations fail writing the feature with [`Error::Schema`], values of non-nullable columns
+/// are checked in all modes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaValidation {
+    /// Write property values as given. Columns are declared on first sight of the next index.
+    #[default]
+    Off,
+    /// Assign properties to columns by name and convert values to the column type if possible
+    /// without loss, e.g. `Int` to `Long`. Columns are declared on first sight of a new name.
+    Lenient,
+    /// Require declared columns with matching name and type at the given index, properties in
+    /// column order without duplicates and values not exceeding the column width. Columns are
+    /// not declared automatically.
+    Strict,
+}
+
+/// Order of features in an indexed dataset
+///
+/// The packing of the index leaf nodes follows the feature order, so the order determines how
+/// many nodes and features a query has to read. Features of datasets without index are written
+/// in input order.
+#[derive(Debug, Default, Clone, Copy)]
+pub enum SortOrder {
+    /// Descending Hilbert curve value of the bounding box centers
+    #[default]
+    Hilbert,
+    /// Ascending Z-order (Morton) curve value of the bounding box centers
+    ZOrder,
+    /// Sort-Tile-Recursive packing by bounding box centers
+    Str,
+    /// Input order, with an index over the unsorted features
+    Input,
+    /// Ascending key of the feature bounding box and the dataset extent, ties in input order
+    Key(fn(&NodeItem, &NodeItem) -> u64),
+}
+
+impl SortOrder {
+    /// Sort leaf nodes of a tree with `node_size`
+    pub(crate) fn sort(&self, items: &mut [NodeItem], extent: &NodeItem, node_size: u16) {
+        match self {
+            SortOrder::Hilbert => hilbert_sort(items, extent),
+            SortOrder::ZOrder => morton_sort(items, extent),
+            SortOrder::Str => str_sort(items, node_size),
+            SortOrder::Input => {}
+            SortOrder::Key(key) => items.sort_by_cached_key(|item| key(item, extent)),
         }
     }
 }
@@ -153,45 +231,12 @@ impl<'a> FgbWriter<'a> {
         let mut fbb = FlatBufferBuilder::new();
 
         let index_node_size = if options.write_index {
-            PackedRTree::DEFAULT_NODE_SIZE
+            options.index_node_size.clamp(2, 65535)
         } else {
             0
         };
-        let crs_args = CrsArgs {
-            org: options.crs.org.map(|v| fbb.create_string(v)),
-            code: options.crs.code,
-            name: options.crs.name.map(|v| fbb.create_string(v)),
-            description: options.crs.description.map(|v| fbb.create_string(v)),
-            wkt: options.crs.wkt.map(|v| fbb.create_string(v)),
-            code_string: options.crs.code_string.map(|v| fbb.create_string(v)),
-        };
-        let header_args = HeaderArgs {
-            name: Some(fbb.create_string(name)),
-            geometry_type,
-            index_node_size,
-            crs: Some(Crs::create(&mut fbb, &crs_args)),
-            has_z: options.has_z,
-            has_m: options.has_m,
-            has_t: options.has_t,
-            has_tm: options.has_tm,
-            title: options.title.map(|v| fbb.create_string(v)),
-            description: options.description.map(|v| fbb.create_string(v)),
-            metadata: options.metadata.map(|v| fbb.create_string(v)),
-            ..Default::default()
-        };
-
-        let dims = CoordDimensions {
-            z: header_args.has_z,
-            m: header_args.has_m,
-            t: header_args.has_t,
-            tm: header_args.has_tm,
-        };
-        let feat_writer = FeatureWriter::with_dims(
-            header_args.geometry_type,
-            options.detect_type,
-            options.promote_to_multi,
-            dims,
-        );
+        let header_args = header_args(&mut fbb, name, geometry_type, &options, index_node_size);
+        let feat_writer = feature_writer(&header_args, &options);
 
         let tmpout = BufWriter::new(tempfile::tempfile()?);
 
@@ -203,6 +248,10 @@ impl<'a> FgbWriter<'a> {
             feat_writer,
             feat_offsets: Vec::new(),
             feat_nodes: Vec::new(),
+            sort: options.sort,
+            node_sort: options
+                .memory_budget
+                .map(|budget| ExternalNodeSort::new(budget, options.sort)),
         })
     }
 
@@ -221,22 +270,30 @@ impl<'a> FgbWriter<'a> {
     where
         F: FnOnce(&mut FlatBufferBuilder<'a>, &mut ColumnArgs),
     {
-        let mut col = ColumnArgs {
-            name: Some(self.fbb.create_string(name)),
-            type_: col_type,
-            ..Default::default()
-        };
-        cfgfn(&mut self.fbb, &mut col);
-        self.columns.push(Column::create(&mut self.fbb, &col));
+        let (column, def) = create_column(&mut self.fbb, name, col_type, cfgfn);
+        self.columns.push(column);
+        self.feat_writer.columns.push(def);
     }
 
     fn write_feature(&mut self) -> Result<()> {
-        let mut node = self.feat_writer.bbox.clone();
+        self.feat_writer.check_properties()?;
+        let feat_buf = self.feat_writer.finish_to_feature();
+        let node = self.feat_writer.bbox.clone();
+        self.spool_feature(node, &feat_buf)
+    }
+
+    /// Append an encoded feature with its bounding box to the temporary file
+    fn spool_feature(&mut self, mut node: NodeItem, feat_buf: &[u8]) -> Result<()> {
+        if let Some(node_sort) = &mut self.node_sort {
+            node_sort.push(node, feat_buf.len() as u64)?;
+            self.tmpout.write_all(feat_buf)?;
+            self.header_args.features_count += 1;
+            return Ok(());
+        }
         // Offset is index of feat_offsets before sorting
         // Will be replaced with output offset after sorting
         node.offset = self.feat_offsets.len() as u64;
         self.feat_nodes.push(node);
-        let feat_buf = self.feat_writer.finish_to_feature();
         let tmpoffset = self
             .feat_offsets
             .last()
@@ -246,16 +303,19 @@ impl<'a> FgbWriter<'a> {
             offset: tmpoffset,
             size: feat_buf.len(),
         });
-        self.tmpout.write_all(&feat_buf)?;
+        self.tmpout.write_all(feat_buf)?;
         self.header_args.features_count += 1;
         Ok(())
     }
 
-    /// Write the FlatGeobuf dataset (Hilbert sorted)
+    /// Write the FlatGeobuf dataset (sorted by [`FgbWriterOptions::sort`])
     pub fn write(mut self, mut out: impl Write) -> Result<()> {
         out.write_all(&MAGIC_BYTES)?;
 
-        let extent = calc_extent(&self.feat_nodes);
+        let extent = match &self.node_sort {
+            Some(node_sort) => node_sort.extent(),
+            None => calc_extent(&self.feat_nodes),
+        };
 
         // Write header
         self.header_args.columns = Some(self.fbb.create_vector(&self.columns));
@@ -270,9 +330,17 @@ impl<'a> FgbWriter<'a> {
         let buf = self.fbb.finished_data();
         out.write_all(buf)?;
 
+        if let Some(node_sort) = self.node_sort.take() {
+            return self.write_external(node_sort, out);
+        }
+
         if self.header_args.index_node_size > 0 && !self.feat_nodes.is_empty() {
             // Create sorted index
-            hilbert_sort(&mut self.feat_nodes, &extent);
+            self.sort.sort(
+                &mut self.feat_nodes,
+                &extent,
+                self.header_args.index_node_size,
+            );
             // Update offsets for index
             let mut offset = 0;
             let index_nodes = self
@@ -311,15 +379,275 @@ impl<'a> FgbWriter<'a> {
 
         Ok(())
     }
+
+    /// Write index and features with nodes sorted and the index built in temporary files
+    fn write_external(self, node_sort: ExternalNodeSort, mut out: impl Write) -> Result<()> {
+        let index_node_size = self.header_args.index_node_size;
+        let num_items = node_sort.len();
+        let mut tmpout = self.tmpout.into_inner().map_err(|e| e.into_error())?;
+        tmpout.rewind()?;
+        let mut unsorted_feature_reader = BufReader::new(tmpout);
+
+        if index_node_size == 0 || num_items == 0 {
+            io::copy(&mut unsorted_feature_reader, &mut out)?;
+            return Ok(());
+        }
+
+        let mut sorted = node_sort.sort(index_node_size)?;
+        let mut offset = 0;
+        let index_nodes = sorted.by_ref().map(|item| {
+            item.map(|item| {
+                let mut node = item.node;
+                node.offset = offset;
+                offset += item.size;
+                node
+            })
+        });
+        PackedRTree::stream_build(index_nodes, num_items, index_node_size, &mut out)?;
+
+        // Copy features from temp file in sort order
+        sorted.rewind()?;
+        let mut buf = Vec::with_capacity(2048);
+        for item in sorted {
+            let item = item?;
+            unsorted_feature_reader.seek(SeekFrom::Start(item.node.offset))?;
+            buf.resize(item.size as usize, 0);
+            unsorted_feature_reader.read_exact(&mut buf)?;
+            out.write_all(&buf)?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn header_args<'a>(
+    fbb: &mut FlatBufferBuilder<'a>,
+    name: &str,
+    geometry_type: GeometryType,
+    options: &FgbWriterOptions,
+    index_node_size: u16,
+) -> HeaderArgs<'a> {
+    let crs_args = CrsArgs {
+        org: options.crs.org.map(|v| fbb.create_string(v)),
+        code: options.crs.code,
+        name: options.crs.name.map(|v| fbb.create_string(v)),
+        description: options.crs.description.map(|v| fbb.create_string(v)),
+        wkt: options.crs.wkt.map(|v| fbb.create_string(v)),
+        code_string: options.crs.code_string.map(|v| fbb.create_string(v)),
+    };
+    HeaderArgs {
+        name: Some(fbb.create_string(name)),
+        geometry_type,
+        index_node_size,
+        crs: Some(Crs::create(fbb, &crs_args)),
+        has_z: options.has_z,
+        has_m: options.has_m,
+        has_t: options.has_t,
+        has_tm: options.has_tm,
+        title: options.title.map(|v| fbb.create_string(v)),
+        description: options.description.map(|v| fbb.create_string(v)),
+        metadata: options.metadata.map(|v| fbb.create_string(v)),
+        ..Default::default()
+    }
+}
+
+pub(crate) fn feature_writer<'a>(
+    header_args: &HeaderArgs,
+    options: &FgbWriterOptions,
+) -> FeatureWriter<'a> {
+    let dims = CoordDimensions {
+        z: header_args.has_z,
+        m: header_args.has_m,
+        t: header_args.has_t,
+        tm: header_args.has_tm,
+    };
+    let mut feat_writer = FeatureWriter::with_dims(
+        header_args.geometry_type,
+        options.detect_type,
+        options.promote_to_multi,
+        dims,
+    );
+    feat_writer.validation = options.schema_validation;
+    feat_writer.per_feature_columns = options.feature_columns;
+    feat_writer
+}
+
+pub(crate) fn create_column<'a, F>(
+    fbb: &mut FlatBufferBuilder<'a>,
+    name: &str,
+    col_type: ColumnType,
+    cfgfn: F,
+) -> (flatbuffers::WIPOffset<Column<'a>>, ColumnDef)
+where
+    F: FnOnce(&mut FlatBufferBuilder<'a>, &mut ColumnArgs),
+{
+    let mut col = ColumnArgs {
+        name: Some(fbb.create_string(name)),
+        type_: col_type,
+        ..Default::default()
+    };
+    cfgfn(fbb, &mut col);
+    let def = ColumnDef::new(name, &col);
+    (Column::create(fbb, &col), def)
+}
+
+/// Column declaration, kept for checking property values
+#[derive(Clone, Debug)]
+pub(crate) struct ColumnDef {
+    pub(crate) name: String,
+    pub(crate) type_: ColumnType,
+    pub(crate) nullable: bool,
+    /// Maximal string length in characters or binary length in bytes, if positive
+    pub(crate) width: i32,
+}
+
+impl ColumnDef {
+    pub(crate) fn new(name: &str, col: &ColumnArgs) -> Self {
+        ColumnDef {
+            name: name.to_string(),
+            type_: col.type_,
+            nullable: col.nullable,
+            width: col.width,
+        }
+    }
+
+    pub(crate) fn from_column(column: &Column) -> Self {
+        ColumnDef {
+            name: column.name().to_string(),
+            type_: column.type_(),
+            nullable: column.nullable(),
+            width: column.width(),
+        }
+    }
+}
+
+/// Implement `GeomProcessor` for a writer by delegating to its `feat_writer`
+macro_rules! delegate_geom_processor {
+    ([$($generics:tt)*] $writer:ty) => {
+        impl<$($generics)*> geozero::GeomProcessor for $writer {
+            fn xy(&mut self, x: f64, y: f64, idx: usize) -> geozero::error::Result<()> {
+                self.feat_writer.xy(x, y, idx)
+            }
+            fn coordinate(
+                &mut self,
+                x: f64,
+                y: f64,
+                z: Option<f64>,
+                m: Option<f64>,
+                t: Option<f64>,
+                tm: Option<u64>,
+                idx: usize,
+            ) -> geozero::error::Result<()> {
+                self.feat_writer.coordinate(x, y, z, m, t, tm, idx)
+            }
+            fn point_begin(&mut self, idx: usize) -> geozero::error::Result<()> {
+                self.feat_writer.point_begin(idx)
+            }
+            fn point_end(&mut self, idx: usize) -> geozero::error::Result<()> {
+                self.feat_writer.point_end(idx)
+            }
+            fn multipoint_begin(&mut self, size: usize, idx: usize) -> geozero::error::Result<()> {
+                self.feat_writer.multipoint_begin(size, idx)
+            }
+            fn multipoint_end(&mut self, idx: usize) -> geozero::error::Result<()> {
+                self.feat_writer.multipoint_end(idx)
+            }
+            fn linestring_begin(&mut self, tagged: bool, size: usize, idx: usize) -> geozero::error::Result<()> {
+                self.feat_writer.linestring_begin(tagged, size, idx)
+            }
+            fn linestring_end(&mut self, tagged: bool, idx: usize) -> geozero::error::Result<()> {
+                self.feat_writer.linestring_end(tagged, idx)
+            }
+            fn multilinestring_begin(&mut self, size: usize, idx: usize) -> geozero::error::Result<()> {
+                self.feat_writer.multilinestring_begin(size, idx)
+            }
+            fn multilinestring_end(&mut self, idx: usize) -> geozero::error::Result<()> {
+                self.feat_writer.multilinestring_end(idx)
+            }
+            fn polygon_begin(&mut self, tagged: bool, size: usize, idx: usize) -> geozero::error::Result<()> {
+                self.feat_writer.polygon_begin(tagged, size, idx)
+            }
+            fn polygon_end(&mut self, tagged: bool, idx: usize) -> geozero::error::Result<()> {
+                self.feat_writer.polygon_end(tagged, idx)
+            }
+            fn multipolygon_begin(&mut self, size: usize, idx: usize) -> geozero::error::Result<()> {
+                self.feat_writer.multipolygon_begin(size, idx)
+            }
+            fn multipolygon_end(&mut self, idx: usize) -> geozero::error::Result<()> {
+                self.feat_writer.multipolygon_end(idx)
+            }
+            fn circularstring_begin(&mut self, size: usize, idx: usize) -> geozero::error::Result<()> {
+                self.feat_writer.circularstring_begin(size, idx)
+            }
+            fn circularstring_end(&mut self, idx: usize) -> geozero::error::Result<()> {
+                self.feat_writer.circularstring_end(idx)
+            }
+            fn compoundcurve_begin(&mut self, size: usize, idx: usize) -> geozero::error::Result<()> {
+                self.feat_writer.compoundcurve_begin(size, idx)
+            }
+            fn compoundcurve_end(&mut self, idx: usize) -> geozero::error::Result<()> {
+                self.feat_writer.compoundcurve_end(idx)
+            }
+            fn curvepolygon_begin(&mut self, size: usize, idx: usize) -> geozero::error::Result<()> {
+                self.feat_writer.curvepolygon_begin(size, idx)
+            }
+            fn curvepolygon_end(&mut self, idx: usize) -> geozero::error::Result<()> {
+                self.feat_writer.curvepolygon_end(idx)
+            }
+            fn multicurve_begin(&mut self, size: usize, idx: usize) -> geozero::error::Result<()> {
+                self.feat_writer.multicurve_begin(size, idx)
+            }
+            fn multicurve_end(&mut self, idx: usize) -> geozero::error::Result<()> {
+                self.feat_writer.multicurve_end(idx)
+            }
+            fn multisurface_begin(&mut self, size: usize, idx: usize) -> geozero::error::Result<()> {
+                self.feat_writer.multisurface_begin(size, idx)
+            }
+            fn multisurface_end(&mut self, idx: usize) -> geozero::error::Result<()> {
+                self.feat_writer.multisurface_end(idx)
+            }
+            fn triangle_begin(&mut self, tagged: bool, size: usize, idx: usize) -> geozero::error::Result<()> {
+                self.feat_writer.triangle_begin(tagged, size, idx)
+            }
+            fn triangle_end(&mut self, tagged: bool, idx: usize) -> geozero::error::Result<()> {
+                self.feat_writer.triangle_end(tagged, idx)
+            }
+            fn polyhedralsurface_begin(&mut self, size: usize, idx: usize) -> geozero::error::Result<()> {
+                self.feat_writer.polyhedralsurface_begin(size, idx)
+            }
+            fn polyhedralsurface_end(&mut self, idx: usize) -> geozero::error::Result<()> {
+                self.feat_writer.polyhedralsurface_end(idx)
+            }
+            fn tin_begin(&mut self, size: usize, idx: usize) -> geozero::error::Result<()> {
+                self.feat_writer.tin_begin(size, idx)
+            }
+            fn tin_end(&mut self, idx: usize) -> geozero::error::Result<()> {
+                self.feat_writer.tin_end(idx)
+            }
+        }
+    };
+}
+pub(crate) use delegate_geom_processor;
+
+/// Convert an error writing a feature for the geozero API.
+///
+/// Schema errors are wrapped in an I/O error, accessible with [`io::Error::get_ref`].
+pub(crate) fn feature_error(e: Error) -> geozero::error::GeozeroError {
+    match e {
+        Error::Schema(schema) => geozero::error::GeozeroError::IoError(io::Error::new(
+            io::ErrorKind::InvalidData,
+            schema,
+        )),
+        e => geozero::error::GeozeroError::Feature(e.to_string()),
+    }
 }
 
 mod geozero_api {
     use crate::feature_writer::{prop_type, FeatureWriter};
+    use crate::file_writer::{feature_error, SchemaValidation};
     use crate::FgbWriter;
-    use geozero::error::GeozeroError;
     use geozero::{
-        error::Result, ColumnValue, FeatureProcessor, GeomProcessor, GeozeroDatasource,
-        GeozeroGeometry, PropertyProcessor,
+        error::Result, ColumnValue, FeatureProcessor, GeozeroDatasource, GeozeroGeometry,
+        PropertyProcessor,
     };
 
     impl FgbWriter<'_> {
@@ -335,9 +663,11 @@ mod geozero_api {
         /// fgb.add_feature(geojson).ok();
         /// ```
         pub fn add_feature(&mut self, mut feature: impl GeozeroDatasource) -> Result<()> {
-            feature.process(&mut self.feat_writer)?;
-            self.write_feature()
-                .map_err(|e| GeozeroError::Feature(e.to_string()))
+            if let Err(e) = feature.process(&mut self.feat_writer) {
+                self.feat_writer.discard_feature();
+                return Err(e);
+            }
+            self.write_feature().map_err(feature_error)
         }
 
         /// Add a new feature from a `GeozeroGeometry`.
@@ -361,24 +691,27 @@ mod geozero_api {
         where
             F: FnOnce(&mut FeatureWriter),
         {
-            geom.process_geom(&mut self.feat_writer)?;
+            if let Err(e) = geom.process_geom(&mut self.feat_writer) {
+                self.feat_writer.discard_feature();
+                return Err(e);
+            }
             cfgfn(&mut self.feat_writer);
-            self.write_feature()
-                .map_err(|e| GeozeroError::Feature(e.to_string()))
+            self.write_feature().map_err(feature_error)
         }
     }
 
     impl FeatureProcessor for FgbWriter<'_> {
         fn feature_end(&mut self, _idx: u64) -> Result<()> {
-            self.write_feature()
-                .map_err(|e| GeozeroError::Feature(e.to_string()))
+            self.write_feature().map_err(feature_error)
         }
     }
 
     impl PropertyProcessor for FgbWriter<'_> {
         fn property(&mut self, i: usize, colname: &str, colval: &ColumnValue) -> Result<bool> {
-            if i >= self.columns.len() {
-                if i == self.columns.len() {
+            if !self.feat_writer.is_declared(i, colname) {
+                if i == self.columns.len()
+                    || self.feat_writer.validation == SchemaValidation::Lenient
+                {
                     info!(
                     "Undefined property index {i}, column: `{colname}` - adding column declaration"
                 );
@@ -388,111 +721,260 @@ mod geozero_api {
                     return Ok(false);
                 }
             }
-            // TODO: check name and type against existing declaration
+            // Name and type are checked by the feature writer, depending on schema validation
             self.feat_writer.property(i, colname, colval)
         }
     }
 
     // Delegate GeomProcessor to self.feat_writer
-    impl GeomProcessor for FgbWriter<'_> {
-        fn xy(&mut self, x: f64, y: f64, idx: usize) -> Result<()> {
-            self.feat_writer.xy(x, y, idx)
-        }
-        fn coordinate(
-            &mut self,
-            x: f64,
-            y: f64,
-            z: Option<f64>,
-            m: Option<f64>,
-            t: Option<f64>,
-            tm: Option<u64>,
-            idx: usize,
-        ) -> Result<()> {
-            self.feat_writer.coordinate(x, y, z, m, t, tm, idx)
-        }
-        fn point_begin(&mut self, idx: usize) -> Result<()> {
-            self.feat_writer.point_begin(idx)
-        }
-        fn point_end(&mut self, idx: usize) -> Result<()> {
-            self.feat_writer.point_end(idx)
-        }
-        fn multipoint_begin(&mut self, size: usize, idx: usize) -> Result<()> {
-            self.feat_writer.multipoint_begin(size, idx)
-        }
-        fn multipoint_end(&mut self, idx: usize) -> Result<()> {
-            self.feat_writer.multipoint_end(idx)
-        }
-        fn linestring_begin(&mut self, tagged: bool, size: usize, idx: usize) -> Result<()> {
-            self.feat_writer.linestring_begin(tagged, size, idx)
-        }
-        fn linestring_end(&mut self, tagged: bool, idx: usize) -> Result<()> {
-            self.feat_writer.linestring_end(tagged, idx)
-        }
-        fn multilinestring_begin(&mut self, size: usize, idx: usize) -> Result<()> {
-            self.feat_writer.multilinestring_begin(size, idx)
-        }
-        fn multilinestring_end(&mut self, idx: usize) -> Result<()> {
-            self.feat_writer.multilinestring_end(idx)
-        }
-        fn polygon_begin(&mut self, tagged: bool, size: usize, idx: usize) -> Result<()> {
-            self.feat_writer.polygon_begin(tagged, size, idx)
-        }
-        fn polygon_end(&mut self, tagged: bool, idx: usize) -> Result<()> {
-            self.feat_writer.polygon_end(tagged, idx)
-        }
-        fn multipolygon_begin(&mut self, size: usize, idx: usize) -> Result<()> {
-            self.feat_writer.multipolygon_begin(size, idx)
-        }
-        fn multipolygon_end(&mut self, idx: usize) -> Result<()> {
-            self.feat_writer.multipolygon_end(idx)
-        }
-        fn circularstring_begin(&mut self, size: usize, idx: usize) -> Result<()> {
-            self.feat_writer.circularstring_begin(size, idx)
-        }
-        fn circularstring_end(&mut self, idx: usize) -> Result<()> {
-            self.feat_writer.circularstring_end(idx)
-        }
-        fn compoundcurve_begin(&mut self, size: usize, idx: usize) -> Result<()> {
-            self.feat_writer.compoundcurve_begin(size, idx)
-        }
-        fn compoundcurve_end(&mut self, idx: usize) -> Result<()> {
-            self.feat_writer.compoundcurve_end(idx)
-        }
-        fn curvepolygon_begin(&mut self, size: usize, idx: usize) -> Result<()> {
-            self.feat_writer.curvepolygon_begin(size, idx)
-        }
-        fn curvepolygon_end(&mut self, idx: usize) -> Result<()> {
-            self.feat_writer.curvepolygon_end(idx)
-        }
-        fn multicurve_begin(&mut self, size: usize, idx: usize) -> Result<()> {
-            self.feat_writer.multicurve_begin(size, idx)
-        }
-        fn multicurve_end(&mut self, idx: usize) -> Result<()> {
-            self.feat_writer.multicurve_end(idx)
-        }
-        fn multisurface_begin(&mut self, size: usize, idx: usize) -> Result<()> {
-            self.feat_writer.multisurface_begin(size, idx)
-        }
-        fn multisurface_end(&mut self, idx: usize) -> Result<()> {
-            self.feat_writer.multisurface_end(idx)
-        }
-        fn triangle_begin(&mut self, tagged: bool, size: usize, idx: usize) -> Result<()> {
-            self.feat_writer.triangle_begin(tagged, size, idx)
+    delegate_geom_processor!([] FgbWriter<'_>);
+}
+
+#[cfg(feature = "rayon")]
+mod rayon_api {
+    use crate::file_writer::feature_error;
+    use crate::packed_r_tree::NodeItem;
+    use crate::FgbWriter;
+    use geozero::{error::Result, GeozeroDatasource};
+    use rayon::prelude::*;
+
+    /// Number of features encoded in parallel before writing them to the temporary file
+    const PAR_BATCH_SIZE: usize = 4096;
+
+    impl FgbWriter<'_> {
+        /// Add features, encoding them on the rayon thread pool.
+        ///
+        /// Features are taken from `features` in batches, encoded in parallel and written in
+        /// input order, so the dataset is identical to adding them one by one with
+        /// [`FgbWriter::add_feature`]. While the geometry type of the dataset is still to be
+        /// detected, features are encoded one by one, so that all features are checked against
+        /// the detected type.
+        ///
+        /// On failure, the features preceding the failed one are added.
+        ///
+        /// # Usage example:
+        ///
+        /// ```
+        /// # use flatgeobuf::*;
+        /// use geozero::geojson::GeoJson;
+        /// # let mut fgb = FgbWriter::create("", GeometryType::Point).unwrap();
+        /// let features = (0..100).map(|i| {
+        ///     format!(r#"{{"type": "Feature", "properties": {{"fid": {i}}}, "geometry": {{"type": "Point", "coordinates": [{i}, {i}]}}}}"#)
+        /// }).collect::<Vec<_>>();
+        /// fgb.par_add_features(features.iter().map(|f| GeoJson(f))).unwrap();
+        /// ```
+        pub fn par_add_features<I>(&mut self, features: I) -> Result<()>
+        where
+            I: IntoIterator,
+            I::Item: GeozeroDatasource + Send,
+        {
+            let mut features = features.into_iter();
+            while self.feat_writer.detecting_type() {
+                match features.next() {
+                    Some(feature) => self.add_feature(feature)?,
+                    None => return Ok(()),
+                }
+            }
+            loop {
+                let batch = features.by_ref().take(PAR_BATCH_SIZE).collect::<Vec<_>>();
+                if batch.is_empty() {
+                    return Ok(());
+                }
+                let encoded = batch
+                    .into_par_iter()
+                    .map_init(
+                        || self.feat_writer.fork(),
+                        |writer, mut feature| -> Result<(NodeItem, Vec<u8>)> {
+                            feature.process(writer)?;
+                            writer.check_properties().map_err(feature_error)?;
+                            let feat_buf = writer.finish_to_feature();
+                            Ok((writer.bbox.clone(), feat_buf))
+                        },
+                    )
+                    .collect::<Vec<_>>();
+                for result in encoded {
+                    let (node, feat_buf) = result?;
+                    self.spool_feature(node, &feat_buf).map_err(feature_error)?;
+                }
+            }
         }
-        fn triangle_end(&mut self, tagged: bool, idx: usize) -> Result<()> {
-            self.feat_writer.triangle_end(tagged, idx)
+    }
+}
+
+#[cfg(feature = "arrow")]
+mod arrow_api {
+    use super::ColumnDef;
+    use crate::arrow_writer::ArrowFeatures;
+    use crate::{Column, ColumnArgs, FgbWriter, Result};
+    use arrow_array::RecordBatch;
+
+    impl FgbWriter<'_> {
+        /// Add the rows of an Arrow record batch as features.
+        ///
+        /// The geometry is read from the first field with a GeoArrow extension type (`point`,
+        /// `linestring`, `polygon`, `multipoint`, `multilinestring`, `multipolygon` or `wkb`) or
+        /// from a binary field named `geometry` containing WKB. Native coordinates are copied
+        /// directly, without processing every coordinate separately.
+        ///
+        /// Columns are declared from the other fields of the first batch, unless columns were
+        /// added before. Fields of following batches are matched by position and must have the
+        /// name of their column. Values of fields with another type are converted to the column
+        /// type, failing with [`SchemaError::ColumnType`](crate::SchemaError::ColumnType) if
+        /// that is not possible without loss.
+        pub fn add_record_batch(&mut self, batch: &RecordBatch) -> Result<()> {
+            let mut features = ArrowFeatures::new(batch)?;
+            if self.columns.is_empty() && self.header_args.features_count == 0 {
+                for (field, column_type) in features.columns() {
+                    let col = ColumnArgs {
+                        name: Some(self.fbb.create_string(field.name())),
+                        type_: column_type,
+                        description: field
+                            .metadata()
+                            .get("description")
+                            .map(|desc| self.fbb.create_string(desc)),
+                        nullable: field.is_nullable(),
+                        ..Default::default()
+                    };
+                    self.columns.push(Column::create(&mut self.fbb, &col));
+                    self.feat_writer
+                        .columns
+                        .push(ColumnDef::new(field.name(), &col));
+                }
+            } else {
+                features.match_columns(&self.feat_writer.columns)?;
+            }
+            for row in 0..batch.num_rows() {
+                if let Err(e) = features.process_row(row, &mut self.feat_writer) {
+                    self.feat_writer.discard_feature();
+                    return Err(e);
+                }
+                self.write_feature()?;
+            }
+            Ok(())
         }
-        fn polyhedralsurface_begin(&mut self, size: usize, idx: usize) -> Result<()> {
-            self.feat_writer.polyhedralsurface_begin(size, idx)
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_api {
+    use crate::error::{Error, Result};
+    use crate::file_writer::ColumnDef;
+    use crate::properties_serializer::{row_columns, schema_columns, serialize_properties};
+    use crate::{FgbWriter, FgbWriterOptions, GeometryType};
+    use geozero::GeozeroGeometry;
+    use serde::{Deserialize, Serialize};
+
+    impl FgbWriter<'_> {
+        /// Configure FlatGeobuf headers with columns derived from the fields of the struct `T`.
+        ///
+        /// Column types follow the Rust types of the fields: integers, floats, `bool`, strings
+        /// and byte sequences map to the corresponding [`ColumnType`](crate::ColumnType),
+        /// `char` to a string of width 1 and byte arrays `[u8; N]` to binary values of width
+        /// `N`. `Option` fields are nullable and unit enum variants are written as strings.
+        /// The field types are traced through their `Deserialize` implementation, so `T` has
+        /// to implement both `Serialize` and `Deserialize`.
+        ///
+        /// Columns are named after the deserialized fields. Rows of types with asymmetric serde
+        /// attributes like `rename(serialize = "..")`, `skip_serializing` or
+        /// `skip_deserializing` are rejected by [`FgbWriter::add_row`], use
+        /// [`FgbWriter::from_row`] for them.
+        ///
+        /// # Usage example:
+        ///
+        /// ```
+        /// use flatgeobuf::*;
+        /// use geozero::geojson::GeoJson;
+        /// use serde::{Deserialize, Serialize};
+        ///
+        /// #[derive(Serialize, Deserialize)]
+        /// struct Country<'a> {
+        ///     id: &'a str,
+        ///     name: Option<String>,
+        /// }
+        ///
+        /// # fn write_fgb() -> Result<()> {
+        /// let mut fgb = FgbWriter::from_schema::<Country>(
+        ///     "countries",
+        ///     GeometryType::Point,
+        ///     FgbWriterOptions::default(),
+        /// )?;
+        /// let geom = GeoJson(r#"{"type": "Point", "coordinates": [1, 1]}"#);
+        /// let country = Country {
+        ///     id: "NZL",
+        ///     name: Some("New Zealand".to_string()),
+        /// };
+        /// fgb.add_row(&geom, &country)?;
+        /// # Ok(())
+        /// # }
+        /// # write_fgb().unwrap();
+        /// ```
+        pub fn from_schema<'de, T>(
+            name: &str,
+            geometry_type: GeometryType,
+            options: FgbWriterOptions,
+        ) -> Result<Self>
+        where
+            T: Serialize + Deserialize<'de>,
+        {
+            let columns = schema_columns::<T>()?;
+            Self::with_columns(name, geometry_type, options, columns)
         }
-        fn polyhedralsurface_end(&mut self, idx: usize) -> Result<()> {
-            self.feat_writer.polyhedralsurface_end(idx)
+
+        /// Configure FlatGeobuf headers with columns derived from the fields of the sample `row`.
+        ///
+        /// The columns are traced through the `Serialize` implementation of `T`, with the names
+        /// and types of the written fields and the column types of
+        /// [`FgbWriter::from_schema`]. `Option` fields are nullable, but their type can only be
+        /// derived from a `Some` value. Fields skipped in the sample, e.g. with
+        /// `skip_serializing_if`, are not declared. The sample row is not written.
+        pub fn from_row<T: Serialize + ?Sized>(
+            name: &str,
+            geometry_type: GeometryType,
+            options: FgbWriterOptions,
+            row: &T,
+        ) -> Result<Self> {
+            let columns = row_columns(row)?;
+            Self::with_columns(name, geometry_type, options, columns)
         }
-        fn tin_begin(&mut self, size: usize, idx: usize) -> Result<()> {
-            self.feat_writer.tin_begin(size, idx)
+
+        fn with_columns(
+            name: &str,
+            geometry_type: GeometryType,
+            options: FgbWriterOptions,
+            columns: Vec<ColumnDef>,
+        ) -> Result<Self> {
+            let mut fgb = FgbWriter::create_with_options(name, geometry_type, options)?;
+            for column in columns {
+                fgb.add_column(&column.name, column.type_, |_, col| {
+                    col.nullable = column.nullable;
+                    col.width = column.width;
+                });
+            }
+            Ok(fgb)
         }
-        fn tin_end(&mut self, idx: usize) -> Result<()> {
-            self.feat_writer.tin_end(idx)
+
+        /// Add a new feature with the fields of `row` as properties.
+        ///
+        /// Fields are matched by name with the declared columns and their values are checked
+        /// against the declared type, nullability and width. Values are converted to the
+        /// column type, if possible without loss.
+        pub fn add_row<T: Serialize + ?Sized>(
+            &mut self,
+            geom: &impl GeozeroGeometry,
+            row: &T,
+        ) -> Result<()> {
+            if let Err(e) = serialize_properties(row, &mut self.feat_writer) {
+                self.feat_writer.reset_properties();
+                return Err(e);
+            }
+            if let Err(e) = geom.process_geom(&mut self.feat_writer) {
+                self.feat_writer.reset_properties();
+                return Err(Error::IO(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    e,
+                )));
+            }
+            self.write_feature()
         }
     }
 }
@@ -0,0 +1,494 @@
+//! Exact spatial predicates for refining index candidates.
+//!
+//! Geometries are flattened into points, line strings and polygons of 2D coordinates. Z and M
+//! values are ignored. The predicates follow the usual spatial database semantics, where the
+//! boundary of a polygon is its rings and the boundary of a line string its end points.
+
+use crate::packed_r_tree::NodeItem;
+use crate::properties_reader::FgbFeature;
+use crate::{Error, Result};
+use geo_traits::{
+    CoordTrait, GeometryCollectionTrait, GeometryTrait, GeometryType, LineStringTrait, LineTrait,
+    MultiLineStringTrait, MultiPointTrait, MultiPolygonTrait, PointTrait, PolygonTrait, RectTrait,
+    TriangleTrait,
+};
+
+type Xy = [f64; 2];
+
+/// Spatial relation between a feature geometry and the query geometry
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum SpatialPredicate {
+    /// Feature intersects the query geometry
+    Intersects,
+    /// Feature is within the query polygon
+    Within,
+    /// Feature contains the query point
+    Contains,
+}
+
+/// Exact geometry filter applied to the candidates of an index search
+#[derive(Clone, Debug)]
+pub(crate) struct SpatialFilter {
+    predicate: SpatialPredicate,
+    query: Shape,
+}
+
+impl SpatialFilter {
+    pub(crate) fn new(
+        predicate: SpatialPredicate,
+        query: &impl GeometryTrait<T = f64>,
+    ) -> Result<Self> {
+        let mut shape = Shape::default();
+        shape.add_geometry(query);
+        match predicate {
+            SpatialPredicate::Within if !shape.points.is_empty() || !shape.lines.is_empty() => {
+                return Err(Error::UnsupportedGeometryType(
+                    "select_within requires a Polygon or MultiPolygon".to_string(),
+                ));
+            }
+            SpatialPredicate::Contains if !shape.lines.is_empty() || !shape.polygons.is_empty() => {
+                return Err(Error::UnsupportedGeometryType(
+                    "select_containing requires a Point".to_string(),
+                ));
+            }
+            // An empty point would vacuously be contained in every feature
+            SpatialPredicate::Contains
+                if shape.points.is_empty()
+                    || shape.points.iter().flatten().any(|v| !v.is_finite()) =>
+            {
+                return Err(Error::UnsupportedGeometryType(
+                    "select_containing requires a non-empty Point".to_string(),
+                ));
+            }
+            _ => {}
+        }
+        Ok(SpatialFilter {
+            predicate,
+            query: shape,
+        })
+    }
+
+    /// Bounding box of the query geometry, used for the index search
+    pub(crate) fn bbox(&self) -> NodeItem {
+        self.query.bbox()
+    }
+
+    /// Test the geometry of a feature. Features without geometry never match.
+    pub(crate) fn matches(&self, feature: &FgbFeature) -> Result<bool> {
+        let Some(geometry) = feature.geometry_trait()? else {
+            return Ok(false);
+        };
+        let mut shape = Shape::default();
+        shape.add_geometry(&geometry);
+        Ok(match self.predicate {
+            SpatialPredicate::Intersects => shape.intersects(&self.query),
+            SpatialPredicate::Within => shape.within(&self.query),
+            SpatialPredicate::Contains => self.query.points.iter().all(|p| shape.contains(p)),
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Location {
+    Interior,
+    Boundary,
+    Exterior,
+}
+
+/// Geometry flattened into its components
+#[derive(Clone, Debug, Default)]
+struct Shape {
+    points: Vec<Xy>,
+    lines: Vec<Vec<Xy>>,
+    /// Polygons as list of rings, the first one being the exterior ring
+    polygons: Vec<Vec<Vec<Xy>>>,
+}
+
+fn xy(coord: impl CoordTrait<T = f64>) -> Xy {
+    [coord.x(), coord.y()]
+}
+
+impl Shape {
+    fn add_geometry(&mut self, geom: &impl GeometryTrait<T = f64>) {
+        match geom.as_type() {
+            GeometryType::Point(point) => self.points.extend(point.coord().map(xy)),
+            GeometryType::LineString(line) => self.add_line(line),
+            GeometryType::Polygon(polygon) => self.add_polygon(polygon),
+            GeometryType::MultiPoint(multi) => {
+                for point in multi.points() {
+                    self.points.extend(point.coord().map(xy));
+                }
+            }
+            GeometryType::MultiLineString(multi) => {
+                for line in multi.line_strings() {
+                    self.add_line(&line);
+                }
+            }
+            GeometryType::MultiPolygon(multi) => {
+                for polygon in multi.polygons() {
+                    self.add_polygon(&polygon);
+                }
+            }
+            GeometryType::GeometryCollection(collection) => {
+                for geom in collection.geometries() {
+                    self.add_geometry(&geom);
+                }
+            }
+            GeometryType::Rect(rect) => {
+                let [min_x, min_y] = xy(rect.min());
+                let [max_x, max_y] = xy(rect.max());
+                self.polygons.push(vec![vec![
+                    [min_x, min_y],
+                    [max_x, min_y],
+                    [max_x, max_y],
+                    [min_x, max_y],
+                    [min_x, min_y],
+                ]]);
+            }
+            GeometryType::Triangle(triangle) => {
+                let [a, b, c] = triangle.coords().map(xy);
+                self.polygons.push(vec![vec![a, b, c, a]]);
+            }
+            GeometryType::Line(line) => {
+                self.lines.push(line.coords().map(xy).to_vec());
+            }
+        }
+    }
+
+    fn add_line(&mut self, line: &impl LineStringTrait<T = f64>) {
+        let coords: Vec<Xy> = line.coords().map(xy).collect();
+        if !coords.is_empty() {
+            self.lines.push(coords);
+        }
+    }
+
+    fn add_polygon(&mut self, polygon: &impl PolygonTrait<T = f64>) {
+        let Some(exterior) = polygon.exterior() else {
+            return;
+        };
+        let mut rings = vec![exterior.coords().map(xy).collect::<Vec<_>>()];
+        for interior in polygon.interiors() {
+            rings.push(interior.coords().map(xy).collect());
+        }
+        if !rings[0].is_empty() {
+            self.polygons.push(rings);
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.points.is_empty() && self.lines.is_empty() && self.polygons.is_empty()
+    }
+
+    fn bbox(&self) -> NodeItem {
+        let mut bbox = NodeItem::create(0);
+        let coords = self
+            .points
+            .iter()
+            .chain(self.lines.iter().flatten())
+            .chain(self.polygons.iter().flatten().flatten());
+        for [x, y] in coords {
+            bbox.expand_xy(*x, *y);
+        }
+        bbox
+    }
+
+    /// All line and ring segments
+    fn segments(&self) -> impl Iterator<Item = (Xy, Xy)> + '_ {
+        let lines = self.lines.iter().flat_map(|line| line_segments(line));
+        let rings = self
+            .polygons
+            .iter()
+            .flatten()
+            .flat_map(|ring| ring_segments(ring));
+        lines.chain(rings)
+    }
+
+    /// All ring segments
+    fn boundary_segments(&self) -> impl Iterator<Item = (Xy, Xy)> + '_ {
+        self.polygons
+            .iter()
+            .flatten()
+            .flat_map(|ring| ring_segments(ring))
+    }
+
+    /// Location of a point relative to the polygons of this shape
+    fn locate_in_area(&self, p: &Xy) -> Location {
+        let mut location = Location::Exterior;
+        for polygon in &self.polygons {
+            match locate_in_polygon(p, polygon) {
+                Location::Boundary => return Location::Boundary,
+                Location::Interior => location = Location::Interior,
+                Location::Exterior => {}
+            }
+        }
+        location
+    }
+
+    fn intersects_point(&self, p: &Xy) -> bool {
+        self.points.contains(p)
+            || self
+                .lines
+                .iter()
+                .flat_map(|line| line_segments(line))
+                .any(|(a, b)| on_segment(p, &a, &b))
+            || self.locate_in_area(p) != Location::Exterior
+    }
+
+    fn intersects(&self, other: &Shape) -> bool {
+        if !self.bbox().intersects(&other.bbox()) {
+            return false;
+        }
+        if self.points.iter().any(|p| other.intersects_point(p))
+            || other.points.iter().any(|p| self.intersects_point(p))
+        {
+            return true;
+        }
+        for (a, b) in self.segments() {
+            if other
+                .segments()
+                .any(|(c, d)| segments_intersect(&a, &b, &c, &d))
+            {
+                return true;
+            }
+        }
+        // Without crossing segments, one geometry can still lie completely inside the other
+        self.first_vertices()
+            .any(|p| other.locate_in_area(p) != Location::Exterior)
+            || other
+                .first_vertices()
+                .any(|p| self.locate_in_area(p) != Location::Exterior)
+    }
+
+    /// First vertex of each line string and polygon
+    fn first_vertices(&self) -> impl Iterator<Item = &Xy> {
+        let lines = self.lines.iter().filter_map(|line| line.first());
+        let polygons = self
+            .polygons
+            .iter()
+            .filter_map(|polygon| polygon.first().and_then(|ring| ring.first()));
+        lines.chain(polygons)
+    }
+
+    /// Whether this shape lies within the polygons of `area`
+    fn within(&self, area: &Shape) -> bool {
+        if self.is_empty() || area.polygons.is_empty() {
+            return false;
+        }
+        // Polygons within the area always share interior points with it
+        let mut interior_found = !self.polygons.is_empty();
+        for p in &self.points {
+            match area.locate_in_area(p) {
+                Location::Exterior => return false,
+                Location::Interior => interior_found = true,
+                Location::Boundary => {}
+            }
+        }
+        // Every piece of a segment between crossings of the area boundary must be inside
+        for (a, b) in self.segments() {
+            let mut params = vec![0.0, 1.0];
+            for (c, d) in area.boundary_segments() {
+                intersection_params(&a, &b, &c, &d, &mut params);
+            }
+            params.sort_by(f64::total_cmp);
+            for p in [a, b] {
+                match area.locate_in_area(&p) {
+                    Location::Exterior => return false,
+                    Location::Interior => interior_found = true,
+                    Location::Boundary => {}
+                }
+            }
+            for t in params.windows(2) {
+                let t = (t[0] + t[1]) / 2.0;
+                let mid = [a[0] + t * (b[0] - a[0]), a[1] + t * (b[1] - a[1])];
+                match area.locate_in_area(&mid) {
+                    Location::Exterior => return false,
+                    Location::Interior => interior_found = true,
+                    Location::Boundary => {}
+                }
+            }
+        }
+        // Polygons must not cover any part of the area exterior, e.g. a hole of the area
+        let boundary_vertex_covered = area
+            .polygons
+            .iter()
+            .flatten()
+            .flatten()
+            .any(|p| self.locate_in_area(p) == Location::Interior);
+        interior_found && !boundary_vertex_covered
+    }
+
+    /// Whether the interior of this shape contains the point `p`
+    fn contains(&self, p: &Xy) -> bool {
+        if self.points.contains(p) || self.locate_in_area(p) == Location::Interior {
+            return true;
+        }
+        self.lines.iter().any(|line| {
+            let closed = line.first() == line.last();
+            let is_end_point = line.first() == Some(p) || line.last() == Some(p);
+            (closed || !is_end_point) && line_segments(line).any(|(a, b)| on_segment(p, &a, &b))
+        })
+    }
+}
+
+fn line_segments(line: &[Xy]) -> impl Iterator<Item = (Xy, Xy)> + '_ {
+    line.windows(2).map(|w| (w[0], w[1]))
+}
+
+/// Segments of a ring, including the closing segment of unclosed rings
+fn ring_segments(ring: &[Xy]) -> impl Iterator<Item = (Xy, Xy)> + '_ {
+    let closing = match (ring.first(), ring.last()) {
+        (Some(first), Some(last)) if first != last => Some((*last, *first)),
+        _ => None,
+    };
+    line_segments(ring).chain(closing)
+}
+
+fn orient(a: &Xy, b: &Xy, c: &Xy) -> f64 {
+    (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+}
+
+/// Whether `p` lies on the segment `a`-`b`
+fn on_segment(p: &Xy, a: &Xy, b: &Xy) -> bool {
+    orient(a, b, p) == 0.0
+        && p[0] >= a[0].min(b[0])
+        && p[0] <= a[0].max(b[0])
+        && p[1] >= a[1].min(b[1])
+        && p[1] <= a[1].max(b[1])
+}
+
+fn segments_intersect(a: &Xy, b: &Xy, c: &Xy, d: &Xy) -> bool {
+    let d1 = orient(c, d, a);
+    let d2 = orient(c, d, b);
+    let d3 = orient(a, b, c);
+    let d4 = orient(a, b, d);
+    if ((d1 > 0.0 && d2 < 0.0) || (d1 < 0.0 && d2 > 0.0))
+        && ((d3 > 0.0 && d4 < 0.0) || (d3 < 0.0 && d4 > 0.0))
+    {
+        return true;
+    }
+    on_segment(a, c, d) || on_segment(b, c, d) || on_segment(c, a, b) || on_segment(d, a, b)
+}
+
+/// Collect the positions along `a`-`b` (as parameter `t` in `[0, 1]`) where it meets `c`-`d`
+fn intersection_params(a: &Xy, b: &Xy, c: &Xy, d: &Xy, params: &mut Vec<f64>) {
+    if !segments_intersect(a, b, c, d) {
+        return;
+    }
+    let d1 = orient(c, d, a);
+    let d2 = orient(c, d, b);
+    if d1 == d2 {
+        // Collinear: the overlap starts and ends at end points of `c`-`d`
+        let dx = b[0] - a[0];
+        let dy = b[1] - a[1];
+        let len = dx * dx + dy * dy;
+        if len > 0.0 {
+            for p in [c, d] {
+                let t = ((p[0] - a[0]) * dx + (p[1] - a[1]) * dy) / len;
+                if t > 0.0 && t < 1.0 {
+                    params.push(t);
+                }
+            }
+        }
+    } else {
+        let t = d1 / (d1 - d2);
+        if t > 0.0 && t < 1.0 {
+            params.push(t);
+        }
+    }
+}
+
+/// Location of `p` relative to a polygon, using the even-odd rule over all rings
+fn locate_in_polygon(p: &Xy, rings: &[Vec<Xy>]) -> Location {
+    let mut inside = false;
+    for ring in rings {
+        for (a, b) in ring_segments(ring) {
+            if on_segment(p, &a, &b) {
+                return Location::Boundary;
+            }
+            if (a[1] > p[1]) != (b[1] > p[1])
+                && p[0] < (b[0] - a[0]) * (p[1] - a[1]) / (b[1] - a[1]) + a[0]
+            {
+                inside = !inside;
+            }
+        }
+    }
+    if inside {
+        Location::Interior
+    } else {
+        Location::Exterior
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shape(wkt: &str) -> Shape {
+        use geozero::wkt::Wkt;
+        use geozero::ToGeo;
+        let geom = Wkt(wkt).to_geo().unwrap();
+        let mut shape = Shape::default();
+        shape.add_geometry(&geom);
+        shape
+    }
+
+    #[test]
+    fn intersects() {
+        let square = shape("POLYGON((0 0,10 0,10 10,0 10,0 0),(4 4,6 4,6 6,4 6,4 4))");
+        assert!(shape("POINT(1 1)").intersects(&square));
+        assert!(shape("POINT(0 5)").intersects(&square));
+        assert!(!shape("POINT(5 5)").intersects(&square));
+        assert!(!shape("POINT(11 5)").intersects(&square));
+        assert!(shape("LINESTRING(-1 -1,1 1)").intersects(&square));
+        assert!(shape("LINESTRING(10 10,11 11)").intersects(&square));
+        assert!(!shape("LINESTRING(4.5 4.5,5.5 5.5)").intersects(&square));
+        assert!(shape("POLYGON((1 1,2 1,2 2,1 1))").intersects(&square));
+        assert!(shape("POLYGON((-1 -1,11 -1,11 11,-1 11,-1 -1))").intersects(&square));
+        assert!(!shape("POLYGON((4.5 4.5,5.5 4.5,5.5 5.5,4.5 4.5))").intersects(&square));
+        assert!(shape("LINESTRING(0 0,1 1)").intersects(&shape("LINESTRING(0 1,1 0)")));
+        assert!(!shape("LINESTRING(0 0,1 1)").intersects(&shape("LINESTRING(0 1,0.4 0.6)")));
+        assert!(shape("MULTIPOINT(20 20,1 1)").intersects(&square));
+    }
+
+    #[test]
+    fn within() {
+        let square = shape("POLYGON((0 0,10 0,10 10,0 10,0 0),(4 4,6 4,6 6,4 6,4 4))");
+        assert!(shape("POINT(1 1)").within(&square));
+        assert!(!shape("POINT(0 5)").within(&square));
+        assert!(!shape("POINT(5 5)").within(&square));
+        assert!(shape("LINESTRING(0 0,1 1)").within(&square));
+        assert!(!shape("LINESTRING(0 0,10 0)").within(&square));
+        assert!(!shape("LINESTRING(1 5,9 5)").within(&square));
+        assert!(shape("POLYGON((0 0,3 0,3 3,0 3,0 0))").within(&square));
+        assert!(!shape("POLYGON((3 3,7 3,7 7,3 7,3 3))").within(&square));
+        assert!(!shape("POLYGON((5 5,15 5,15 6,5 6,5 5))").within(&square));
+        assert!(shape("POLYGON((0 0,10 0,10 10,0 10,0 0),(4 4,6 4,6 6,4 6,4 4))").within(&square));
+
+        let concave = shape("POLYGON((0 0,10 0,10 10,5 1,0 10,0 0))");
+        assert!(!shape("LINESTRING(1 5,9 5)").within(&concave));
+        assert!(shape("LINESTRING(1 0.5,9 0.5)").within(&concave));
+    }
+
+    #[test]
+    fn contains() {
+        let square = shape("POLYGON((0 0,10 0,10 10,0 10,0 0),(4 4,6 4,6 6,4 6,4 4))");
+        assert!(square.contains(&[1.0, 1.0]));
+        assert!(!square.contains(&[0.0, 5.0]));
+        assert!(!square.contains(&[5.0, 5.0]));
+        let line = shape("LINESTRING(0 0,10 10)");
+        assert!(line.contains(&[5.0, 5.0]));
+        assert!(!line.contains(&[0.0, 0.0]));
+        assert!(!line.contains(&[5.0, 6.0]));
+        assert!(shape("POINT(1 2)").contains(&[1.0, 2.0]));
+    }
+
+    #[test]
+    fn contains_rejects_empty_query() {
+        let empty = geo_types::MultiPoint::<f64>(Vec::new());
+        assert!(SpatialFilter::new(SpatialPredicate::Contains, &empty).is_err());
+        let nan = geo_types::Point::new(f64::NAN, f64::NAN);
+        assert!(SpatialFilter::new(SpatialPredicate::Contains, &nan).is_err());
+        let point = geo_types::Point::new(1.0, 2.0);
+        assert!(SpatialFilter::new(SpatialPredicate::Contains, &point).is_ok());
+    }
+}
@@ -91,7 +91,8 @@ impl AsyncHttpRangeClient for MockHttpRangeClient {
         _url: &str,
         _header: &str,
     ) -> http_range_client::Result<Option<String>> {
-        unimplemented!()
+        let length = std::fs::metadata(&self.path).unwrap().len();
+        Ok(Some(length.to_string()))
     }
 }
 
@@ -1,19 +1,32 @@
 use crate::feature_generated::*;
+use crate::file_index::FgbIndex;
+use crate::geometry_predicates::{SpatialFilter, SpatialPredicate};
 use crate::header_generated::*;
-use crate::packed_r_tree::{HttpRange, HttpSearchResultItem, NodeItem, PackedRTree};
-use crate::properties_reader::FgbFeature;
+use crate::packed_r_tree::{
+    HttpRange, HttpSearchResultItem, MultiSearchResult, NodeItem, PackedRTree,
+};
+use crate::properties_reader::{
+    FeatureContext, FeatureGroups, FgbFeature, OwnedFeature, Projection,
+};
+use crate::property_filter::{Expr, PropertyFilter};
 use crate::{check_magic_bytes, HEADER_MAX_BUFFER_SIZE};
 use crate::{Error, Result};
 use byteorder::{ByteOrder, LittleEndian};
 use bytes::{BufMut, Bytes, BytesMut};
+use geo_traits::{GeometryTrait, PointTrait};
 use http_range_client::{
     AsyncBufferedHttpRangeClient, AsyncHttpRangeClient, BufferedHttpRangeClient,
 };
 use std::collections::VecDeque;
+use std::mem::size_of;
 use std::ops::Range;
+use std::sync::Arc;
 
 #[cfg(test)]
 mod mock_http_range_client;
+mod stream;
+
+pub use stream::FeatureStream;
 
 // The largest request we'll speculatively make.
 // If a single huge feature requires, we'll necessarily exceed this limit.
@@ -25,6 +38,8 @@ pub struct HttpFgbReader<T: AsyncHttpRangeClient = reqwest::Client> {
     // feature reading requires header access, therefore
     // header_buf is included in the FgbFeature struct.
     fbs: FgbFeature,
+    /// Index replacing the index of the dataset
+    external_index: Option<FgbIndex>,
 }
 
 pub struct AsyncFeatureIter<T: AsyncHttpRangeClient = reqwest::Client> {
@@ -34,6 +49,10 @@ pub struct AsyncFeatureIter<T: AsyncHttpRangeClient = reqwest::Client> {
     fbs: FgbFeature,
     /// Which features to iterate
     selection: FeatureSelection,
+    /// Exact geometry test applied to the selected features
+    spatial_filter: Option<SpatialFilter>,
+    /// Property filter applied to the selected features
+    property_filter: Option<PropertyFilter>,
     /// Number of selected features
     count: usize,
 }
@@ -99,16 +118,95 @@ impl<T: AsyncHttpRangeClient> HttpFgbReader<T> {
             fbs: FgbFeature {
                 header_buf,
                 feature_buf: Vec::new(),
+                projection: None,
+                explicit_nulls: false,
             },
+            external_index: None,
         })
     }
 
+    /// Use an index built with [`FgbIndex`] for bbox queries, e.g. for datasets without index.
+    ///
+    /// The features within a bounding box are then fetched without any index request.
+    /// Fails if the index does not match the features count of the header.
+    pub fn with_index(mut self, index: FgbIndex) -> Result<Self> {
+        index.check_features_count(self.fbs.header())?;
+        self.external_index = Some(index);
+        Ok(self)
+    }
+
     pub fn header(&self) -> Header<'_> {
         self.fbs.header()
     }
     fn header_len(&self) -> usize {
         8 + self.fbs.header_buf.len()
     }
+    /// Offset of the first feature, after the index of the dataset
+    fn feature_begin(&self) -> usize {
+        let header = self.fbs.header();
+        let count = header.features_count() as usize;
+        let index_size = if header.index_node_size() > 0 {
+            PackedRTree::index_size(count, header.index_node_size())
+        } else {
+            0
+        };
+        self.header_len() + index_size
+    }
+    /// Read a single feature by its feature number, i.e. its position in the file.
+    ///
+    /// The leaf level of the index is used as an offset table, so any feature can be fetched
+    /// with a single index request and a single feature request. Returns `None` if `n` is not
+    /// below the feature count. Fails with [`Error::NoIndex`] for files without index.
+    pub async fn feature(&mut self, n: usize) -> Result<Option<&FgbFeature>> {
+        let header = self.fbs.header();
+        let count = header.features_count() as usize;
+        let index_node_size = header.index_node_size();
+        if index_node_size == 0 || count == 0 {
+            return Err(Error::NoIndex);
+        }
+        let leaf_nodes_offset = PackedRTree::leaf_nodes_offset(count, index_node_size)?;
+        if n >= count {
+            return Ok(None);
+        }
+        let index_begin = self.header_len();
+        let feature_begin = index_begin + PackedRTree::index_size(count, index_node_size);
+
+        // To infer the length of the feature, we also fetch the leaf node of the next feature.
+        let num_nodes = if n + 1 < count { 2 } else { 1 };
+        let node_size = size_of::<NodeItem>();
+        let bytes = self
+            .client
+            .min_req_size(0)
+            .get_range(
+                index_begin + (leaf_nodes_offset + n) * node_size,
+                num_nodes * node_size,
+            )
+            .await?;
+        let node = NodeItem::from_bytes(&bytes[..node_size])?;
+        let next_node = if num_nodes == 2 {
+            Some(NodeItem::from_bytes(&bytes[node_size..])?)
+        } else {
+            None
+        };
+        let start = feature_begin + node.offset as usize;
+        let range = match next_node {
+            Some(next_node) if next_node.offset > node.offset => {
+                HttpRange::Range(start..feature_begin + next_node.offset as usize)
+            }
+            _ => HttpRange::RangeFrom(start..),
+        };
+
+        let Some(buffer) = FeatureBatch::new(VecDeque::from([range]))
+            .next_buffer(&mut self.client)
+            .await?
+        else {
+            return Ok(None);
+        };
+        self.fbs.feature_buf = buffer.to_vec();
+        // verify flatbuffer
+        let _feature = size_prefixed_root_as_feature(&self.fbs.feature_buf)?;
+        Ok(Some(&self.fbs))
+    }
     /// Select all features.
     pub async fn select_all(self) -> Result<AsyncFeatureIter<T>> {
         let header = self.fbs.header();
@@ -130,7 +228,12 @@ impl<T: AsyncHttpRangeClient> HttpFgbReader<T> {
             selection: FeatureSelection::SelectAll(SelectAll {
                 features_left: count,
                 pos: feature_base,
+                end: None,
+                end_requested: false,
+                fetched: feature_base,
             }),
+            spatial_filter: None,
+            property_filter: None,
             count: count as usize,
         })
     }
@@ -143,29 +246,32 @@ impl<T: AsyncHttpRangeClient> HttpFgbReader<T> {
         max_y: f64,
     ) -> Result<AsyncFeatureIter<T>> {
         trace!("starting: select_bbox, traversing index");
-        // Read R-Tree index and build filter for features within bbox
-        let header = self.fbs.header();
-        if header.index_node_size() == 0 || header.features_count() == 0 {
-            return Err(Error::NoIndex);
-        }
-        let count = header.features_count() as usize;
-        let header_len = self.header_len();
-
         // request up to this many extra bytes if it means we can eliminate an extra request
         let combine_request_threshold = 256 * 1024;
 
-        let list = PackedRTree::http_stream_search(
-            &mut self.client,
-            header_len,
-            count,
-            PackedRTree::DEFAULT_NODE_SIZE,
-            min_x,
-            min_y,
-            max_x,
-            max_y,
-            combine_request_threshold,
-        )
-        .await?;
+        let list = if let Some(index) = self.external_index.take() {
+            let list = index.search(min_x, min_y, max_x, max_y)?;
+            index.http_ranges(list, self.feature_begin())
+        } else {
+            // Read R-Tree index and build filter for features within bbox
+            let header_len = self.header_len();
+            let header = self.fbs.header();
+            if header.index_node_size() == 0 || header.features_count() == 0 {
+                return Err(Error::NoIndex);
+            }
+            PackedRTree::http_stream_search(
+                &mut self.client,
+                header_len,
+                header.features_count() as usize,
+                header.index_node_size(),
+                min_x,
+                min_y,
+                max_x,
+                max_y,
+                combine_request_threshold,
+            )
+            .await?
+        };
         debug_assert!(
             list.windows(2)
                 .all(|w| w[0].range.start() < w[1].range.start()),
@@ -180,9 +286,105 @@ impl<T: AsyncHttpRangeClient> HttpFgbReader<T> {
             client: self.client,
             fbs: self.fbs,
             selection,
+            spatial_filter: None,
+            property_filter: None,
             count,
         })
     }
+    /// Select features within several bounding boxes.
+    ///
+    /// The index is traversed once for all bounding boxes, sharing the merged index and feature
+    /// requests between them. Each selected feature is read once, also when it lies within more
+    /// than one bounding box. See [`FgbReader::select_bbox_many`](crate::FgbReader::select_bbox_many).
+    pub async fn select_bbox_many(mut self, bboxes: &[NodeItem]) -> Result<FeatureGroups> {
+        trace!("starting: select_bbox_many, traversing index");
+        // request up to this many extra bytes if it means we can eliminate an extra request
+        let combine_request_threshold = 256 * 1024;
+
+        let result = if let Some(index) = self.external_index.take() {
+            let result = index.search_many(bboxes)?;
+            MultiSearchResult {
+                items: index.http_ranges(result.items, self.feature_begin()),
+                groups: result.groups,
+            }
+        } else {
+            let header_len = self.header_len();
+            let header = self.fbs.header();
+            if header.index_node_size() == 0 || header.features_count() == 0 {
+                return Err(Error::NoIndex);
+            }
+            PackedRTree::http_stream_search_many(
+                &mut self.client,
+                header_len,
+                header.features_count() as usize,
+                header.index_node_size(),
+                bboxes,
+                combine_request_threshold,
+            )
+            .await?
+        };
+
+        let count = result.items.len();
+        let feature_batches =
+            FeatureBatch::make_batches(result.items, combine_request_threshold).await?;
+        let mut iter = AsyncFeatureIter {
+            client: self.client,
+            fbs: self.fbs,
+            selection: FeatureSelection::SelectBbox(SelectBbox { feature_batches }),
+            spatial_filter: None,
+            property_filter: None,
+            count,
+        };
+        let context = FeatureContext::new(&iter.fbs);
+        let mut features = Vec::with_capacity(count);
+        while iter.next().await?.is_some() {
+            features.push(OwnedFeature::take(&mut iter.fbs, &context));
+        }
+        trace!("completed: select_bbox_many");
+        Ok(FeatureGroups::new(features, result.groups))
+    }
+    /// Select features intersecting a geometry.
+    ///
+    /// The index is searched with the bounding box of `geom` and the candidates are refined with an
+    /// exact intersection test against their geometry. The number of selected features is not
+    /// known in advance.
+    pub async fn select_intersecting(
+        self,
+        geom: &impl GeometryTrait<T = f64>,
+    ) -> Result<AsyncFeatureIter<T>> {
+        let filter = SpatialFilter::new(SpatialPredicate::Intersects, geom)?;
+        self.select_spatial(filter).await
+    }
+    /// Select features within a polygon or multipolygon.
+    ///
+    /// Features touching the polygon boundary from inside are selected, features lying completely
+    /// on the boundary are not. See [`select_intersecting`](Self::select_intersecting) for details.
+    pub async fn select_within(
+        self,
+        polygon: &impl GeometryTrait<T = f64>,
+    ) -> Result<AsyncFeatureIter<T>> {
+        let filter = SpatialFilter::new(SpatialPredicate::Within, polygon)?;
+        self.select_spatial(filter).await
+    }
+    /// Select features containing a point.
+    ///
+    /// Points on the boundary of a feature, like the ring of a polygon, are not contained.
+    /// See [`select_intersecting`](Self::select_intersecting) for details.
+    pub async fn select_containing(
+        self,
+        point: &impl PointTrait<T = f64>,
+    ) -> Result<AsyncFeatureIter<T>> {
+        let filter = SpatialFilter::new(SpatialPredicate::Contains, point)?;
+        self.select_spatial(filter).await
+    }
+    async fn select_spatial(self, filter: SpatialFilter) -> Result<AsyncFeatureIter<T>> {
+        let bbox = filter.bbox();
+        let mut iter = self
+            .select_bbox(bbox.min_x, bbox.min_y, bbox.max_x, bbox.max_y)
+            .await?;
+        iter.spatial_filter = Some(filter);
+        Ok(iter)
+    }
 }
 
 impl<T: AsyncHttpRangeClient> AsyncFeatureIter<T> {
@@ -191,28 +393,100 @@ impl<T: AsyncHttpRangeClient> AsyncFeatureIter<T> {
     }
     /// Number of selected features (might be unknown)
     pub fn features_count(&self) -> Option<usize> {
-        if self.count > 0 {
+        if self.count > 0 && self.spatial_filter.is_none() && self.property_filter.is_none() {
             Some(self.count)
         } else {
             None
         }
     }
+    /// Skip features not matching the property filter expression.
+    ///
+    /// See [`FeatureIter::filter_properties`](crate::FeatureIter::filter_properties).
+    pub fn filter_properties(mut self, expr: Expr) -> Result<Self> {
+        let filter = PropertyFilter::new(expr, self.fbs.header())?;
+        self.property_filter = Some(match self.property_filter.take() {
+            Some(existing) => existing.and(filter),
+            None => filter,
+        });
+        Ok(self)
+    }
+    /// Decode only the named columns.
+    ///
+    /// See [`FeatureIter::columns`](crate::FeatureIter::columns).
+    pub fn columns(mut self, names: &[&str]) -> Result<Self> {
+        self.fbs.projection = Some(Projection::new(self.fbs.header(), names)?);
+        Ok(self)
+    }
+
+    /// Report columns without value in a feature as JSON `null` values.
+    ///
+    /// See [`FeatureIter::explicit_nulls`](crate::FeatureIter::explicit_nulls).
+    pub fn explicit_nulls(mut self) -> Self {
+        self.fbs.explicit_nulls = true;
+        self
+    }
+
     /// Read next feature
     pub async fn next(&mut self) -> Result<Option<&FgbFeature>> {
-        let Some(buffer) = self.selection.next_feature_buffer(&mut self.client).await? else {
-            return Ok(None);
-        };
+        loop {
+            let Some(buffer) = self.selection.next_feature_buffer(&mut self.client).await? else {
+                return Ok(None);
+            };
 
-        // Not zero-copy
-        self.fbs.feature_buf = buffer.to_vec();
-        // verify flatbuffer
-        let _feature = size_prefixed_root_as_feature(&self.fbs.feature_buf)?;
-        Ok(Some(&self.fbs))
+            // Not zero-copy
+            self.fbs.feature_buf = buffer.to_vec();
+            // verify flatbuffer
+            let _feature = size_prefixed_root_as_feature(&self.fbs.feature_buf)?;
+            if let Some(filter) = &self.property_filter {
+                if !filter.matches(self.fbs.header(), self.fbs.fbs_feature())? {
+                    continue;
+                }
+            }
+            match &self.spatial_filter {
+                Some(filter) if !filter.matches(&self.fbs)? => {}
+                _ => return Ok(Some(&self.fbs)),
+            }
+        }
     }
     /// Return current feature
     pub fn cur_feature(&self) -> &FgbFeature {
         &self.fbs
     }
+    /// Read the selected features of the next batch, empty if all features are read.
+    ///
+    /// A batch ends with the features of a merged range request of a bbox selection, or after
+    /// reading `DEFAULT_HTTP_FETCH_SIZE` bytes.
+    async fn next_batch(
+        &mut self,
+        context: &Arc<FeatureContext>,
+    ) -> Result<VecDeque<OwnedFeature>> {
+        if let FeatureSelection::SelectAll(select_all) = &mut self.selection {
+            select_all.request_end(&mut self.client).await;
+        }
+        let mut batch = VecDeque::new();
+        let mut size = 0;
+        while size < DEFAULT_HTTP_FETCH_SIZE {
+            if self.next().await?.is_none() {
+                break;
+            }
+            size += self.fbs.feature_buf.len();
+            batch.push_back(OwnedFeature::take(&mut self.fbs, context));
+            if self.selection.batch_done() {
+                break;
+            }
+        }
+        Ok(batch)
+    }
+}
+
+impl<T: AsyncHttpRangeClient + Send + Sync + 'static> AsyncFeatureIter<T> {
+    /// Stream of owned features, for use with `StreamExt` combinators.
+    ///
+    /// The next batch of features is read ahead while the current one is consumed.
+    /// See [`FeatureStream`].
+    pub fn into_stream(self) -> FeatureStream<T> {
+        FeatureStream::new(self)
+    }
 }
 
 enum FeatureSelection {
@@ -230,6 +504,17 @@ impl FeatureSelection {
             FeatureSelection::SelectBbox(select_bbox) => select_bbox.next_buffer(client).await,
         }
     }
+
+    /// Whether the features of the current feature batch are read
+    fn batch_done(&self) -> bool {
+        match self {
+            FeatureSelection::SelectAll(_) => false,
+            FeatureSelection::SelectBbox(select_bbox) => select_bbox
+                .feature_batches
+                .last()
+                .is_none_or(|batch| batch.feature_ranges.is_empty()),
+        }
+    }
 }
 
 struct SelectAll {
@@ -238,25 +523,66 @@ struct SelectAll {
 
     /// How many bytes into the file we've read so far
     pos: usize,
+
+    /// Size of the file, if known
+    end: Option<usize>,
+
+    /// Whether the size of the file was requested
+    end_requested: bool,
+
+    /// End of the bytes fetched so far
+    fetched: usize,
 }
 
 impl SelectAll {
+    /// Request the size of the file, to bound the read-ahead of a feature stream by it.
+    ///
+    /// Costs a HEAD request, so it is only issued once and only for streams.
+    async fn request_end<T: AsyncHttpRangeClient>(
+        &mut self,
+        client: &mut AsyncBufferedHttpRangeClient<T>,
+    ) {
+        if self.end_requested {
+            return;
+        }
+        self.end_requested = true;
+        self.end = client
+            .head_response_header("content-length")
+            .await
+            .ok()
+            .flatten()
+            .and_then(|length| length.parse().ok());
+    }
+
     async fn next_buffer<T: AsyncHttpRangeClient>(
         &mut self,
         client: &mut AsyncBufferedHttpRangeClient<T>,
     ) -> Result<Option<Bytes>> {
-        client.min_req_size(DEFAULT_HTTP_FETCH_SIZE);
-
         if self.features_left == 0 {
             return Ok(None);
         }
         self.features_left -= 1;
 
+        match self.end {
+            Some(end) => {
+                if self.pos + 4 > self.fetched {
+                    // Fetch up to DEFAULT_HTTP_FETCH_SIZE bytes, but not beyond the end of the file
+                    let length = (end.max(self.pos + 4) - self.pos).min(DEFAULT_HTTP_FETCH_SIZE);
+                    client.min_req_size(0).get_range(self.pos, length).await?;
+                    self.fetched = self.pos + length;
+                }
+                client.set_min_req_size(0);
+            }
+            None => {
+                client.min_req_size(DEFAULT_HTTP_FETCH_SIZE);
+            }
+        }
         let mut feature_buffer = BytesMut::from(client.get_range(self.pos, 4).await?);
         self.pos += 4;
         let feature_size = LittleEndian::read_u32(&feature_buffer) as usize;
         feature_buffer.put(client.get_range(self.pos, feature_size).await?);
         self.pos += feature_size;
+        self.fetched = self.fetched.max(self.pos);
 
         Ok(Some(feature_buffer.freeze()))
     }
@@ -294,6 +620,9 @@ impl SelectBbox {
 struct FeatureBatch {
     /// The byte location of each feature within the file
     feature_ranges: VecDeque<HttpRange>,
+
+    /// End of the bytes fetched so far
+    fetched: usize,
 }
 
 impl FeatureBatch {
@@ -343,50 +672,48 @@ impl FeatureBatch {
     }
 
     fn new(feature_ranges: VecDeque<HttpRange>) -> Self {
-        Self { feature_ranges }
+        Self {
+            feature_ranges,
+            fetched: 0,
+        }
     }
 
-    /// When fetching new data, how many bytes should we fetch at once.
-    /// It was computed based on the specific feature ranges of the batch
-    /// to optimize number of requests vs. wasted bytes vs. resident memory
-    fn request_size(&self) -> usize {
-        let Some(first) = self.feature_ranges.front() else {
-            return 0;
-        };
-        let Some(last) = self.feature_ranges.back() else {
-            return 0;
-        };
-
-        // `last.length()` should only be None if this batch includes the final feature
-        // in the dataset. Since we can't infer its actual length, we'll fetch only
-        // the first 4 bytes of that feature buffer, which will tell us the actual length
-        // of the feature buffer for the subsequent request.
-        let last_feature_length = last.length().unwrap_or(4);
-
-        let covering_range = first.start()..last.start() + last_feature_length;
-
-        covering_range
-            .len()
-            // Since it's all held in memory, don't fetch more than DEFAULT_HTTP_FETCH_SIZE at a time
-            // unless necessary.
-            .min(DEFAULT_HTTP_FETCH_SIZE)
+    /// End of the bytes known to belong to the features of the batch.
+    ///
+    /// Only the size prefix of the final feature of the dataset is known, since its range
+    /// has no end.
+    fn known_end(range: &HttpRange) -> usize {
+        range.start() + range.length().unwrap_or(4)
     }
 
     async fn next_buffer<T: AsyncHttpRangeClient>(
         &mut self,
         client: &mut AsyncBufferedHttpRangeClient<T>,
     ) -> Result<Option<Bytes>> {
-        let request_size = self.request_size();
-        client.set_min_req_size(request_size);
+        let batch_end = self.feature_ranges.back().map(Self::known_end);
         let Some(feature_range) = self.feature_ranges.pop_front() else {
             return Ok(None);
         };
 
         let mut pos = feature_range.start();
+        let feature_end = Self::known_end(&feature_range);
+        if feature_end > self.fetched {
+            // Fetch the following features of the batch with the same request. Since it's all
+            // held in memory, don't fetch more than DEFAULT_HTTP_FETCH_SIZE at a time unless
+            // necessary.
+            let end = batch_end.unwrap_or(feature_end);
+            let length = (end - pos)
+                .min(DEFAULT_HTTP_FETCH_SIZE)
+                .max(feature_end - pos);
+            client.min_req_size(0).get_range(pos, length).await?;
+            self.fetched = pos + length;
+        }
+        client.set_min_req_size(0);
         let mut feature_buffer = BytesMut::from(client.get_range(pos, 4).await?);
         pos += 4;
         let feature_size = LittleEndian::read_u32(&feature_buffer) as usize;
         feature_buffer.put(client.get_range(pos, feature_size).await?);
+        self.fetched = self.fetched.max(pos + feature_size);
 
         Ok(Some(feature_buffer.freeze()))
     }
@@ -417,7 +744,388 @@ mod geozero_api {
 
 #[cfg(test)]
 mod tests {
-    use crate::HttpFgbReader;
+    use crate::packed_r_tree::{NodeItem, PackedRTree};
+    use crate::{
+        Error, Expr, FallibleStreamingIterator, FgbReader, FgbWriter, FgbWriterOptions,
+        GeometryType, HttpFgbReader,
+    };
+    use geozero::{FeatureProperties, ToWkt};
+    use std::fs::File;
+    use std::io::{BufReader, Seek, SeekFrom};
+
+    #[tokio::test]
+    async fn fgb_feature_by_number() {
+        let (mut fgb, stats) = HttpFgbReader::mock_from_file("../../test/data/countries.fgb")
+            .await
+            .unwrap();
+        let mut filein = BufReader::new(File::open("../../test/data/countries.fgb").unwrap());
+        let mut expected = FgbReader::open(&mut filein).unwrap().select_all().unwrap();
+
+        for n in 0..179 {
+            let expected = expected.next().unwrap().unwrap();
+            let request_count = stats.read().unwrap().request_count;
+            let feature = fgb.feature(n).await.unwrap().unwrap();
+            assert_eq!(feature.to_wkt().unwrap(), expected.to_wkt().unwrap());
+            // At most one index request and one feature request. The length of the last
+            // feature is unknown, so its size prefix is fetched separately.
+            let max_requests = if n == 178 { 3 } else { 2 };
+            assert!(stats.read().unwrap().request_count - request_count <= max_requests);
+        }
+        assert!(fgb.feature(179).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn fgb_select_spatial() {
+        let point = geo_types::Point::new(8.5, 47.4);
+        let (fgb, _stats) = HttpFgbReader::mock_from_file("../../test/data/countries.fgb")
+            .await
+            .unwrap();
+        let mut fgb = fgb.select_containing(&point).await.unwrap();
+        assert_eq!(fgb.features_count(), None);
+        let feature = fgb.next().await.unwrap().unwrap();
+        assert_eq!(feature.property::<String>("name").unwrap(), "Switzerland");
+        assert!(fgb.next().await.unwrap().is_none());
+
+        let rect = geo_types::Rect::new((2.0, 49.0), (8.0, 54.0));
+        let (fgb, _stats) = HttpFgbReader::mock_from_file("../../test/data/countries.fgb")
+            .await
+            .unwrap();
+        let mut fgb = fgb.select_within(&rect.to_polygon()).await.unwrap();
+        let mut names = Vec::new();
+        while let Some(feature) = fgb.next().await.unwrap() {
+            names.push(feature.property::<String>("name").unwrap());
+        }
+        assert_eq!(names, vec!["Netherlands", "Luxembourg", "Belgium"]);
+
+        let line = geo_types::LineString::from(vec![(6.0, 47.5), (10.0, 47.5)]);
+        let (fgb, _stats) = HttpFgbReader::mock_from_file("../../test/data/countries.fgb")
+            .await
+            .unwrap();
+        let mut fgb = fgb.select_intersecting(&line).await.unwrap();
+        let mut names = Vec::new();
+        while let Some(feature) = fgb.next().await.unwrap() {
+            names.push(feature.property::<String>("name").unwrap());
+        }
+        assert_eq!(names, vec!["Austria", "Switzerland", "France"]);
+    }
+
+    #[tokio::test]
+    async fn fgb_feature_stream() {
+        use futures::{StreamExt, TryStreamExt};
+
+        let mut filein = BufReader::new(File::open("../../test/data/countries.fgb").unwrap());
+        let mut fgb = FgbReader::open(&mut filein)
+            .unwrap()
+            .select_bbox(-30.0, -60.0, 30.0, 60.0)
+            .unwrap();
+        let mut expected = Vec::new();
+        while let Some(feature) = fgb.next().unwrap() {
+            expected.push(feature.property::<String>("name").unwrap());
+        }
+        assert!(expected.len() > 50);
+
+        let (fgb, _stats) = HttpFgbReader::mock_from_file("../../test/data/countries.fgb")
+            .await
+            .unwrap();
+        let stream = fgb
+            .select_bbox(-30.0, -60.0, 30.0, 60.0)
+            .await
+            .unwrap()
+            .into_stream();
+        // Owned features can be consumed on another task
+        let features = tokio::spawn(stream.try_collect::<Vec<_>>())
+            .await
+            .unwrap()
+            .unwrap();
+        let names = features
+            .iter()
+            .map(|feature| feature.property::<String>("name").unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(names, expected);
+
+        let (fgb, _stats) = HttpFgbReader::mock_from_file("../../test/data/countries.fgb")
+            .await
+            .unwrap();
+        let stream = fgb
+            .select_all()
+            .await
+            .unwrap()
+            .filter_properties(Expr::like("name", "G%"))
+            .unwrap()
+            .into_stream();
+        let names = stream
+            .map_ok(|feature| feature.property_opt::<String>("name").unwrap().unwrap())
+            .take(3)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(names, vec!["Georgia", "Germany", "Greece"]);
+    }
+
+    #[tokio::test]
+    async fn fgb_select_bbox_many() {
+        // 4x4 adjacent tiles over Europe
+        let mut bboxes = Vec::new();
+        for x in 0..4 {
+            for y in 0..4 {
+                let (x, y) = (x as f64 * 5.0, 40.0 + y as f64 * 5.0);
+                bboxes.push(NodeItem::bounds(x, y, x + 5.0, y + 5.0));
+            }
+        }
+
+        let mut request_count = 0;
+        let mut bytes_requested = 0;
+        let mut expected = Vec::new();
+        for bbox in &bboxes {
+            let (fgb, stats) = HttpFgbReader::mock_from_file("../../test/data/countries.fgb")
+                .await
+                .unwrap();
+            let open_requests = stats.read().unwrap().request_count;
+            let open_bytes = stats.read().unwrap().bytes_requested;
+            let mut fgb = fgb
+                .select_bbox(bbox.min_x, bbox.min_y, bbox.max_x, bbox.max_y)
+                .await
+                .unwrap();
+            let mut names = Vec::new();
+            while let Some(feature) = fgb.next().await.unwrap() {
+                names.push(feature.property::<String>("name").unwrap());
+            }
+            expected.push(names);
+            let stats = stats.read().unwrap();
+            request_count += stats.request_count - open_requests;
+            bytes_requested += stats.bytes_requested - open_bytes;
+        }
+
+        let (fgb, stats) = HttpFgbReader::mock_from_file("../../test/data/countries.fgb")
+            .await
+            .unwrap();
+        let open_requests = stats.read().unwrap().request_count;
+        let open_bytes = stats.read().unwrap().bytes_requested;
+        let selection = fgb.select_bbox_many(&bboxes).await.unwrap();
+        assert_eq!(selection.len(), bboxes.len());
+        for (n, expected) in expected.iter().enumerate() {
+            let names = selection
+                .group(n)
+                .map(|feature| feature.property::<String>("name").unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(&names, expected);
+        }
+        let total = expected.iter().map(Vec::len).sum::<usize>();
+        assert!(selection.features().len() < total);
+
+        // Index nodes and features are fetched once for all tiles
+        let stats = stats.read().unwrap();
+        assert!(stats.request_count - open_requests < request_count);
+        assert!(stats.bytes_requested - open_bytes < bytes_requested);
+    }
+
+    #[tokio::test]
+    async fn fgb_select_bbox_with_index() {
+        use crate::FgbIndex;
+        use geozero::geojson::GeoJsonReader;
+        use geozero::GeozeroDatasource;
+
+        let mut fgb = FgbWriter::create_with_options(
+            "countries",
+            GeometryType::MultiPolygon,
+            FgbWriterOptions {
+                write_index: false,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let mut fin = BufReader::new(File::open("../../test/data/countries.geojson").unwrap());
+        GeoJsonReader(&mut fin).process(&mut fgb).unwrap();
+        let file = tempfile::NamedTempFile::new().unwrap();
+        fgb.write(std::io::BufWriter::new(file.as_file())).unwrap();
+        let path = file.path().to_str().unwrap();
+        let index = || FgbIndex::build(BufReader::new(File::open(path).unwrap()), 16).unwrap();
+
+        let (fgb, _stats) = HttpFgbReader::mock_from_file(path).await.unwrap();
+        assert!(matches!(
+            fgb.select_bbox(8.8, 47.2, 9.5, 55.3).await,
+            Err(Error::NoIndex)
+        ));
+
+        // The last feature has no known end
+        for bbox in [(8.8, 47.2, 9.5, 55.3), (-180.0, -90.0, 180.0, 90.0)] {
+            let mut filein = BufReader::new(File::open(path).unwrap());
+            let mut expected = FgbReader::open(&mut filein)
+                .unwrap()
+                .with_index(index())
+                .unwrap()
+                .select_bbox(bbox.0, bbox.1, bbox.2, bbox.3)
+                .unwrap();
+            let mut expected_names = Vec::new();
+            while let Some(feature) = expected.next().unwrap() {
+                expected_names.push(feature.property::<String>("name").unwrap());
+            }
+
+            let (fgb, _stats) = HttpFgbReader::mock_from_file(path).await.unwrap();
+            let mut fgb = fgb
+                .with_index(index())
+                .unwrap()
+                .select_bbox(bbox.0, bbox.1, bbox.2, bbox.3)
+                .await
+                .unwrap();
+            let mut names = Vec::new();
+            while let Some(feature) = fgb.next().await.unwrap() {
+                names.push(feature.property::<String>("name").unwrap());
+            }
+            assert_eq!(names, expected_names);
+
+            let (fgb, _stats) = HttpFgbReader::mock_from_file(path).await.unwrap();
+            let selection = fgb
+                .with_index(index())
+                .unwrap()
+                .select_bbox_many(&[NodeItem::bounds(bbox.0, bbox.1, bbox.2, bbox.3)])
+                .await
+                .unwrap();
+            let names = selection
+                .group(0)
+                .map(|feature| feature.property::<String>("name").unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(names, expected_names);
+        }
+    }
+
+    #[tokio::test]
+    async fn fgb_select_bbox_node_size_one() {
+        let (fgb, _stats) = HttpFgbReader::mock_from_file("../../test/data/node_size_one.fgb")
+            .await
+            .unwrap();
+        assert_eq!(fgb.header().index_node_size(), 1);
+        let mut fgb = fgb.select_bbox(1.5, 1.5, 3.5, 3.5).await.unwrap();
+        let mut count = 0;
+        while fgb.next().await.unwrap().is_some() {
+            count += 1;
+        }
+        assert_eq!(count, 2);
+    }
+
+    #[tokio::test]
+    async fn fgb_filter_properties() {
+        let (fgb, _stats) = HttpFgbReader::mock_from_file("../../test/data/countries.fgb")
+            .await
+            .unwrap();
+        let mut fgb = fgb
+            .select_bbox(8.8, 47.2, 9.5, 55.3)
+            .await
+            .unwrap()
+            .filter_properties(Expr::like("name", "%many").or(Expr::eq("name", "Denmark")))
+            .unwrap();
+        assert_eq!(fgb.features_count(), None);
+        let mut names = Vec::new();
+        while let Some(feature) = fgb.next().await.unwrap() {
+            names.push(feature.property::<String>("name").unwrap());
+        }
+        assert_eq!(names, vec!["Denmark", "Germany"]);
+    }
+
+    #[tokio::test]
+    async fn fgb_columns() {
+        let (fgb, _stats) = HttpFgbReader::mock_from_file("../../test/data/countries.fgb")
+            .await
+            .unwrap();
+        let mut fgb = fgb
+            .select_bbox(8.8, 47.2, 9.5, 55.3)
+            .await
+            .unwrap()
+            .columns(&["name"])
+            .unwrap();
+        let feature = fgb.next().await.unwrap().unwrap();
+        let props = feature.properties().unwrap();
+        assert_eq!(props.len(), 1);
+        assert!(props.contains_key("name"));
+    }
+
+    #[tokio::test]
+    async fn fgb_nearest() {
+        let (mut fgb, _stats) = HttpFgbReader::mock_from_file("../../test/data/countries.fgb")
+            .await
+            .unwrap();
+        let mut filein = BufReader::new(File::open("../../test/data/countries.fgb").unwrap());
+        let mut reader = FgbReader::open(&mut filein).unwrap();
+        let header = reader.header();
+        let count = header.features_count() as usize;
+        let node_size = header.index_node_size();
+
+        let index_begin = fgb.header_len();
+        let list = PackedRTree::http_stream_nearest(
+            &mut fgb.client,
+            index_begin,
+            count,
+            node_size,
+            8.5,
+            47.4,
+            5,
+        )
+        .await
+        .unwrap();
+        assert_eq!(list.len(), 5);
+        assert_eq!(list[0].distance, 0.0);
+        assert!(list.windows(2).all(|w| w[0].distance <= w[1].distance));
+
+        // Same features as the sync search, with ranges covering exactly one feature
+        let mut filein = BufReader::new(File::open("../../test/data/countries.fgb").unwrap());
+        filein.seek(SeekFrom::Start(index_begin as u64)).unwrap();
+        let sync_list =
+            PackedRTree::stream_nearest(&mut filein, count, node_size, 8.5, 47.4, 5).unwrap();
+        let feature_begin = index_begin + PackedRTree::index_size(count, node_size);
+        for (item, sync_item) in list.iter().zip(&sync_list) {
+            assert_eq!(item.distance, sync_item.distance);
+            assert_eq!(item.range.start(), feature_begin + sync_item.offset);
+            let feature = reader.feature(sync_item.index).unwrap().unwrap();
+            assert_eq!(item.range.length(), Some(feature.feature_buf.len()));
+        }
+    }
+
+    #[tokio::test]
+    async fn fgb_index_node_sizes() {
+        use geozero::geojson::GeoJsonReader;
+        use geozero::GeozeroDatasource;
+
+        for node_size in [2, 4, 64, 256] {
+            let mut fgb = FgbWriter::create_with_options(
+                "countries",
+                GeometryType::MultiPolygon,
+                FgbWriterOptions {
+                    index_node_size: node_size,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            let mut fin = BufReader::new(File::open("../../test/data/countries.geojson").unwrap());
+            GeoJsonReader(&mut fin).process(&mut fgb).unwrap();
+            let file = tempfile::NamedTempFile::new().unwrap();
+            fgb.write(std::io::BufWriter::new(file.as_file())).unwrap();
+            let path = file.path().to_str().unwrap();
+
+            let (fgb, _stats) = HttpFgbReader::mock_from_file(path).await.unwrap();
+            assert_eq!(fgb.header().index_node_size(), node_size);
+            let mut fgb = fgb.select_bbox(8.8, 47.2, 9.5, 55.3).await.unwrap();
+            let mut names = Vec::new();
+            while let Some(feature) = fgb.next().await.unwrap() {
+                names.push(feature.property::<String>("name").unwrap());
+            }
+            names.sort();
+            assert_eq!(
+                names,
+                [
+                    "Austria",
+                    "Denmark",
+                    "France",
+                    "Germany",
+                    "Russia",
+                    "Switzerland"
+                ]
+            );
+
+            let (mut fgb, _stats) = HttpFgbReader::mock_from_file(path).await.unwrap();
+            assert!(fgb.feature(0).await.unwrap().is_some());
+            assert!(fgb.feature(179).await.unwrap().is_none());
+        }
+    }
 
     #[tokio::test]
     async fn fgb_max_request_size() {
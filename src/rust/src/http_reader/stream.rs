@@ -0,0 +1,121 @@
+use crate::properties_reader::{FeatureContext, OwnedFeature};
+use crate::{AsyncFeatureIter, Error, Result};
+use futures_core::Stream;
+use http_range_client::AsyncHttpRangeClient;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+type BatchFuture<T> =
+    Pin<Box<dyn Future<Output = (AsyncFeatureIter<T>, Result<VecDeque<OwnedFeature>>)> + Send>>;
+
+/// Stream of the owned features selected by an [`AsyncFeatureIter`].
+///
+/// Features are read in batches of a merged range request, and the next batch is read ahead
+/// while the current one is consumed, so at most two batches are held in memory. After a read
+/// error, the features read before are yielded, followed by the error.
+///
+/// # Usage example:
+///
+/// ```
+/// use flatgeobuf::*;
+/// use futures::TryStreamExt;
+///
+/// # async fn read() -> std::result::Result<(), Box<dyn std::error::Error>> {
+/// let url = "https://flatgeobuf.org/test/data/countries.fgb";
+/// let stream = HttpFgbReader::open(url)
+///     .await?
+///     .select_bbox(8.8, 47.2, 9.5, 55.3)
+///     .await?
+///     .into_stream();
+/// let names: Vec<Option<String>> = stream
+///     .and_then(|feature| async move { feature.property_opt("name") })
+///     .try_collect()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct FeatureStream<T: AsyncHttpRangeClient> {
+    context: Arc<FeatureContext>,
+    /// Read batches, the first one being consumed
+    batches: VecDeque<VecDeque<OwnedFeature>>,
+    /// Feature iterator while no batch is read
+    iter: Option<AsyncFeatureIter<T>>,
+    /// Batch being read ahead, owning the feature iterator
+    read_ahead: Option<BatchFuture<T>>,
+    /// Read error, reported after the batches read before
+    error: Option<Error>,
+    finished: bool,
+}
+
+// The feature iterator is only moved, never pinned
+impl<T: AsyncHttpRangeClient> Unpin for FeatureStream<T> {}
+
+impl<T: AsyncHttpRangeClient + Send + Sync + 'static> FeatureStream<T> {
+    pub(crate) fn new(iter: AsyncFeatureIter<T>) -> Self {
+        FeatureStream {
+            context: FeatureContext::new(&iter.fbs),
+            batches: VecDeque::new(),
+            iter: Some(iter),
+            read_ahead: None,
+            error: None,
+            finished: false,
+        }
+    }
+
+    fn start_read_ahead(&mut self) {
+        if self.finished || self.error.is_some() || self.batches.len() > 1 {
+            return;
+        }
+        if let Some(mut iter) = self.iter.take() {
+            let context = self.context.clone();
+            self.read_ahead = Some(Box::pin(async move {
+                let batch = iter.next_batch(&context).await;
+                (iter, batch)
+            }));
+        }
+    }
+}
+
+impl<T: AsyncHttpRangeClient + Send + Sync + 'static> Stream for FeatureStream<T> {
+    type Item = Result<OwnedFeature>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if this.batches.front().is_some_and(VecDeque::is_empty) {
+                this.batches.pop_front();
+            }
+            this.start_read_ahead();
+            if let Some(read_ahead) = &mut this.read_ahead {
+                if let Poll::Ready((iter, batch)) = read_ahead.as_mut().poll(cx) {
+                    this.read_ahead = None;
+                    this.iter = Some(iter);
+                    match batch {
+                        Ok(batch) if batch.is_empty() => this.finished = true,
+                        Ok(batch) => {
+                            this.batches.push_back(batch);
+                            continue;
+                        }
+                        Err(e) => this.error = Some(e),
+                    }
+                }
+            }
+            if let Some(feature) = this.batches.front_mut().and_then(VecDeque::pop_front) {
+                return Poll::Ready(Some(Ok(feature)));
+            }
+            if this.read_ahead.is_some() {
+                return Poll::Pending;
+            }
+            this.finished = true;
+            return Poll::Ready(this.error.take().map(Err));
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let queued = self.batches.iter().map(VecDeque::len).sum();
+        (queued, None)
+    }
+}
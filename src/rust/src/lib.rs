@@ -70,8 +70,9 @@
 //! It can be easier to use than geozero in some cases because you get _random access_ to the
 //! underlying coordinates, rather than needing to receive a stream of coordinates. This means that
 //! you can write algorithms based on `geo_traits` and pass in FlatGeobuf objects _directly_.
-//! Because the underlying Flatbuffers support zero-copy access, you could even memory-map a
-//! FlatGeobuf file directly.
+//! Because the underlying Flatbuffers support zero-copy access, you can even memory-map a
+//! FlatGeobuf file directly and read it with [`FgbSliceReader`], which hands out borrowed
+//! [`FgbFeatureRef`] views without copying any feature bytes.
 //!
 //! Use [`FgbFeature::geometry_trait`] to access an opaque object that implements
 //! [`geo_traits::GeometryTrait`]. Then with [`geo_traits::GeometryTrait::as_type`] you can match
@@ -105,14 +106,24 @@
 #[macro_use]
 extern crate log;
 
+#[cfg(feature = "arrow")]
+mod arrow_reader;
+#[cfg(feature = "arrow")]
+mod arrow_writer;
+#[cfg(feature = "tokio")]
+mod async_reader;
 mod error;
+mod external_sort;
 #[allow(unused_imports, non_snake_case, clippy::all)]
 #[rustfmt::skip]
 mod feature_generated;
 mod feature_writer;
+mod file_appender;
+mod file_index;
 mod file_reader;
 mod file_writer;
 mod geo_trait_impl;
+mod geometry_predicates;
 mod geometry_reader;
 #[allow(unused_imports, non_snake_case, clippy::all)]
 #[rustfmt::skip]
@@ -120,10 +131,25 @@ mod header_generated;
 #[cfg(feature = "http")]
 mod http_reader;
 pub mod packed_r_tree;
+#[cfg(feature = "rayon")]
+mod par_reader;
+#[cfg(feature = "serde")]
+mod properties_deserializer;
 mod properties_reader;
+#[cfg(feature = "serde")]
+mod properties_serializer;
+mod property_filter;
+mod slice_reader;
+mod stream_writer;
 
-pub use error::{Error, Result};
+#[cfg(feature = "arrow")]
+pub use arrow_reader::*;
+#[cfg(feature = "tokio")]
+pub use async_reader::*;
+pub use error::{Error, Result, SchemaError};
 pub use feature_generated::*;
+pub use file_appender::*;
+pub use file_index::*;
 pub use file_reader::reader_trait::*;
 pub use file_reader::*;
 pub use file_writer::*;
@@ -131,7 +157,12 @@ pub use geometry_reader::*;
 pub use header_generated::*;
 #[cfg(feature = "http")]
 pub use http_reader::*;
+#[cfg(feature = "rayon")]
+pub use par_reader::*;
 pub use properties_reader::*;
+pub use property_filter::*;
+pub use slice_reader::*;
+pub use stream_writer::*;
 
 // Re-export used traits
 pub use fallible_streaming_iterator::FallibleStreamingIterator;
@@ -8,11 +8,14 @@ use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use http_range_client::{
     AsyncBufferedHttpRangeClient, AsyncHttpRangeClient, BufferedHttpRangeClient,
 };
-use std::cmp::min;
-use std::collections::VecDeque;
-use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+use std::cmp::{min, Ordering};
+use std::collections::{BinaryHeap, VecDeque};
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
 use std::mem::size_of;
 use std::ops::Range;
+#[cfg(feature = "tokio")]
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
 
 #[derive(Clone, PartialEq, Debug)]
 #[repr(C)]
@@ -64,8 +67,9 @@ impl NodeItem {
         })
     }
 
-    fn from_bytes(raw: &[u8]) -> Result<Self> {
-        Self::from_reader(&mut Cursor::new(raw))
+    #[cfg(any(feature = "http", feature = "tokio"))]
+    pub(crate) fn from_bytes(raw: &[u8]) -> Result<Self> {
+        Self::from_reader(raw)
     }
 
     pub fn write<W: Write>(&self, wtr: &mut W) -> std::io::Result<()> {
@@ -135,6 +139,13 @@ impl NodeItem {
         }
         true
     }
+
+    /// Squared distance from a point to the node bounds (0 if the point is inside)
+    pub fn distance_squared(&self, x: f64, y: f64) -> f64 {
+        let dx = (self.min_x - x).max(x - self.max_x).max(0.0);
+        let dy = (self.min_y - y).max(y - self.max_y).max(0.0);
+        dx * dx + dy * dy
+    }
 }
 
 /// Read `count` node items from `data` and append them to `node_items`.
@@ -173,6 +184,26 @@ fn read_node_items<R: Read + Seek>(
     Ok(node_items)
 }
 
+/// Read partial item vec from async data stream
+#[cfg(feature = "tokio")]
+async fn read_async_node_items<R: AsyncRead + AsyncSeek + Unpin>(
+    data: &mut R,
+    base: u64,
+    node_index: usize,
+    length: usize,
+) -> Result<Vec<NodeItem>> {
+    data.seek(SeekFrom::Start(
+        base + (node_index * size_of::<NodeItem>()) as u64,
+    ))
+    .await?;
+    let mut bytes = vec![0; length * size_of::<NodeItem>()];
+    data.read_exact(&mut bytes).await?;
+    bytes
+        .chunks(size_of::<NodeItem>())
+        .map(NodeItem::from_bytes)
+        .collect()
+}
+
 /// Read partial item vec from http
 #[cfg(feature = "http")]
 async fn read_http_node_items<T: AsyncHttpRangeClient>(
@@ -205,6 +236,170 @@ pub struct SearchResultItem {
     pub index: usize,
 }
 
+#[derive(Debug)]
+/// Nearest neighbour search result
+pub struct NearestResultItem {
+    /// Byte offset in feature data section
+    pub offset: usize,
+    /// Feature number
+    pub index: usize,
+    /// Distance from the query point to the feature bounding box
+    pub distance: f64,
+}
+
+/// Bbox filter search result of several query boxes
+#[derive(Debug)]
+pub struct MultiSearchResult<T> {
+    /// Features matching any of the query boxes, ordered by feature number
+    pub items: Vec<T>,
+    /// Positions in `items` of the features matching each query box
+    pub groups: Vec<Vec<usize>>,
+}
+
+/// Node queued for best-first nearest neighbour traversal
+struct NearestCandidate<T> {
+    /// Squared distance from the query point to the node bounds
+    distance: f64,
+    /// Node position within the index
+    pos: usize,
+    level: usize,
+    item: T,
+}
+
+impl<T> PartialEq for NearestCandidate<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T> Eq for NearestCandidate<T> {}
+
+impl<T> PartialOrd for NearestCandidate<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for NearestCandidate<T> {
+    /// Reversed order, so that `BinaryHeap` pops the closest node first.
+    /// Ties are broken by node position, which visits parents before their children
+    /// and returns equally distant features in feature order.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .distance
+            .total_cmp(&self.distance)
+            .then_with(|| other.pos.cmp(&self.pos))
+    }
+}
+
+/// Best-first search for the `k` features closest to `(x, y)`.
+///
+/// `read_nodes` returns the node items for a range of node positions.
+fn nearest_search(
+    level_bounds: &[Range<usize>],
+    node_size: u16,
+    x: f64,
+    y: f64,
+    k: usize,
+    mut read_nodes: impl FnMut(Range<usize>) -> Result<Vec<NodeItem>>,
+) -> Result<Vec<NearestResultItem>> {
+    let leaf_nodes_offset = level_bounds
+        .first()
+        .expect("RTree has at least one level when node_size >= 2 and num_items > 0")
+        .start;
+    let mut results = Vec::new();
+    if k == 0 {
+        return Ok(results);
+    }
+    let mut queue = BinaryHeap::new();
+    let mut push_nodes = |queue: &mut BinaryHeap<_>, nodes: Range<usize>, level: usize| {
+        for (pos, node_item) in nodes.clone().zip(read_nodes(nodes)?) {
+            queue.push(NearestCandidate {
+                distance: node_item.distance_squared(x, y),
+                pos,
+                level,
+                item: node_item.offset,
+            });
+        }
+        Ok::<_, Error>(())
+    };
+    push_nodes(&mut queue, 0..1, level_bounds.len() - 1)?;
+    while let Some(candidate) = queue.pop() {
+        if candidate.level == 0 {
+            results.push(NearestResultItem {
+                offset: candidate.item as usize,
+                index: candidate.pos - leaf_nodes_offset,
+                distance: candidate.distance.sqrt(),
+            });
+            if results.len() == k {
+                break;
+            }
+        } else {
+            let level = candidate.level - 1;
+            let start = candidate.item as usize;
+            let end = min(start + node_size as usize, level_bounds[level].end);
+            trace!("expanding node {} at level {level}", candidate.pos);
+            push_nodes(&mut queue, start..end, level)?;
+        }
+    }
+    Ok(results)
+}
+
+/// Breadth-first search for the features intersecting any of the `queries` boxes.
+///
+/// Each node is read once, together with the query boxes intersecting its parent.
+/// `read_nodes` returns the node items for a range of node positions.
+fn search_many(
+    level_bounds: &[Range<usize>],
+    node_size: u16,
+    queries: &[NodeItem],
+    mut read_nodes: impl FnMut(Range<usize>) -> Result<Vec<NodeItem>>,
+) -> Result<MultiSearchResult<SearchResultItem>> {
+    let leaf_nodes_offset = level_bounds
+        .first()
+        .expect("RTree has at least one level when node_size >= 2 and num_items > 0")
+        .start;
+    let mut results = MultiSearchResult {
+        items: Vec::new(),
+        groups: vec![Vec::new(); queries.len()],
+    };
+    if queries.is_empty() {
+        return Ok(results);
+    }
+    let mut queue = VecDeque::new();
+    queue.push_back((
+        0,
+        level_bounds.len() - 1,
+        (0..queries.len()).collect::<Vec<_>>(),
+    ));
+    while let Some((node_index, level, parent_queries)) = queue.pop_front() {
+        let end = min(node_index + node_size as usize, level_bounds[level].end);
+        let node_items = read_nodes(node_index..end)?;
+        for (pos, node_item) in (node_index..end).zip(&node_items) {
+            let matching = parent_queries
+                .iter()
+                .copied()
+                .filter(|&q| queries[q].intersects(node_item))
+                .collect::<Vec<_>>();
+            if matching.is_empty() {
+                continue;
+            }
+            if level == 0 {
+                for &q in &matching {
+                    results.groups[q].push(results.items.len());
+                }
+                results.items.push(SearchResultItem {
+                    offset: node_item.offset as usize,
+                    index: pos - leaf_nodes_offset,
+                });
+            } else {
+                queue.push_back((node_item.offset as usize, level - 1, matching));
+            }
+        }
+    }
+    Ok(results)
+}
+
 const HILBERT_MAX: u32 = (1 << 16) - 1;
 
 // Based on public domain code at https://github.com/rawrunprotected/hilbert_curves
@@ -272,6 +467,11 @@ fn hilbert_bbox(r: &NodeItem, hilbert_max: u32, extent: &NodeItem) -> u32 {
     hilbert(x, y)
 }
 
+/// Hilbert curve value of the node center within `extent`, as used by [`hilbert_sort`].
+pub(crate) fn hilbert_value(r: &NodeItem, extent: &NodeItem) -> u32 {
+    hilbert_bbox(r, HILBERT_MAX, extent)
+}
+
 /// Sort leaf node items in descending Hilbert curve order.
 ///
 /// Writers typically sort feature leaf nodes before building the packed tree to improve spatial
@@ -284,6 +484,82 @@ pub fn hilbert_sort(items: &mut [NodeItem], extent: &NodeItem) {
     });
 }
 
+// Interleave the lower 16 bits of x and y
+fn morton(x: u32, y: u32) -> u32 {
+    fn spread(mut v: u32) -> u32 {
+        v &= 0xFFFF;
+        v = (v | (v << 8)) & 0x00FF00FF;
+        v = (v | (v << 4)) & 0x0F0F0F0F;
+        v = (v | (v << 2)) & 0x33333333;
+        (v | (v << 1)) & 0x55555555
+    }
+    (spread(y) << 1) | spread(x)
+}
+
+/// Z-order (Morton) curve value of the node center within `extent`, as used by [`morton_sort`].
+pub(crate) fn morton_value(r: &NodeItem, extent: &NodeItem) -> u32 {
+    let x = (HILBERT_MAX as f64 * ((r.min_x + r.max_x) / 2.0 - extent.min_x) / extent.width())
+        .floor() as u32;
+    let y = (HILBERT_MAX as f64 * ((r.min_y + r.max_y) / 2.0 - extent.min_y) / extent.height())
+        .floor() as u32;
+    morton(x, y)
+}
+
+/// Sort leaf node items in ascending Z-order (Morton) curve order.
+///
+/// Cheaper to compute than the Hilbert order, with less locality at the quadrant boundaries.
+pub fn morton_sort(items: &mut [NodeItem], extent: &NodeItem) {
+    items.sort_by_cached_key(|item| morton_value(item, extent));
+}
+
+fn center_x(r: &NodeItem) -> f64 {
+    (r.min_x + r.max_x) / 2.0
+}
+
+fn center_y(r: &NodeItem) -> f64 {
+    (r.min_y + r.max_y) / 2.0
+}
+
+/// Map a float to an integer with the same order as [`f64::total_cmp`]
+fn ordered_bits(v: f64) -> u64 {
+    let bits = v.to_bits();
+    if bits >> 63 == 1 {
+        !bits
+    } else {
+        bits | (1 << 63)
+    }
+}
+
+/// Sort key of the first STR pass, ordering by node center x
+pub(crate) fn str_x_key(r: &NodeItem) -> u64 {
+    ordered_bits(center_x(r))
+}
+
+/// Sort key within a vertical STR slice, ordering by node center y
+pub(crate) fn str_y_key(r: &NodeItem) -> u64 {
+    ordered_bits(center_y(r))
+}
+
+/// Number of items per vertical slice of [`str_sort`]
+pub(crate) fn str_slice_size(num_items: usize, node_size: u16) -> usize {
+    let node_size = node_size.max(2) as usize;
+    let num_leaf_nodes = num_items.div_ceil(node_size);
+    let num_slices = (num_leaf_nodes as f64).sqrt().ceil() as usize;
+    (num_slices * node_size).max(1)
+}
+
+/// Sort leaf node items for Sort-Tile-Recursive packing.
+///
+/// Items are sorted by center x into vertical slices of `sqrt(n / node_size)` leaf nodes, which
+/// are sorted by center y. Each run of `node_size` items then forms a compact tile.
+pub fn str_sort(items: &mut [NodeItem], node_size: u16) {
+    items.sort_by_cached_key(str_x_key);
+    let slice_size = str_slice_size(items.len(), node_size);
+    for slice in items.chunks_mut(slice_size) {
+        slice.sort_by_cached_key(str_y_key);
+    }
+}
+
 /// Compute the overall bounding box (extent) from a slice of node items.
 pub fn calc_extent(nodes: &[NodeItem]) -> NodeItem {
     nodes.iter().fold(NodeItem::create(0), |mut a, b| {
@@ -387,6 +663,20 @@ impl PackedRTree {
         level_bounds
     }
 
+    /// Position of the first leaf node within the packed index (in nodes, not bytes).
+    ///
+    /// Leaf nodes are stored in feature order, so the leaf node of feature `n` is found at
+    /// `leaf_nodes_offset(..) + n`. Its `offset` is the byte offset of the feature within the
+    /// feature data section, which turns the leaf level into an offset table for random access.
+    pub(crate) fn leaf_nodes_offset(num_items: usize, node_size: u16) -> Result<usize> {
+        Self::validate_num_items(num_items)?;
+        let level_bounds = PackedRTree::generate_level_bounds(num_items, node_size.clamp(2, 65535));
+        Ok(level_bounds
+            .first()
+            .expect("RTree has at least one level when node_size >= 2 and num_items > 0")
+            .start)
+    }
+
     fn generate_nodes(&mut self) {
         for level in 0..self.level_bounds.len() - 1 {
             let children_level = &self.level_bounds[level];
@@ -442,6 +732,15 @@ impl PackedRTree {
         self.node_items.len()
     }
 
+    /// Offset of the leaf node of feature number `index`
+    #[cfg(feature = "http")]
+    pub(crate) fn leaf_offset(&self, index: usize) -> Option<u64> {
+        let leaf_nodes_offset = self.num_nodes() - self.num_leaf_nodes;
+        self.node_items
+            .get(leaf_nodes_offset + index)
+            .map(|node| node.offset)
+    }
+
     /// Build a packed R-Tree from leaf nodes.
     ///
     /// - `nodes` must contain **leaf nodes** (one per feature), whose `offset` values point into the
@@ -466,6 +765,90 @@ impl PackedRTree {
         Ok(tree)
     }
 
+    /// Build a packed R-Tree level by level and write it to `out`.
+    ///
+    /// Unlike [`Self::build`], the tree is not held in memory: every level is buffered in a
+    /// temporary file while the leaf nodes are streamed in, so memory use does not depend on the
+    /// number of items. The written index is identical to `build(..)` followed by
+    /// [`Self::stream_write`].
+    ///
+    /// - `nodes` must yield exactly `num_items` **leaf nodes** in index order, otherwise an
+    ///   [`InvalidData`](std::io::ErrorKind::InvalidData) IO error is returned.
+    /// - `node_size` is the branching factor; values are clamped to `[2, 65535]`.
+    pub fn stream_build<W: Write>(
+        nodes: impl IntoIterator<Item = Result<NodeItem>>,
+        num_items: usize,
+        node_size: u16,
+        out: &mut W,
+    ) -> Result<()> {
+        Self::validate_num_items(num_items)?;
+        let node_size = node_size.clamp(2, 65535) as usize;
+        let level_bounds = PackedRTree::generate_level_bounds(num_items, node_size as u16);
+        let mut levels = Vec::with_capacity(level_bounds.len());
+        for _ in &level_bounds {
+            levels.push(BufWriter::new(tempfile::tempfile()?));
+        }
+        // Parent node under construction and its number of children, for each level below the root
+        let mut parents = level_bounds[..level_bounds.len() - 1]
+            .iter()
+            .map(|children| (NodeItem::create(children.start as u64), 0))
+            .collect::<Vec<_>>();
+
+        fn push(
+            levels: &mut [BufWriter<File>],
+            parents: &mut [(NodeItem, usize)],
+            level: usize,
+            node: &NodeItem,
+            node_size: usize,
+        ) -> std::io::Result<()> {
+            node.write(&mut levels[level])?;
+            if let Some((parent, num_children)) = parents.get_mut(level) {
+                parent.expand(node);
+                *num_children += 1;
+                if *num_children == node_size {
+                    let next = NodeItem::create(parent.offset + node_size as u64);
+                    let full = std::mem::replace(parent, next);
+                    *num_children = 0;
+                    push(levels, parents, level + 1, &full, node_size)?;
+                }
+            }
+            Ok(())
+        }
+
+        let count_mismatch = |count| {
+            Error::IO(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Expected {num_items} leaf nodes, found {count}"),
+            ))
+        };
+        let mut count = 0;
+        for node in nodes {
+            if count == num_items {
+                return Err(count_mismatch("more".to_string()));
+            }
+            push(&mut levels, &mut parents, 0, &node?, node_size)?;
+            count += 1;
+        }
+        if count != num_items {
+            return Err(count_mismatch(count.to_string()));
+        }
+        for level in 0..parents.len() {
+            let (parent, num_children) = &parents[level];
+            if *num_children > 0 {
+                let parent = parent.clone();
+                push(&mut levels, &mut parents, level + 1, &parent, node_size)?;
+            }
+        }
+
+        // Levels are stored top-down
+        for level in levels.into_iter().rev() {
+            let mut level = level.into_inner().map_err(|e| e.into_error())?;
+            level.rewind()?;
+            std::io::copy(&mut level, out)?;
+        }
+        Ok(())
+    }
+
     /// Read a packed R-Tree index from a byte stream into memory.
     ///
     /// The reader must be positioned at the start of the index bytes (i.e. right after the
@@ -585,6 +968,7 @@ impl PackedRTree {
         max_y: f64,
     ) -> Result<Vec<SearchResultItem>> {
         Self::validate_num_items(num_items)?;
+        let node_size = node_size.clamp(2, 65535);
         let bounds = NodeItem::bounds(min_x, min_y, max_x, max_y);
         let level_bounds = PackedRTree::generate_level_bounds(num_items, node_size);
         let Range {
@@ -638,6 +1022,249 @@ impl PackedRTree {
         Ok(results)
     }
 
+    /// Search an in-memory index for features intersecting any of the `queries` boxes.
+    ///
+    /// The tree is traversed once for all query boxes, visiting each node at most once. Every
+    /// matching feature is returned once in [`MultiSearchResult::items`], ordered by feature
+    /// number, and [`MultiSearchResult::groups`] lists the matches of each query box.
+    pub fn search_many(&self, queries: &[NodeItem]) -> Result<MultiSearchResult<SearchResultItem>> {
+        search_many(
+            &self.level_bounds,
+            self.branching_factor,
+            queries,
+            |nodes| Ok(self.node_items[nodes].to_vec()),
+        )
+    }
+
+    /// Search an index on a seekable stream for features intersecting any of the `queries` boxes,
+    /// without loading the entire index into memory.
+    ///
+    /// Like [`Self::stream_search`], the stream must be positioned at the start of the index
+    /// bytes and is advanced to the end of the index afterwards. See [`Self::search_many`] for
+    /// the result.
+    pub fn stream_search_many<R: Read + Seek>(
+        data: &mut R,
+        num_items: usize,
+        node_size: u16,
+        queries: &[NodeItem],
+    ) -> Result<MultiSearchResult<SearchResultItem>> {
+        Self::validate_num_items(num_items)?;
+        let node_size = node_size.clamp(2, 65535);
+        let level_bounds = PackedRTree::generate_level_bounds(num_items, node_size);
+        let num_nodes = level_bounds
+            .first()
+            .expect("RTree has at least one level when node_size >= 2 and num_items > 0")
+            .end;
+
+        // current position must be start of index
+        let index_base = data.stream_position()?;
+        let results = search_many(&level_bounds, node_size, queries, |nodes| {
+            read_node_items(data, index_base, nodes.start, nodes.len())
+        })?;
+        // Skip rest of index
+        data.seek(SeekFrom::Start(
+            index_base + (num_nodes * size_of::<NodeItem>()) as u64,
+        ))?;
+        Ok(results)
+    }
+
+    /// Search an index on an async seekable stream without loading the entire index into memory.
+    ///
+    /// Async version of [`Self::stream_search`], with the same requirements on the stream
+    /// position and parameters.
+    #[cfg(feature = "tokio")]
+    pub async fn async_stream_search<R: AsyncRead + AsyncSeek + Unpin>(
+        data: &mut R,
+        num_items: usize,
+        node_size: u16,
+        min_x: f64,
+        min_y: f64,
+        max_x: f64,
+        max_y: f64,
+    ) -> Result<Vec<SearchResultItem>> {
+        Self::validate_num_items(num_items)?;
+        let node_size = node_size.clamp(2, 65535);
+        let bounds = NodeItem::bounds(min_x, min_y, max_x, max_y);
+        let level_bounds = PackedRTree::generate_level_bounds(num_items, node_size);
+        let Range {
+            start: leaf_nodes_offset,
+            end: num_nodes,
+        } = level_bounds
+            .first()
+            .expect("RTree has at least one level when node_size >= 2 and num_items > 0");
+
+        // current position must be start of index
+        let index_base = data.stream_position().await?;
+
+        // use ordered search queue to make index traversal in sequential order
+        let mut queue = VecDeque::new();
+        queue.push_back((0, level_bounds.len() - 1));
+        let mut results = Vec::new();
+
+        while let Some((node_index, level)) = queue.pop_front() {
+            trace!("popped next node_index: {node_index}, level: {level}");
+            let is_leaf_node = node_index >= num_nodes - num_items;
+            // find the end index of the node
+            let end = min(node_index + node_size as usize, level_bounds[level].end);
+            let node_items =
+                read_async_node_items(data, index_base, node_index, end - node_index).await?;
+            // search through child nodes
+            for (pos, node_item) in (node_index..end).zip(&node_items) {
+                if !bounds.intersects(node_item) {
+                    continue;
+                }
+                if is_leaf_node {
+                    results.push(SearchResultItem {
+                        offset: node_item.offset as usize,
+                        index: pos - leaf_nodes_offset,
+                    });
+                } else {
+                    queue.push_back((node_item.offset as usize, level - 1));
+                }
+            }
+        }
+        // Skip rest of index
+        data.seek(SeekFrom::Start(
+            index_base + (num_nodes * size_of::<NodeItem>()) as u64,
+        ))
+        .await?;
+        Ok(results)
+    }
+
+    /// Search an in-memory index for the `k` features closest to the point `(x, y)`.
+    ///
+    /// The tree is traversed best-first by the distance to the node bounds, so only nodes which
+    /// can contain one of the `k` nearest features are visited. The result is ordered by
+    /// distance to the feature bounding boxes, equally distant features by feature number.
+    pub fn nearest(&self, x: f64, y: f64, k: usize) -> Result<Vec<NearestResultItem>> {
+        nearest_search(
+            &self.level_bounds,
+            self.branching_factor,
+            x,
+            y,
+            k,
+            |nodes| Ok(self.node_items[nodes].to_vec()),
+        )
+    }
+
+    /// Search an index on a seekable stream for the `k` features closest to the point `(x, y)`,
+    /// without loading the entire index into memory.
+    ///
+    /// Like [`Self::stream_search`], the stream must be positioned at the start of the index
+    /// bytes and is advanced to the end of the index afterwards. See [`Self::nearest`] for the
+    /// ordering of the results.
+    pub fn stream_nearest<R: Read + Seek>(
+        data: &mut R,
+        num_items: usize,
+        node_size: u16,
+        x: f64,
+        y: f64,
+        k: usize,
+    ) -> Result<Vec<NearestResultItem>> {
+        Self::validate_num_items(num_items)?;
+        let node_size = node_size.clamp(2, 65535);
+        let level_bounds = PackedRTree::generate_level_bounds(num_items, node_size);
+        let num_nodes = level_bounds
+            .first()
+            .expect("RTree has at least one level when node_size >= 2 and num_items > 0")
+            .end;
+
+        // current position must be start of index
+        let index_base = data.stream_position()?;
+        let results = nearest_search(&level_bounds, node_size, x, y, k, |nodes| {
+            read_node_items(data, index_base, nodes.start, nodes.len())
+        })?;
+        // Skip rest of index
+        data.seek(SeekFrom::Start(
+            index_base + (num_nodes * size_of::<NodeItem>()) as u64,
+        ))?;
+        Ok(results)
+    }
+
+    /// Search an index via HTTP range requests for the `k` features closest to the point
+    /// `(x, y)`.
+    ///
+    /// Returns the byte ranges of the features, ordered by distance as in [`Self::nearest`].
+    #[cfg(feature = "http")]
+    #[allow(clippy::too_many_arguments)]
+    pub async fn http_stream_nearest<T: AsyncHttpRangeClient>(
+        client: &mut AsyncBufferedHttpRangeClient<T>,
+        index_begin: usize,
+        num_items: usize,
+        branching_factor: u16,
+        x: f64,
+        y: f64,
+        k: usize,
+    ) -> Result<Vec<HttpNearestResultItem>> {
+        if num_items == 0 || k == 0 {
+            return Ok(vec![]);
+        }
+        Self::validate_num_items(num_items)?;
+        let branching_factor = branching_factor.clamp(2, 65535);
+        let level_bounds = PackedRTree::generate_level_bounds(num_items, branching_factor);
+        let feature_begin = index_begin + PackedRTree::index_size(num_items, branching_factor);
+        debug!("http_stream_nearest - index_begin: {index_begin}, feature_begin: {feature_begin} num_items: {num_items}, branching_factor: {branching_factor}, point: ({x}, {y}), k: {k}");
+
+        // Queued items carry the node offset and, for leaf nodes, the offset of the next feature
+        let mut queue = BinaryHeap::new();
+        let mut results = Vec::new();
+        let mut level = level_bounds.len() - 1;
+        let mut nodes = 0..1;
+        loop {
+            // To infer the length of the features, we fetch an extra leaf node
+            let fetch_end = if level == 0 {
+                min(nodes.end + 1, level_bounds[0].end)
+            } else {
+                nodes.end
+            };
+            let node_items =
+                read_http_node_items(client, index_begin, &(nodes.start..fetch_end)).await?;
+            for (node_pos, node_item) in node_items.iter().enumerate().take(nodes.len()) {
+                let next_offset = if level == 0 {
+                    node_items.get(node_pos + 1).map(|next| next.offset)
+                } else {
+                    None
+                };
+                queue.push(NearestCandidate {
+                    distance: node_item.distance_squared(x, y),
+                    pos: nodes.start + node_pos,
+                    level,
+                    item: (node_item.offset, next_offset),
+                });
+            }
+
+            // Collect leaf nodes until the closest candidate needs to be expanded
+            let next = loop {
+                let Some(candidate) = queue.pop() else {
+                    break None;
+                };
+                if candidate.level > 0 {
+                    break Some(candidate);
+                }
+                let (offset, next_offset) = candidate.item;
+                let start = feature_begin + offset as usize;
+                let range = match next_offset {
+                    Some(end) => HttpRange::Range(start..feature_begin + end as usize),
+                    None => HttpRange::RangeFrom(start..),
+                };
+                results.push(HttpNearestResultItem {
+                    range,
+                    distance: candidate.distance.sqrt(),
+                });
+                if results.len() == k {
+                    break None;
+                }
+            };
+            let Some(candidate) = next else {
+                break;
+            };
+            level = candidate.level - 1;
+            let start = candidate.item.0 as usize;
+            nodes = start..min(start + branching_factor as usize, level_bounds[level].end);
+        }
+        Ok(results)
+    }
+
     #[cfg(feature = "http")]
     #[allow(clippy::too_many_arguments)]
     pub async fn http_stream_search<T: AsyncHttpRangeClient>(
@@ -656,6 +1283,7 @@ impl PackedRTree {
             return Ok(vec![]);
         }
         Self::validate_num_items(num_items)?;
+        let branching_factor = branching_factor.clamp(2, 65535);
         let level_bounds = PackedRTree::generate_level_bounds(num_items, branching_factor);
         let feature_begin = index_begin + PackedRTree::index_size(num_items, branching_factor);
         debug!("http_stream_search - index_begin: {index_begin}, feature_begin: {feature_begin} num_items: {num_items}, branching_factor: {branching_factor}, level_bounds: {level_bounds:?}, GPS bounds:[({min_x}, {min_y}), ({max_x},{max_y})]");
@@ -764,6 +1392,122 @@ impl PackedRTree {
         Ok(results)
     }
 
+    /// Search an index via HTTP range requests for features intersecting any of the `queries`
+    /// boxes.
+    ///
+    /// The tree is traversed once for all query boxes. Node ranges needed by different query
+    /// boxes are merged into the same requests, as in [`Self::http_stream_search`]. See
+    /// [`Self::search_many`] for the result.
+    #[cfg(feature = "http")]
+    pub async fn http_stream_search_many<T: AsyncHttpRangeClient>(
+        client: &mut AsyncBufferedHttpRangeClient<T>,
+        index_begin: usize,
+        num_items: usize,
+        branching_factor: u16,
+        queries: &[NodeItem],
+        combine_request_threshold: usize,
+    ) -> Result<MultiSearchResult<HttpSearchResultItem>> {
+        let mut results = MultiSearchResult {
+            items: Vec::new(),
+            groups: vec![Vec::new(); queries.len()],
+        };
+        if num_items == 0 || queries.is_empty() {
+            return Ok(results);
+        }
+        Self::validate_num_items(num_items)?;
+        let branching_factor = branching_factor.clamp(2, 65535);
+        let level_bounds = PackedRTree::generate_level_bounds(num_items, branching_factor);
+        let feature_begin = index_begin + PackedRTree::index_size(num_items, branching_factor);
+        debug!("http_stream_search_many - index_begin: {index_begin}, feature_begin: {feature_begin} num_items: {num_items}, branching_factor: {branching_factor}, {} queries", queries.len());
+
+        /// Nodes fetched with one request, with the children of each parent node to search and
+        /// the query boxes intersecting the parent
+        #[derive(Debug)]
+        struct NodeRange {
+            level: usize,
+            nodes: Range<usize>,
+            children: Vec<(Range<usize>, Vec<usize>)>,
+        }
+
+        let mut queue = VecDeque::new();
+        queue.push_back(NodeRange {
+            level: level_bounds.len() - 1,
+            nodes: 0..1,
+            children: vec![(0..1, (0..queries.len()).collect())],
+        });
+
+        while let Some(node_range) = queue.pop_front() {
+            debug!(
+                "next: level {}, nodes {:?}. {} items left in queue",
+                node_range.level,
+                node_range.nodes,
+                queue.len()
+            );
+            let node_items = read_http_node_items(client, index_begin, &node_range.nodes).await?;
+            for (children, parent_queries) in node_range.children {
+                for pos in children {
+                    let node_pos = pos - node_range.nodes.start;
+                    let node_item = &node_items[node_pos];
+                    let matching = parent_queries
+                        .iter()
+                        .copied()
+                        .filter(|&q| queries[q].intersects(node_item))
+                        .collect::<Vec<_>>();
+                    if matching.is_empty() {
+                        continue;
+                    }
+
+                    if node_range.level == 0 {
+                        // leaf node, the next leaf node is fetched to infer the feature length
+                        let start = feature_begin + node_item.offset as usize;
+                        let range = match node_items.get(node_pos + 1) {
+                            Some(next_node_item) => HttpRange::Range(
+                                start..feature_begin + next_node_item.offset as usize,
+                            ),
+                            None => {
+                                debug_assert_eq!(pos, level_bounds[0].end - 1);
+                                HttpRange::RangeFrom(start..)
+                            }
+                        };
+                        for &q in &matching {
+                            results.groups[q].push(results.items.len());
+                        }
+                        results.items.push(HttpSearchResultItem { range });
+                        continue;
+                    }
+
+                    let children_level = node_range.level - 1;
+                    let level_end = level_bounds[children_level].end;
+                    let start = node_item.offset as usize;
+                    let children_nodes = start..min(start + branching_factor as usize, level_end);
+                    let fetch_end = if children_level == 0 {
+                        min(children_nodes.end + 1, level_end)
+                    } else {
+                        children_nodes.end
+                    };
+                    match queue.back_mut() {
+                        Some(tail)
+                            if tail.level == children_level
+                                && children_nodes.start.saturating_sub(tail.nodes.end)
+                                    * size_of::<NodeItem>()
+                                    <= combine_request_threshold =>
+                        {
+                            // Merge the ranges to avoid an extra request
+                            tail.nodes.end = fetch_end;
+                            tail.children.push((children_nodes, matching));
+                        }
+                        _ => queue.push_back(NodeRange {
+                            level: children_level,
+                            nodes: children_nodes.start..fetch_end,
+                            children: vec![(children_nodes, matching)],
+                        }),
+                    }
+                }
+            }
+        }
+        Ok(results)
+    }
+
     pub fn size(&self) -> usize {
         self.num_nodes() * size_of::<NodeItem>()
     }
@@ -862,6 +1606,15 @@ pub(crate) mod http {
         /// Byte offset in feature data section
         pub range: HttpRange,
     }
+
+    #[derive(Debug)]
+    /// Nearest neighbour search result
+    pub struct HttpNearestResultItem {
+        /// Byte range of the feature
+        pub range: HttpRange,
+        /// Distance from the query point to the feature bounding box
+        pub distance: f64,
+    }
 }
 #[cfg(feature = "http")]
 pub(crate) use http::*;
@@ -910,6 +1663,7 @@ mod inspect {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::io::Cursor;
     type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
     #[test]
@@ -936,6 +1690,32 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn morton_and_str_sort() {
+        let grid = |size: usize| -> Vec<NodeItem> {
+            (0..size * size)
+                .map(|i| {
+                    let (x, y) = ((i / size) as f64, (i % size) as f64);
+                    NodeItem::bounds(x, y, x, y)
+                })
+                .collect()
+        };
+
+        let mut nodes = grid(2);
+        let extent = calc_extent(&nodes);
+        morton_sort(&mut nodes, &extent);
+        let centers = nodes.iter().map(|n| (n.min_x, n.min_y)).collect::<Vec<_>>();
+        assert_eq!(centers, [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)]);
+
+        // 16 leaf nodes in 4 slices of 4x16 points, forming tiles of 4x4 points
+        let mut nodes = grid(16);
+        str_sort(&mut nodes, 16);
+        for leaf in nodes.chunks(16) {
+            let bounds = calc_extent(leaf);
+            assert_eq!((bounds.width(), bounds.height()), (3.0, 3.0));
+        }
+    }
+
     #[test]
     fn tree_19items_roundtrip_stream_search() -> Result<()> {
         let mut nodes = vec![
@@ -1059,6 +1839,168 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn tree_nearest() -> Result<()> {
+        use rand::distr::{Distribution, Uniform};
+
+        let unif = Uniform::try_from(0.0..1000.0)?;
+        let mut rng = rand::rng();
+
+        let mut nodes = Vec::new();
+        for _ in 0..1000 {
+            let x = unif.sample(&mut rng);
+            let y = unif.sample(&mut rng);
+            nodes.push(NodeItem::bounds(x, y, x + 5.0, y + 5.0));
+        }
+        let extent = calc_extent(&nodes);
+        hilbert_sort(&mut nodes, &extent);
+        for (i, node) in nodes.iter_mut().enumerate() {
+            node.offset = i as u64 * 100;
+        }
+
+        for node_size in [2, 16] {
+            let tree = PackedRTree::build(&nodes, &extent, node_size)?;
+            let mut tree_data: Vec<u8> = Vec::new();
+            tree.stream_write(&mut tree_data)?;
+
+            for (x, y) in [(500.0, 500.0), (-100.0, 20.0), (1000.0, 1000.0)] {
+                let mut expected: Vec<(f64, usize)> = nodes
+                    .iter()
+                    .enumerate()
+                    .map(|(i, node)| (node.distance_squared(x, y).sqrt(), i))
+                    .collect();
+                expected.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+                expected.truncate(10);
+
+                let list = tree.nearest(x, y, 10)?;
+                let found: Vec<(f64, usize)> = list
+                    .iter()
+                    .map(|item| (item.distance, item.index))
+                    .collect();
+                assert_eq!(found, expected);
+                for item in &list {
+                    assert_eq!(item.offset, item.index * 100);
+                }
+
+                let mut reader = Cursor::new(&tree_data);
+                let list =
+                    PackedRTree::stream_nearest(&mut reader, nodes.len(), node_size, x, y, 10)?;
+                let found: Vec<(f64, usize)> = list
+                    .iter()
+                    .map(|item| (item.distance, item.index))
+                    .collect();
+                assert_eq!(found, expected);
+                assert_eq!(reader.position() as usize, tree_data.len());
+            }
+
+            assert!(tree.nearest(0.0, 0.0, 0)?.is_empty());
+            assert_eq!(tree.nearest(0.0, 0.0, 5000)?.len(), nodes.len());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn tree_search_many() -> Result<()> {
+        use rand::distr::{Distribution, Uniform};
+
+        let unif = Uniform::try_from(0.0..1000.0)?;
+        let mut rng = rand::rng();
+
+        let mut nodes = Vec::new();
+        for _ in 0..1000 {
+            let x = unif.sample(&mut rng);
+            let y = unif.sample(&mut rng);
+            nodes.push(NodeItem::bounds(x, y, x + 5.0, y + 5.0));
+        }
+        let extent = calc_extent(&nodes);
+        hilbert_sort(&mut nodes, &extent);
+        for (i, node) in nodes.iter_mut().enumerate() {
+            node.offset = i as u64 * 100;
+        }
+
+        // Adjacent tiles, an overlapping box and a box outside of the extent
+        let mut queries = Vec::new();
+        for x in 0..4 {
+            for y in 0..4 {
+                let (x, y) = (x as f64 * 100.0, y as f64 * 100.0);
+                queries.push(NodeItem::bounds(x, y, x + 100.0, y + 100.0));
+            }
+        }
+        queries.push(NodeItem::bounds(50.0, 50.0, 250.0, 250.0));
+        queries.push(NodeItem::bounds(2000.0, 2000.0, 2100.0, 2100.0));
+
+        for node_size in [2, 16] {
+            let tree = PackedRTree::build(&nodes, &extent, node_size)?;
+            let mut tree_data: Vec<u8> = Vec::new();
+            tree.stream_write(&mut tree_data)?;
+
+            let result = tree.search_many(&queries)?;
+            assert!(result.items.windows(2).all(|w| w[0].index < w[1].index));
+            assert_eq!(result.groups.len(), queries.len());
+            for (query, group) in queries.iter().zip(&result.groups) {
+                let expected = tree
+                    .search(query.min_x, query.min_y, query.max_x, query.max_y)?
+                    .iter()
+                    .map(|item| item.index)
+                    .collect::<Vec<_>>();
+                let found = group
+                    .iter()
+                    .map(|&i| result.items[i].index)
+                    .collect::<Vec<_>>();
+                assert_eq!(found, expected);
+            }
+            assert!(result.groups.last().unwrap().is_empty());
+
+            let mut reader = Cursor::new(&tree_data);
+            let stream_result =
+                PackedRTree::stream_search_many(&mut reader, nodes.len(), node_size, &queries)?;
+            assert_eq!(stream_result.groups, result.groups);
+            assert!(stream_result
+                .items
+                .iter()
+                .zip(&result.items)
+                .all(|(a, b)| a.index == b.index && a.offset == b.offset));
+            assert_eq!(reader.position() as usize, tree_data.len());
+
+            let result = tree.search_many(&[])?;
+            assert!(result.items.is_empty() && result.groups.is_empty());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn tree_stream_build() -> Result<()> {
+        for (num_items, node_size) in [(1, 16), (2, 2), (16, 16), (17, 16), (1000, 4), (4097, 16)] {
+            let nodes = (0..num_items)
+                .map(|i| {
+                    let x = (i % 97) as f64;
+                    let y = (i / 97) as f64;
+                    let mut node = NodeItem::bounds(x, y, x + 0.5, y + 0.5);
+                    node.offset = i as u64 * 100;
+                    node
+                })
+                .collect::<Vec<_>>();
+            let tree = PackedRTree::build(&nodes, &calc_extent(&nodes), node_size)?;
+            let mut expected = Vec::new();
+            tree.stream_write(&mut expected)?;
+
+            let mut index = Vec::new();
+            PackedRTree::stream_build(nodes.into_iter().map(Ok), num_items, node_size, &mut index)?;
+            assert_eq!(index, expected, "{num_items} items, node size {node_size}");
+        }
+
+        // Number of nodes differing from num_items
+        let nodes = || (0..10).map(|i| Ok(NodeItem::bounds(i as f64, 0.0, i as f64, 0.0)));
+        for num_items in [9, 11, 100] {
+            let result = PackedRTree::stream_build(nodes(), num_items, 4, &mut Vec::new());
+            assert!(
+                matches!(&result, Err(Error::IO(e)) if e.kind() == std::io::ErrorKind::InvalidData),
+                "{num_items} items"
+            );
+        }
+        Ok(())
+    }
+
     #[test]
     fn tree_processing() -> Result<()> {
         use geozero::geojson::GeoJsonWriter;
@@ -0,0 +1,322 @@
+use crate::feature_generated::size_prefixed_root_as_feature;
+use crate::file_reader::reader_trait::Seekable;
+use crate::file_reader::{FeatureIter, OwnedFeatureIter};
+use crate::header_generated::Header;
+use crate::properties_reader::OwnedFeature;
+use crate::property_filter::{Expr, PropertyFilter};
+use crate::{FgbReader, Result};
+use rayon::Yield;
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{Read, Seek};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use std::sync::Arc;
+
+/// Default size of the feature bytes decoded in one task
+const DEFAULT_CHUNK_SIZE: usize = 1_048_576; // 1MB
+
+impl<R: Read + Seek> FgbReader<R> {
+    /// Select all features for decoding on the rayon thread pool.
+    pub fn par_select_all(mut self) -> Result<ParFeatureIter<R>> {
+        let verify = std::mem::replace(&mut self.verify, false);
+        Ok(ParFeatureIter::new(self.select_all()?, verify))
+    }
+
+    /// Select features within a bounding box for decoding on the rayon thread pool.
+    pub fn par_select_bbox(
+        mut self,
+        min_x: f64,
+        min_y: f64,
+        max_x: f64,
+        max_y: f64,
+    ) -> Result<ParFeatureIter<R>> {
+        let verify = std::mem::replace(&mut self.verify, false);
+        Ok(ParFeatureIter::new(
+            self.select_bbox(min_x, min_y, max_x, max_y)?,
+            verify,
+        ))
+    }
+}
+
+/// Selected features to be decoded in parallel.
+///
+/// Feature bytes are read sequentially in chunks, which are verified, filtered and mapped on the
+/// rayon thread pool while the next chunks are read.
+///
+/// # Usage example:
+///
+/// ```
+/// use flatgeobuf::*;
+/// use geozero::ToWkt;
+/// # use std::fs::File;
+/// # use std::io::BufReader;
+///
+/// # fn read_fbg() -> std::result::Result<(), Box<dyn std::error::Error>> {
+/// let mut filein = BufReader::new(File::open("countries.fgb")?);
+/// let wkts = FgbReader::open(&mut filein)?
+///     .par_select_all()?
+///     .map(|feature| feature.to_wkt())
+///     .collect::<Result<Vec<_>>>()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ParFeatureIter<R> {
+    iter: FeatureIter<R, Seekable>,
+    /// FlatBuffers verification of the features
+    verify: bool,
+    property_filter: Option<PropertyFilter>,
+    chunk_size: usize,
+    ordered: bool,
+}
+
+impl<R: Read + Seek> ParFeatureIter<R> {
+    fn new(iter: FeatureIter<R, Seekable>, verify: bool) -> Self {
+        ParFeatureIter {
+            iter,
+            verify,
+            property_filter: None,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            ordered: true,
+        }
+    }
+
+    /// Header information
+    pub fn header(&self) -> Header<'_> {
+        self.iter.header()
+    }
+
+    /// Number of selected features (None for undefined feature count)
+    pub fn features_count(&self) -> Option<usize> {
+        match self.property_filter {
+            Some(_) => None,
+            None => self.iter.features_count(),
+        }
+    }
+
+    /// Skip features not matching the property filter expression.
+    ///
+    /// See [`FeatureIter::filter_properties`]. The filter is evaluated on the thread pool.
+    pub fn filter_properties(mut self, expr: Expr) -> Result<Self> {
+        let filter = PropertyFilter::new(expr, self.iter.header())?;
+        self.property_filter = Some(match self.property_filter.take() {
+            Some(existing) => existing.and(filter),
+            None => filter,
+        });
+        Ok(self)
+    }
+
+    /// Decode only the named columns.
+    ///
+    /// See [`FeatureIter::columns`].
+    pub fn columns(mut self, names: &[&str]) -> Result<Self> {
+        self.iter = self.iter.columns(names)?;
+        Ok(self)
+    }
+
+    /// Report columns without value in a feature as JSON `null` values.
+    ///
+    /// See [`FeatureIter::explicit_nulls`].
+    pub fn explicit_nulls(mut self) -> Self {
+        self.iter = self.iter.explicit_nulls();
+        self
+    }
+
+    /// Number of feature bytes decoded in one task, 1MB by default
+    pub fn chunk_size(mut self, bytes: usize) -> Self {
+        self.chunk_size = bytes.max(1);
+        self
+    }
+
+    /// Yield results in order of completion instead of file order.
+    ///
+    /// Features of a chunk stay in file order, but a slow chunk does not hold back the
+    /// results of the following ones.
+    pub fn unordered(mut self) -> Self {
+        self.ordered = false;
+        self
+    }
+
+    /// Apply `f` to each selected feature on the rayon thread pool.
+    ///
+    /// The returned iterator yields the results in file order, unless [`Self::unordered`] was
+    /// set. Reading stops after the first read error. A panic in `f` is resumed on the thread
+    /// iterating the results. Iterating on a worker thread of the pool, e.g. within
+    /// `ThreadPool::install`, executes pending tasks while waiting for results.
+    pub fn map<T, F>(self, f: F) -> ParMap<R, T>
+    where
+        T: Send + 'static,
+        F: Fn(&OwnedFeature) -> T + Send + Sync + 'static,
+    {
+        let verify = self.verify;
+        let property_filter = self.property_filter;
+        let task = move |feature: &OwnedFeature| -> Option<Result<T>> {
+            if verify {
+                if let Err(e) = size_prefixed_root_as_feature(&feature.feature_buf) {
+                    return Some(Err(e.into()));
+                }
+            }
+            if let Some(filter) = &property_filter {
+                match filter.matches(feature.header(), feature.fbs_feature()) {
+                    Ok(true) => {}
+                    Ok(false) => return None,
+                    Err(e) => return Some(Err(e)),
+                }
+            }
+            Some(Ok(f(feature)))
+        };
+        let (sender, receiver) = channel();
+        ParMap {
+            features: self.iter.into_iter_owned(),
+            task: Arc::new(task),
+            chunk_size: self.chunk_size,
+            ordered: self.ordered,
+            max_chunks: rayon::current_num_threads() * 2,
+            sender,
+            receiver,
+            reading_done: false,
+            dispatched: 0,
+            received: 0,
+            emitted: 0,
+            pending: BTreeMap::new(),
+            ready: VecDeque::new(),
+        }
+    }
+}
+
+type Task<T> = Arc<dyn Fn(&OwnedFeature) -> Option<Result<T>> + Send + Sync>;
+
+/// Results of a chunk, or the panic of its task
+type ChunkResult<T> = std::thread::Result<Vec<Result<T>>>;
+
+/// Iterator over the results of [`ParFeatureIter::map`]
+pub struct ParMap<R, T> {
+    features: OwnedFeatureIter<R, Seekable>,
+    task: Task<T>,
+    chunk_size: usize,
+    ordered: bool,
+    /// Maximal number of chunks read and not yet yielded
+    max_chunks: usize,
+    sender: Sender<(usize, ChunkResult<T>)>,
+    receiver: Receiver<(usize, ChunkResult<T>)>,
+    reading_done: bool,
+    /// Number of chunks read
+    dispatched: usize,
+    /// Number of chunks completed
+    received: usize,
+    /// Number of chunks yielded
+    emitted: usize,
+    /// Completed chunks by sequence number, waiting for the preceding ones
+    pending: BTreeMap<usize, Vec<Result<T>>>,
+    /// Results of the yielded chunks
+    ready: VecDeque<Result<T>>,
+}
+
+impl<R: Read + Seek, T: Send + 'static> ParMap<R, T> {
+    /// Read the next chunk of features, empty at the end of the selection
+    fn read_chunk(&mut self) -> Result<Vec<OwnedFeature>> {
+        let mut chunk = Vec::new();
+        let mut size = 0;
+        while size < self.chunk_size {
+            let Some(feature) = self.features.next().transpose()? else {
+                break;
+            };
+            size += feature.feature_buf.len();
+            chunk.push(feature);
+        }
+        Ok(chunk)
+    }
+
+    /// Read and spawn chunks until the maximal number of chunks is in progress
+    fn dispatch(&mut self) {
+        while !self.reading_done && self.in_progress() < self.max_chunks {
+            let seq = self.dispatched;
+            match self.read_chunk() {
+                Ok(chunk) if chunk.is_empty() => {
+                    self.reading_done = true;
+                    return;
+                }
+                Ok(chunk) => {
+                    let task = self.task.clone();
+                    let sender = self.sender.clone();
+                    rayon::spawn(move || {
+                        // The panic is resumed by the iterator, which does not reuse the task
+                        let results = panic::catch_unwind(AssertUnwindSafe(|| {
+                            chunk.iter().filter_map(|feature| task(feature)).collect()
+                        }));
+                        // The receiver is gone if the iterator was dropped
+                        let _ = sender.send((seq, results));
+                    });
+                }
+                Err(e) => {
+                    self.reading_done = true;
+                    let _ = self.sender.send((seq, Ok(vec![Err(e)])));
+                }
+            }
+            self.dispatched += 1;
+        }
+    }
+
+    /// Wait for the next completed chunk.
+    ///
+    /// On a worker thread of the pool, pending tasks are executed while waiting, since
+    /// blocking the thread could prevent the chunks from being decoded, e.g. in a pool with
+    /// a single thread.
+    fn receive(&self) -> (usize, ChunkResult<T>) {
+        if rayon::current_thread_index().is_none() {
+            return self
+                .receiver
+                .recv()
+                .expect("sender is owned by the iterator");
+        }
+        loop {
+            match self.receiver.try_recv() {
+                Ok(message) => return message,
+                Err(TryRecvError::Empty) => {
+                    if rayon::yield_now() != Some(Yield::Executed) {
+                        // Tasks are running on other threads
+                        std::thread::yield_now();
+                    }
+                }
+                Err(TryRecvError::Disconnected) => unreachable!("sender is owned by the iterator"),
+            }
+        }
+    }
+
+    fn in_progress(&self) -> usize {
+        if self.ordered {
+            self.dispatched - self.emitted
+        } else {
+            self.dispatched - self.received
+        }
+    }
+}
+
+impl<R: Read + Seek, T: Send + 'static> Iterator for ParMap<R, T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(result) = self.ready.pop_front() {
+                return Some(result);
+            }
+            if let Some(results) = self.pending.remove(&self.emitted) {
+                self.emitted += 1;
+                self.ready.extend(results);
+                continue;
+            }
+            self.dispatch();
+            if self.received == self.dispatched {
+                return None;
+            }
+            let (seq, results) = self.receive();
+            self.received += 1;
+            let results = results.unwrap_or_else(|panic| panic::resume_unwind(panic));
+            if self.ordered {
+                self.pending.insert(seq, results);
+            } else {
+                self.emitted += 1;
+                self.ready.extend(results);
+            }
+        }
+    }
+}
@@ -0,0 +1,278 @@
+//! Serde deserialization of feature properties.
+
+use crate::error::{Error, Result};
+use crate::feature_generated::Feature;
+use crate::header_generated::Header;
+use crate::properties_reader::{column_values, Projection};
+use crate::{FgbFeature, FgbFeatureRef, OwnedFeature};
+use geozero::ColumnValue;
+use serde::de::{self, DeserializeSeed, MapAccess, SeqAccess, Visitor};
+use serde::{forward_to_deserialize_any, Deserialize};
+
+impl FgbFeature {
+    /// Deserialize the feature properties into `T`.
+    ///
+    /// Properties are decoded directly from the feature buffer and strings and binary values
+    /// can be borrowed from it. Columns without value in this feature are deserialized as
+    /// `None`. Only the columns selected with
+    /// [`FeatureIter::columns`](crate::FeatureIter::columns) are visible.
+    ///
+    /// # Usage example:
+    ///
+    /// ```
+    /// use flatgeobuf::*;
+    /// use serde::Deserialize;
+    /// # use std::fs::File;
+    /// # use std::io::BufReader;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Country<'a> {
+    ///     id: &'a str,
+    ///     name: Option<String>,
+    /// }
+    ///
+    /// # fn read_fbg() -> std::result::Result<(), Box<dyn std::error::Error>> {
+    /// let mut filein = BufReader::new(File::open("countries.fgb")?);
+    /// let mut fgb = FgbReader::open(&mut filein)?.select_all()?;
+    /// while let Some(feature) = fgb.next()? {
+    ///     let country: Country = feature.deserialize()?;
+    ///     println!("{}", country.id);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn deserialize<'de, T: Deserialize<'de>>(&'de self) -> Result<T> {
+        T::deserialize(PropertiesDeserializer::new(
+            self.header(),
+            self.fbs_feature(),
+            self.projection.as_ref(),
+        )?)
+    }
+}
+
+impl<'a> FgbFeatureRef<'a> {
+    /// Deserialize the feature properties into `T`.
+    ///
+    /// See [`FgbFeature::deserialize`].
+    pub fn deserialize<T: Deserialize<'a>>(&self) -> Result<T> {
+        T::deserialize(PropertiesDeserializer::new(
+            self.header,
+            self.feature,
+            None,
+        )?)
+    }
+}
+
+impl OwnedFeature {
+    /// Deserialize the feature properties into `T`.
+    ///
+    /// See [`FgbFeature::deserialize`].
+    pub fn deserialize<'de, T: Deserialize<'de>>(&'de self) -> Result<T> {
+        T::deserialize(PropertiesDeserializer::new(
+            self.header(),
+            self.fbs_feature(),
+            self.context.projection.as_ref(),
+        )?)
+    }
+}
+
+/// Deserializer of the properties as map of column names to optional values
+struct PropertiesDeserializer<'de> {
+    entries: Vec<(&'de str, Option<ColumnValue<'de>>)>,
+}
+
+impl<'de> PropertiesDeserializer<'de> {
+    fn new(
+        header: Header<'de>,
+        feature: Feature<'de>,
+        projection: Option<&'de Projection>,
+    ) -> Result<Self> {
+        let entries =
+            column_values(header, feature, projection).map_err(<Error as de::Error>::custom)?;
+        Ok(PropertiesDeserializer { entries })
+    }
+}
+
+impl<'de> de::Deserializer<'de> for PropertiesDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_map(Entries {
+            entries: self.entries.into_iter(),
+            value: None,
+        })
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(Entries {
+            entries: self.entries.into_iter(),
+            value: None,
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct map struct enum identifier ignored_any
+    }
+}
+
+/// Property entries as map or as sequence of values
+struct Entries<'de, I> {
+    entries: I,
+    /// Value of the last key in map access
+    value: Option<Option<ColumnValue<'de>>>,
+}
+
+impl<'de, I> MapAccess<'de> for Entries<'de, I>
+where
+    I: Iterator<Item = (&'de str, Option<ColumnValue<'de>>)>,
+{
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        match self.entries.next() {
+            Some((name, value)) => {
+                self.value = Some(value);
+                seed.deserialize(de::value::BorrowedStrDeserializer::new(name))
+                    .map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let value = self
+            .value
+            .take()
+            .ok_or_else(|| <Error as de::Error>::custom("value requested before key"))?;
+        seed.deserialize(ValueDeserializer(value))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.entries.size_hint().1
+    }
+}
+
+impl<'de, I> SeqAccess<'de> for Entries<'de, I>
+where
+    I: Iterator<Item = (&'de str, Option<ColumnValue<'de>>)>,
+{
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        match self.entries.next() {
+            Some((_, value)) => seed.deserialize(ValueDeserializer(value)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.entries.size_hint().1
+    }
+}
+
+/// Deserializer of a property value, `None` for missing values
+struct ValueDeserializer<'de>(Option<ColumnValue<'de>>);
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let Some(value) = self.0 else {
+            return visitor.visit_none();
+        };
+        match value {
+            ColumnValue::Byte(v) => visitor.visit_i8(v),
+            ColumnValue::UByte(v) => visitor.visit_u8(v),
+            ColumnValue::Bool(v) => visitor.visit_bool(v),
+            ColumnValue::Short(v) => visitor.visit_i16(v),
+            ColumnValue::UShort(v) => visitor.visit_u16(v),
+            ColumnValue::Int(v) => visitor.visit_i32(v),
+            ColumnValue::UInt(v) => visitor.visit_u32(v),
+            ColumnValue::Long(v) => visitor.visit_i64(v),
+            ColumnValue::ULong(v) => visitor.visit_u64(v),
+            ColumnValue::Float(v) => visitor.visit_f32(v),
+            ColumnValue::Double(v) => visitor.visit_f64(v),
+            ColumnValue::String(v) | ColumnValue::Json(v) | ColumnValue::DateTime(v) => {
+                visitor.visit_borrowed_str(v)
+            }
+            ColumnValue::Binary(v) => visitor.visit_borrowed_bytes(v),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.0 {
+            Some(_) => visitor.visit_some(self),
+            None => visitor.visit_none(),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    // Binary values as byte sequences like `Vec<u8>` or `[u8; N]`
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.0 {
+            Some(ColumnValue::Binary(v)) => {
+                visitor.visit_seq(de::value::SeqDeserializer::new(v.iter().copied()))
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        // Unit variants from strings
+        match self.0 {
+            Some(ColumnValue::String(v)) => {
+                de::value::BorrowedStrDeserializer::new(v).deserialize_enum(name, variants, visitor)
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Error::Serde(msg.to_string())
+    }
+}
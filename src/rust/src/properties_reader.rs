@@ -3,14 +3,20 @@ use crate::header_generated::*;
 use byteorder::{ByteOrder, LittleEndian};
 use geozero::error::{GeozeroError, Result};
 use geozero::GeozeroGeometry;
-use geozero::{ColumnValue, GeomProcessor, PropertyProcessor};
+use geozero::{ColumnValue, GeomProcessor, PropertyProcessor, PropertyReadType};
+use std::borrow::Cow;
 use std::mem::size_of;
 use std::str;
+use std::sync::Arc;
 
 /// Access to the *current* feature during iteration.
 pub struct FgbFeature {
     pub(crate) header_buf: Vec<u8>, // Using type Header<'a> instead of Vec would require adding a lifetime to FgbFeature
     pub(crate) feature_buf: Vec<u8>,
+    /// Columns selected with `columns()`, all columns if `None`
+    pub(crate) projection: Option<Projection>,
+    /// Report columns without value as JSON `null`, set with `explicit_nulls()`
+    pub(crate) explicit_nulls: bool,
 }
 
 impl FgbFeature {
@@ -28,12 +34,11 @@ impl FgbFeature {
         self.fbs_feature().geometry()
     }
 
-    fn dimension(&self) -> geo_traits::Dimensions {
-        match (self.header().has_z(), self.header().has_m()) {
-            (true, true) => geo_traits::Dimensions::Xyzm,
-            (true, false) => geo_traits::Dimensions::Xyz,
-            (false, true) => geo_traits::Dimensions::Xym,
-            (false, false) => geo_traits::Dimensions::Xy,
+    /// Borrowed view of the current feature.
+    pub fn as_feature_ref(&self) -> FgbFeatureRef<'_> {
+        FgbFeatureRef {
+            header: self.header(),
+            feature: self.fbs_feature(),
         }
     }
 
@@ -52,43 +57,174 @@ impl FgbFeature {
         &self,
     ) -> std::result::Result<Option<impl geo_traits::GeometryTrait<T = f64> + use<'_>>, crate::Error>
     {
-        if let Some(geom) = self.geometry() {
-            let dim = self.dimension();
-            let result = match self.header().geometry_type() {
-                GeometryType::Point => crate::geo_trait_impl::Geometry::Point(
-                    crate::geo_trait_impl::Point::new(geom, dim),
-                ),
-                GeometryType::LineString => crate::geo_trait_impl::Geometry::LineString(
-                    crate::geo_trait_impl::LineString::new(geom, dim),
-                ),
-                GeometryType::Polygon => crate::geo_trait_impl::Geometry::Polygon(
-                    crate::geo_trait_impl::Polygon::new(geom, dim),
-                ),
-                GeometryType::MultiPoint => crate::geo_trait_impl::Geometry::MultiPoint(
-                    crate::geo_trait_impl::MultiPoint::new(geom, dim),
-                ),
-                GeometryType::MultiLineString => crate::geo_trait_impl::Geometry::MultiLineString(
-                    crate::geo_trait_impl::MultiLineString::new(geom, dim),
-                ),
-                GeometryType::MultiPolygon => crate::geo_trait_impl::Geometry::MultiPolygon(
-                    crate::geo_trait_impl::MultiPolygon::new(geom, dim),
-                ),
-                GeometryType::Unknown => crate::geo_trait_impl::Geometry::new(geom, dim),
-                GeometryType::GeometryCollection => {
-                    crate::geo_trait_impl::Geometry::GeometryCollection(
-                        crate::geo_trait_impl::GeometryCollection::new(geom, dim),
-                    )
-                }
-                geom_type => {
-                    return Err(crate::Error::UnsupportedGeometryType(format!(
-                        "Unsupported geometry type in geo-traits: {geom_type:?}",
-                    )))
-                }
-            };
-            Ok(Some(result))
-        } else {
-            Ok(None)
-        }
+        geometry_trait(self.header(), self.fbs_feature())
+    }
+
+    /// Get property value by name, `None` if the column has no value in this feature.
+    ///
+    /// Unlike [`FeatureProperties::property`](geozero::FeatureProperties::property), null values
+    /// are distinct from unknown columns, which fail with [`crate::Error::UnknownColumn`]. Only
+    /// the columns selected with [`FeatureIter::columns`](crate::FeatureIter::columns) are
+    /// known.
+    ///
+    /// Features with their own columns, e.g. written with
+    /// [`FgbWriterOptions::feature_columns`](crate::FgbWriterOptions::feature_columns), are read
+    /// with these instead of the dataset columns. Columns missing in such a feature have no
+    /// value.
+    ///
+    /// # Usage example:
+    ///
+    /// ```
+    /// use flatgeobuf::*;
+    /// # use std::fs::File;
+    /// # use std::io::BufReader;
+    ///
+    /// # fn read_fbg() -> std::result::Result<(), Box<dyn std::error::Error>> {
+    /// let mut filein = BufReader::new(File::open("countries.fgb")?);
+    /// let mut fgb = FgbReader::open(&mut filein)?.select_all()?;
+    /// while let Some(feature) = fgb.next()? {
+    ///     let name: Option<String> = feature.property_opt("name")?;
+    ///     println!("{}", name.as_deref().unwrap_or("<unnamed>"));
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn property_opt<T: PropertyReadType>(&self, name: &str) -> crate::Result<Option<T>> {
+        property_opt(
+            self.header(),
+            self.fbs_feature(),
+            self.projection.as_ref(),
+            name,
+        )
+    }
+
+    /// Process all or the selected columns in column order, including columns without value.
+    ///
+    /// Values are passed to [`PropertyProcessor::property`], columns without value to
+    /// [`NullPropertyProcessor::null_property`] with their column type. In contrast,
+    /// [`FeatureProperties::process_properties`](geozero::FeatureProperties::process_properties)
+    /// only processes the columns with a value.
+    pub fn process_properties_with_nulls<P: NullPropertyProcessor>(
+        &self,
+        processor: &mut P,
+    ) -> Result<bool> {
+        process_properties_with_nulls(
+            self.header(),
+            self.fbs_feature(),
+            self.projection.as_ref(),
+            processor,
+        )
+    }
+}
+
+/// Feature property processing including columns without value.
+///
+/// See [`FgbFeature::process_properties_with_nulls`].
+pub trait NullPropertyProcessor: PropertyProcessor {
+    /// Process a column without value in the feature. Abort processing, if return value is true.
+    ///
+    /// `column_type` is the type of the column, `None` for a selected column which is neither
+    /// declared in the header nor in the feature.
+    fn null_property(
+        &mut self,
+        idx: usize,
+        name: &str,
+        column_type: Option<ColumnType>,
+    ) -> Result<bool>;
+}
+
+/// Borrowed access to a feature whose bytes live elsewhere, e.g. in a memory-mapped file.
+///
+/// Unlike [`FgbFeature`], no feature bytes are copied: the FlatBuffers tables point directly
+/// into the buffer the feature was read from.
+#[derive(Clone, Copy)]
+pub struct FgbFeatureRef<'a> {
+    pub(crate) header: Header<'a>,
+    pub(crate) feature: Feature<'a>,
+}
+
+impl<'a> FgbFeatureRef<'a> {
+    /// Flatbuffers header access
+    pub fn header(&self) -> Header<'a> {
+        self.header
+    }
+    /// Flatbuffers feature access
+    pub fn fbs_feature(&self) -> Feature<'a> {
+        self.feature
+    }
+    /// Flatbuffers geometry access
+    pub fn geometry(&self) -> Option<Geometry<'a>> {
+        self.feature.geometry()
+    }
+    /// Access the underlying geometry, returning an object that implements
+    /// [`geo_traits::GeometryTrait`].
+    ///
+    /// See [`FgbFeature::geometry_trait`]. The returned geometry borrows from the underlying
+    /// buffer and may outlive this `FgbFeatureRef`.
+    pub fn geometry_trait(
+        &self,
+    ) -> std::result::Result<Option<impl geo_traits::GeometryTrait<T = f64> + use<'a>>, crate::Error>
+    {
+        geometry_trait(self.header, self.feature)
+    }
+
+    /// Get property value by name, `None` if the column has no value in this feature.
+    ///
+    /// See [`FgbFeature::property_opt`].
+    pub fn property_opt<T: PropertyReadType>(&self, name: &str) -> crate::Result<Option<T>> {
+        property_opt(self.header, self.feature, None, name)
+    }
+}
+
+fn dimension(header: Header) -> geo_traits::Dimensions {
+    match (header.has_z(), header.has_m()) {
+        (true, true) => geo_traits::Dimensions::Xyzm,
+        (true, false) => geo_traits::Dimensions::Xyz,
+        (false, true) => geo_traits::Dimensions::Xym,
+        (false, false) => geo_traits::Dimensions::Xy,
+    }
+}
+
+fn geometry_trait<'a>(
+    header: Header<'a>,
+    feature: Feature<'a>,
+) -> std::result::Result<Option<crate::geo_trait_impl::Geometry<'a>>, crate::Error> {
+    if let Some(geom) = feature.geometry() {
+        let dim = dimension(header);
+        let result = match header.geometry_type() {
+            GeometryType::Point => {
+                crate::geo_trait_impl::Geometry::Point(crate::geo_trait_impl::Point::new(geom, dim))
+            }
+            GeometryType::LineString => crate::geo_trait_impl::Geometry::LineString(
+                crate::geo_trait_impl::LineString::new(geom, dim),
+            ),
+            GeometryType::Polygon => crate::geo_trait_impl::Geometry::Polygon(
+                crate::geo_trait_impl::Polygon::new(geom, dim),
+            ),
+            GeometryType::MultiPoint => crate::geo_trait_impl::Geometry::MultiPoint(
+                crate::geo_trait_impl::MultiPoint::new(geom, dim),
+            ),
+            GeometryType::MultiLineString => crate::geo_trait_impl::Geometry::MultiLineString(
+                crate::geo_trait_impl::MultiLineString::new(geom, dim),
+            ),
+            GeometryType::MultiPolygon => crate::geo_trait_impl::Geometry::MultiPolygon(
+                crate::geo_trait_impl::MultiPolygon::new(geom, dim),
+            ),
+            GeometryType::Unknown => crate::geo_trait_impl::Geometry::new(geom, dim),
+            GeometryType::GeometryCollection => {
+                crate::geo_trait_impl::Geometry::GeometryCollection(
+                    crate::geo_trait_impl::GeometryCollection::new(geom, dim),
+                )
+            }
+            geom_type => {
+                return Err(crate::Error::UnsupportedGeometryType(format!(
+                    "Unsupported geometry type in geo-traits: {geom_type:?}",
+                )))
+            }
+        };
+        Ok(Some(result))
+    } else {
+        Ok(None)
     }
 }
 
@@ -96,192 +232,562 @@ impl geozero::FeatureAccess for FgbFeature {}
 
 impl GeozeroGeometry for FgbFeature {
     fn process_geom<P: GeomProcessor>(&self, processor: &mut P) -> Result<()> {
-        let geometry = self
-            .fbs_feature()
-            .geometry()
-            .ok_or(GeozeroError::GeometryFormat)?;
-        let geometry_type = self.header().geometry_type();
-        geometry.process(processor, geometry_type)
+        process_geom(self.header(), self.fbs_feature(), processor)
     }
 }
 
 impl geozero::FeatureProperties for FgbFeature {
     /// Process feature properties.
     fn process_properties<P: PropertyProcessor>(&self, reader: &mut P) -> Result<bool> {
-        if self.header().columns().is_none() {
-            return Ok(false);
+        process_selected_properties(
+            self.header(),
+            self.fbs_feature(),
+            self.projection.as_ref(),
+            self.explicit_nulls,
+            reader,
+        )
+    }
+}
+
+/// Dataset information shared by the owned features of a selection
+pub(crate) struct FeatureContext {
+    header_buf: Vec<u8>,
+    pub(crate) projection: Option<Projection>,
+    explicit_nulls: bool,
+}
+
+impl FeatureContext {
+    pub(crate) fn new(fbs: &FgbFeature) -> Arc<Self> {
+        Arc::new(FeatureContext {
+            header_buf: fbs.header_buf.clone(),
+            projection: fbs.projection.clone(),
+            explicit_nulls: fbs.explicit_nulls,
+        })
+    }
+}
+
+/// Feature owning its bytes, independent of the reader it was read from.
+///
+/// Unlike [`FgbFeature`], which is reused for each feature of a selection, owned features can
+/// be collected, sent to other threads or held across `.await` points. The header is shared
+/// with the other features of the selection, so only the feature bytes are owned.
+#[derive(Clone)]
+pub struct OwnedFeature {
+    pub(crate) context: Arc<FeatureContext>,
+    pub(crate) feature_buf: Vec<u8>,
+}
+
+impl OwnedFeature {
+    /// Take the current feature bytes out of `fbs`, which must be read again before use.
+    pub(crate) fn take(fbs: &mut FgbFeature, context: &Arc<FeatureContext>) -> Self {
+        OwnedFeature {
+            context: context.clone(),
+            feature_buf: std::mem::take(&mut fbs.feature_buf),
         }
+    }
+    /// Flatbuffers header access
+    pub fn header(&self) -> Header<'_> {
+        // SAFETY: verification is done before creating instance
+        unsafe { size_prefixed_root_as_header_unchecked(&self.context.header_buf) }
+    }
+    /// Flatbuffers feature access
+    pub fn fbs_feature(&self) -> Feature<'_> {
+        // SAFETY: verification is done before creating instance
+        unsafe { size_prefixed_root_as_feature_unchecked(&self.feature_buf) }
+    }
+    /// Flatbuffers geometry access
+    pub fn geometry(&self) -> Option<Geometry<'_>> {
+        self.fbs_feature().geometry()
+    }
+    /// Borrowed view of the feature.
+    pub fn as_feature_ref(&self) -> FgbFeatureRef<'_> {
+        FgbFeatureRef {
+            header: self.header(),
+            feature: self.fbs_feature(),
+        }
+    }
+    /// Access the underlying geometry, returning an object that implements
+    /// [`geo_traits::GeometryTrait`].
+    ///
+    /// See [`FgbFeature::geometry_trait`].
+    pub fn geometry_trait(
+        &self,
+    ) -> std::result::Result<Option<impl geo_traits::GeometryTrait<T = f64> + use<'_>>, crate::Error>
+    {
+        geometry_trait(self.header(), self.fbs_feature())
+    }
+    /// Get property value by name, `None` if the column has no value in this feature.
+    ///
+    /// See [`FgbFeature::property_opt`].
+    pub fn property_opt<T: PropertyReadType>(&self, name: &str) -> crate::Result<Option<T>> {
+        property_opt(
+            self.header(),
+            self.fbs_feature(),
+            self.context.projection.as_ref(),
+            name,
+        )
+    }
+    /// Process all or the selected columns in column order, including columns without value.
+    ///
+    /// See [`FgbFeature::process_properties_with_nulls`].
+    pub fn process_properties_with_nulls<P: NullPropertyProcessor>(
+        &self,
+        processor: &mut P,
+    ) -> Result<bool> {
+        process_properties_with_nulls(
+            self.header(),
+            self.fbs_feature(),
+            self.context.projection.as_ref(),
+            processor,
+        )
+    }
+}
 
-        let columns_meta = self
-            .header()
-            .columns()
-            .ok_or(GeozeroError::GeometryFormat)?;
-        let mut finish = false;
-        if let Some(properties) = self.fbs_feature().properties() {
-            let mut offset = 0;
-            let bytes = properties.bytes();
-            while offset + 1 < properties.len() && !finish {
-                // NOTE: it should be offset < properties.len(), but there is data with a
-                // trailing byte in the last column of type Binary
-                let column_idx = LittleEndian::read_u16(&bytes[offset..offset + 2]) as usize;
-                offset += size_of::<u16>();
-                if column_idx >= columns_meta.len() {
-                    // NOTE: reading also fails if column._type is different from effective entry
-                    return Err(GeozeroError::GeometryFormat);
-                }
-                let column = &columns_meta.get(column_idx);
-                match column.type_() {
-                    ColumnType::Int => {
-                        finish = reader.property(
-                            column_idx,
-                            column.name(),
-                            &ColumnValue::Int(LittleEndian::read_i32(&bytes[offset..offset + 4])),
-                        )?;
-                        offset += size_of::<i32>();
-                    }
-                    ColumnType::Long => {
-                        finish = reader.property(
-                            column_idx,
-                            column.name(),
-                            &ColumnValue::Long(LittleEndian::read_i64(&bytes[offset..offset + 8])),
-                        )?;
-                        offset += size_of::<i64>();
-                    }
-                    ColumnType::ULong => {
-                        finish = reader.property(
-                            column_idx,
-                            column.name(),
-                            &ColumnValue::ULong(LittleEndian::read_u64(&bytes[offset..offset + 8])),
-                        )?;
-                        offset += size_of::<u64>();
-                    }
-                    ColumnType::Double => {
-                        finish = reader.property(
-                            column_idx,
-                            column.name(),
-                            &ColumnValue::Double(LittleEndian::read_f64(
-                                &bytes[offset..offset + 8],
-                            )),
-                        )?;
-                        offset += size_of::<f64>();
-                    }
-                    ColumnType::String => {
-                        let len = LittleEndian::read_u32(&bytes[offset..offset + 4]) as usize;
-                        offset += size_of::<u32>();
-                        finish = reader.property(
-                            column_idx,
-                            column.name(),
-                            &ColumnValue::String(
-                                // unsafe variant without UTF-8 checking would be faster...
-                                str::from_utf8(&bytes[offset..offset + len]).map_err(|_| {
-                                    GeozeroError::Property("Invalid UTF-8 encoding".to_string())
-                                })?,
-                            ),
-                        )?;
-                        offset += len;
-                    }
-                    ColumnType::Byte => {
-                        finish = reader.property(
-                            column_idx,
-                            column.name(),
-                            &ColumnValue::Byte(bytes[offset] as i8),
-                        )?;
-                        offset += size_of::<i8>();
-                    }
-                    ColumnType::UByte => {
-                        finish = reader.property(
-                            column_idx,
-                            column.name(),
-                            &ColumnValue::UByte(bytes[offset]),
-                        )?;
-                        offset += size_of::<u8>();
-                    }
-                    ColumnType::Bool => {
-                        finish = reader.property(
-                            column_idx,
-                            column.name(),
-                            &ColumnValue::Bool(bytes[offset] != 0),
-                        )?;
-                        offset += size_of::<u8>();
-                    }
-                    ColumnType::Short => {
-                        finish = reader.property(
-                            column_idx,
-                            column.name(),
-                            &ColumnValue::Short(LittleEndian::read_i16(&bytes[offset..offset + 2])),
-                        )?;
-                        offset += size_of::<i16>();
-                    }
-                    ColumnType::UShort => {
-                        finish = reader.property(
-                            column_idx,
-                            column.name(),
-                            &ColumnValue::UShort(LittleEndian::read_u16(
-                                &bytes[offset..offset + 2],
-                            )),
-                        )?;
-                        offset += size_of::<u16>();
-                    }
-                    ColumnType::UInt => {
-                        finish = reader.property(
-                            column_idx,
-                            column.name(),
-                            &ColumnValue::UInt(LittleEndian::read_u32(&bytes[offset..offset + 4])),
-                        )?;
-                        offset += size_of::<u32>();
-                    }
-                    ColumnType::Float => {
-                        finish = reader.property(
-                            column_idx,
-                            column.name(),
-                            &ColumnValue::Float(LittleEndian::read_f32(&bytes[offset..offset + 4])),
-                        )?;
-                        offset += size_of::<f32>();
-                    }
-                    ColumnType::Json => {
-                        let len = LittleEndian::read_u32(&bytes[offset..offset + 4]) as usize;
-                        offset += size_of::<u32>();
-                        finish = reader.property(
-                            column_idx,
-                            column.name(),
-                            &ColumnValue::Json(
-                                // JSON may be represented using UTF-8, UTF-16, or UTF-32. The default encoding is UTF-8.
-                                str::from_utf8(&bytes[offset..offset + len]).map_err(|_| {
-                                    GeozeroError::Property("Invalid UTF-8 encoding".to_string())
-                                })?,
-                            ),
-                        )?;
-                        offset += len;
-                    }
-                    ColumnType::DateTime => {
-                        let len = LittleEndian::read_u32(&bytes[offset..offset + 4]) as usize;
-                        offset += size_of::<u32>();
-                        finish = reader.property(
-                            column_idx,
-                            column.name(),
-                            &ColumnValue::DateTime(
-                                // unsafe variant without UTF-8 checking would be faster...
-                                str::from_utf8(&bytes[offset..offset + len]).map_err(|_| {
-                                    GeozeroError::Property("Invalid UTF-8 encoding".to_string())
-                                })?,
-                            ),
-                        )?;
-                        offset += len;
-                    }
-                    ColumnType::Binary => {
-                        let len = LittleEndian::read_u32(&bytes[offset..offset + 4]) as usize;
-                        offset += size_of::<u32>();
-                        finish = reader.property(
-                            column_idx,
-                            column.name(),
-                            &ColumnValue::Binary(&bytes[offset..offset + len]),
-                        )?;
-                        offset += len;
+impl geozero::FeatureAccess for OwnedFeature {}
+
+/// Features selected with several bounding boxes
+///
+/// Each feature is read once, also when it matches more than one bounding box. Returned by
+/// [`FgbReader::select_bbox_many`](crate::FgbReader::select_bbox_many).
+pub struct FeatureGroups {
+    features: Vec<OwnedFeature>,
+    groups: Vec<Vec<usize>>,
+}
+
+impl FeatureGroups {
+    pub(crate) fn new(features: Vec<OwnedFeature>, groups: Vec<Vec<usize>>) -> Self {
+        FeatureGroups { features, groups }
+    }
+    /// Features matching any of the bounding boxes, in file order
+    pub fn features(&self) -> &[OwnedFeature] {
+        &self.features
+    }
+    /// Number of bounding boxes
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+    /// Whether the selection was made without bounding boxes
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+    /// Positions in [`features`](Self::features) of the features matching bounding box `n`
+    ///
+    /// Panics if `n` is not below the number of bounding boxes.
+    pub fn group_indices(&self, n: usize) -> &[usize] {
+        &self.groups[n]
+    }
+    /// Features matching bounding box `n`, in file order
+    ///
+    /// Panics if `n` is not below the number of bounding boxes.
+    pub fn group(&self, n: usize) -> impl ExactSizeIterator<Item = &OwnedFeature> {
+        self.groups[n].iter().map(|&i| &self.features[i])
+    }
+}
+
+impl GeozeroGeometry for OwnedFeature {
+    fn process_geom<P: GeomProcessor>(&self, processor: &mut P) -> Result<()> {
+        process_geom(self.header(), self.fbs_feature(), processor)
+    }
+}
+
+impl geozero::FeatureProperties for OwnedFeature {
+    /// Process feature properties.
+    fn process_properties<P: PropertyProcessor>(&self, reader: &mut P) -> Result<bool> {
+        process_selected_properties(
+            self.header(),
+            self.fbs_feature(),
+            self.context.projection.as_ref(),
+            self.context.explicit_nulls,
+            reader,
+        )
+    }
+}
+
+impl geozero::FeatureAccess for FgbFeatureRef<'_> {}
+
+impl GeozeroGeometry for FgbFeatureRef<'_> {
+    fn process_geom<P: GeomProcessor>(&self, processor: &mut P) -> Result<()> {
+        process_geom(self.header, self.feature, processor)
+    }
+}
+
+impl geozero::FeatureProperties for FgbFeatureRef<'_> {
+    /// Process feature properties.
+    fn process_properties<P: PropertyProcessor>(&self, reader: &mut P) -> Result<bool> {
+        process_properties(self.header, self.feature, reader)
+    }
+}
+
+fn process_geom<P: GeomProcessor>(
+    header: Header,
+    feature: Feature,
+    processor: &mut P,
+) -> Result<()> {
+    let geometry = feature.geometry().ok_or(GeozeroError::GeometryFormat)?;
+    geometry.process(processor, header.geometry_type())
+}
+
+/// Process the properties selected by `projection` and `explicit_nulls`
+fn process_selected_properties<P: PropertyProcessor>(
+    header: Header,
+    feature: Feature,
+    projection: Option<&Projection>,
+    explicit_nulls: bool,
+    reader: &mut P,
+) -> Result<bool> {
+    if explicit_nulls {
+        return process_properties_with_nulls(header, feature, projection, &mut JsonNulls(reader));
+    }
+    match projection {
+        Some(projection) => process_projected_properties(header, feature, projection, reader),
+        None => process_properties(header, feature, reader),
+    }
+}
+
+fn process_properties<P: PropertyProcessor>(
+    header: Header,
+    feature: Feature,
+    reader: &mut P,
+) -> Result<bool> {
+    let Some(properties) = RawProperties::new(header, feature) else {
+        return Ok(false);
+    };
+    for property in properties {
+        let (column_idx, column, value) = property?;
+        if reader.property(column_idx, column.name(), &value)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn process_projected_properties<P: PropertyProcessor>(
+    header: Header,
+    feature: Feature,
+    projection: &Projection,
+    reader: &mut P,
+) -> Result<bool> {
+    let Some(mut properties) = RawProperties::new(header, feature) else {
+        return Ok(false);
+    };
+    let positions = projection.positions(feature);
+    let mut remaining = projection.len();
+    while remaining > 0 {
+        let Some(entry) = properties.next_column() else {
+            break;
+        };
+        let (column_idx, column) = entry?;
+        match positions[column_idx] {
+            Some(idx) => {
+                remaining -= 1;
+                if let Some(value) = properties.read_value(column.type_())? {
+                    if reader.property(idx, column.name(), &value)? {
+                        return Ok(true);
                     }
-                    ColumnType(_) => {}
+                }
+            }
+            None => properties.skip_value(column.type_())?,
+        }
+    }
+    Ok(false)
+}
+
+/// Process all or the projected columns in column order, including columns without value
+fn process_properties_with_nulls<P: NullPropertyProcessor>(
+    header: Header,
+    feature: Feature,
+    projection: Option<&Projection>,
+    reader: &mut P,
+) -> Result<bool> {
+    for (idx, (name, value)) in column_values(header, feature, projection)?
+        .into_iter()
+        .enumerate()
+    {
+        let abort = match value {
+            Some(value) => reader.property(idx, name, &value)?,
+            None => reader.null_property(idx, name, column_type(header, feature, name))?,
+        };
+        if abort {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Type of column `name` of the feature or else of the dataset
+fn column_type(header: Header, feature: Feature, name: &str) -> Option<ColumnType> {
+    [feature.columns(), header.columns()]
+        .into_iter()
+        .flatten()
+        .find_map(|columns| columns.iter().find(|column| column.name() == name))
+        .map(|column| column.type_())
+}
+
+/// Passes columns without value as JSON `null` to a property processor, e.g. a GeoJSON writer
+struct JsonNulls<'a, P>(&'a mut P);
+
+impl<P: PropertyProcessor> PropertyProcessor for JsonNulls<'_, P> {
+    fn property(&mut self, idx: usize, name: &str, value: &ColumnValue) -> Result<bool> {
+        self.0.property(idx, name, value)
+    }
+}
+
+impl<P: PropertyProcessor> NullPropertyProcessor for JsonNulls<'_, P> {
+    fn null_property(
+        &mut self,
+        idx: usize,
+        name: &str,
+        _column_type: Option<ColumnType>,
+    ) -> Result<bool> {
+        self.0.property(idx, name, &ColumnValue::Json("null"))
+    }
+}
+
+/// Names and values of all or the projected columns in column order, `None` for columns without
+/// value
+pub(crate) fn column_values<'a>(
+    header: Header<'a>,
+    feature: Feature<'a>,
+    projection: Option<&'a Projection>,
+) -> Result<Vec<(&'a str, Option<ColumnValue<'a>>)>> {
+    let Some(projection) = projection else {
+        let mut values: Vec<_> = feature_columns(header, feature)
+            .map(|columns| columns.iter().map(|column| (column.name(), None)).collect())
+            .unwrap_or_default();
+        for property in RawProperties::new(header, feature).into_iter().flatten() {
+            let (column_idx, _, value) = property?;
+            values[column_idx].1 = Some(value);
+        }
+        return Ok(values);
+    };
+    let positions = projection.positions(feature);
+    let mut values = projection
+        .names
+        .iter()
+        .map(|name| (name.as_str(), None))
+        .collect::<Vec<_>>();
+    for property in RawProperties::new(header, feature).into_iter().flatten() {
+        let (column_idx, _, value) = property?;
+        if let Some(idx) = positions[column_idx] {
+            values[idx].1 = Some(value);
+        }
+    }
+    Ok(values)
+}
+
+fn property_opt<T: PropertyReadType>(
+    header: Header,
+    feature: Feature,
+    projection: Option<&Projection>,
+    name: &str,
+) -> crate::Result<Option<T>> {
+    let column_idx = feature_columns(header, feature)
+        .and_then(|columns| columns.iter().position(|column| column.name() == name));
+    // Without dataset columns, features have their own and may lack any column
+    let declared = header.columns().is_some_and(|columns| !columns.is_empty());
+    if column_idx.is_none() && declared && feature.columns().is_none()
+        || projection.is_some_and(|p| !p.names.iter().any(|n| n == name))
+    {
+        return Err(crate::Error::UnknownColumn(name.to_string()));
+    }
+    let to_error = |e| crate::Error::IO(std::io::Error::new(std::io::ErrorKind::InvalidData, e));
+    for property in RawProperties::new(header, feature).into_iter().flatten() {
+        let (idx, _, value) = property.map_err(to_error)?;
+        if Some(idx) == column_idx {
+            return T::get_value(&value).map(Some).map_err(to_error);
+        }
+    }
+    Ok(None)
+}
+
+/// Columns of a feature, the dataset columns unless the feature has its own
+pub(crate) fn feature_columns<'a>(
+    header: Header<'a>,
+    feature: Feature<'a>,
+) -> Option<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<Column<'a>>>> {
+    feature.columns().or_else(|| header.columns())
+}
+
+/// Subset of the columns, re-indexed in selection order
+#[derive(Clone, Debug)]
+pub(crate) struct Projection {
+    /// Selected column names without duplicates
+    names: Vec<String>,
+    /// Projected index by dataset column index
+    positions: Vec<Option<usize>>,
+}
+
+impl Projection {
+    /// Fails with [`crate::Error::UnknownColumn`] for names not in `header`, unless the dataset
+    /// declares no columns and features have their own.
+    pub(crate) fn new(header: Header, names: &[&str]) -> crate::Result<Self> {
+        let columns = header.columns().filter(|columns| !columns.is_empty());
+        let mut positions = vec![None; columns.map(|c| c.len()).unwrap_or(0)];
+        let mut selected: Vec<String> = Vec::new();
+        for name in names {
+            if selected.iter().any(|n| n == name) {
+                continue;
+            }
+            if let Some(columns) = columns {
+                let column_idx = columns
+                    .iter()
+                    .position(|column| column.name() == *name)
+                    .ok_or_else(|| crate::Error::UnknownColumn(name.to_string()))?;
+                positions[column_idx] = Some(selected.len());
+            }
+            selected.push(name.to_string());
+        }
+        Ok(Projection {
+            names: selected,
+            positions,
+        })
+    }
+
+    /// Number of selected columns
+    pub(crate) fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Projected index by index into the columns of `feature`
+    pub(crate) fn positions(&self, feature: Feature) -> Cow<'_, [Option<usize>]> {
+        match feature.columns() {
+            Some(columns) => Cow::Owned(
+                columns
+                    .iter()
+                    .map(|column| self.names.iter().position(|n| n == column.name()))
+                    .collect(),
+            ),
+            None => Cow::Borrowed(&self.positions),
+        }
+    }
+
+    /// Dataset column indices in selection order
+    #[cfg(feature = "arrow")]
+    pub(crate) fn column_indices(&self) -> Vec<usize> {
+        let mut indices = vec![0; self.len()];
+        for (column_idx, position) in self.positions.iter().enumerate() {
+            if let Some(idx) = position {
+                indices[*idx] = column_idx;
+            }
+        }
+        indices
+    }
+}
+
+/// Iterator over the encoded properties of a feature.
+///
+/// Values borrow from the feature buffer, so no property data is copied.
+pub(crate) struct RawProperties<'a> {
+    columns: flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<Column<'a>>>,
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> RawProperties<'a> {
+    /// Returns `None` if neither the feature nor the dataset has columns.
+    pub(crate) fn new(header: Header<'a>, feature: Feature<'a>) -> Option<Self> {
+        Some(RawProperties {
+            columns: feature_columns(header, feature)?,
+            bytes: feature.properties().map(|p| p.bytes()).unwrap_or_default(),
+            offset: 0,
+        })
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .offset
+            .checked_add(len)
+            .and_then(|end| self.bytes.get(self.offset..end))
+            .ok_or(GeozeroError::GeometryFormat)?;
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn read_str(&mut self) -> Result<&'a str> {
+        let len = LittleEndian::read_u32(self.read_bytes(size_of::<u32>())?) as usize;
+        // unsafe variant without UTF-8 checking would be faster...
+        str::from_utf8(self.read_bytes(len)?)
+            .map_err(|_| GeozeroError::Property("Invalid UTF-8 encoding".to_string()))
+    }
+
+    /// Skip a value without decoding it
+    fn skip_value(&mut self, column_type: ColumnType) -> Result<()> {
+        let len = match column_type {
+            ColumnType::Byte | ColumnType::UByte | ColumnType::Bool => 1,
+            ColumnType::Short | ColumnType::UShort => 2,
+            ColumnType::Int | ColumnType::UInt | ColumnType::Float => 4,
+            ColumnType::Long | ColumnType::ULong | ColumnType::Double => 8,
+            ColumnType::String | ColumnType::Json | ColumnType::DateTime | ColumnType::Binary => {
+                LittleEndian::read_u32(self.read_bytes(size_of::<u32>())?) as usize
+            }
+            ColumnType(_) => 0,
+        };
+        self.read_bytes(len)?;
+        Ok(())
+    }
+
+    /// Read the column index of the next value
+    fn next_column(&mut self) -> Option<Result<(usize, Column<'a>)>> {
+        // NOTE: it should be offset < len, but there is data with a
+        // trailing byte in the last column of type Binary
+        if self.offset + 1 >= self.bytes.len() {
+            return None;
+        }
+        let column_idx = LittleEndian::read_u16(&self.bytes[self.offset..]) as usize;
+        self.offset += size_of::<u16>();
+        if column_idx >= self.columns.len() {
+            // NOTE: reading also fails if column._type is different from effective entry
+            self.offset = self.bytes.len();
+            return Some(Err(GeozeroError::GeometryFormat));
+        }
+        Some(Ok((column_idx, self.columns.get(column_idx))))
+    }
+
+    fn read_value(&mut self, column_type: ColumnType) -> Result<Option<ColumnValue<'a>>> {
+        let value = match column_type {
+            ColumnType::Int => ColumnValue::Int(LittleEndian::read_i32(self.read_bytes(4)?)),
+            ColumnType::Long => ColumnValue::Long(LittleEndian::read_i64(self.read_bytes(8)?)),
+            ColumnType::ULong => ColumnValue::ULong(LittleEndian::read_u64(self.read_bytes(8)?)),
+            ColumnType::Double => ColumnValue::Double(LittleEndian::read_f64(self.read_bytes(8)?)),
+            ColumnType::String => ColumnValue::String(self.read_str()?),
+            ColumnType::Byte => ColumnValue::Byte(self.read_bytes(1)?[0] as i8),
+            ColumnType::UByte => ColumnValue::UByte(self.read_bytes(1)?[0]),
+            ColumnType::Bool => ColumnValue::Bool(self.read_bytes(1)?[0] != 0),
+            ColumnType::Short => ColumnValue::Short(LittleEndian::read_i16(self.read_bytes(2)?)),
+            ColumnType::UShort => ColumnValue::UShort(LittleEndian::read_u16(self.read_bytes(2)?)),
+            ColumnType::UInt => ColumnValue::UInt(LittleEndian::read_u32(self.read_bytes(4)?)),
+            ColumnType::Float => ColumnValue::Float(LittleEndian::read_f32(self.read_bytes(4)?)),
+            // JSON may be represented using UTF-8, UTF-16, or UTF-32. The default encoding is UTF-8.
+            ColumnType::Json => ColumnValue::Json(self.read_str()?),
+            ColumnType::DateTime => ColumnValue::DateTime(self.read_str()?),
+            ColumnType::Binary => {
+                let len = LittleEndian::read_u32(self.read_bytes(size_of::<u32>())?) as usize;
+                ColumnValue::Binary(self.read_bytes(len)?)
+            }
+            ColumnType(_) => return Ok(None),
+        };
+        Ok(Some(value))
+    }
+}
+
+impl<'a> Iterator for RawProperties<'a> {
+    /// Column index, column metadata and value
+    type Item = Result<(usize, Column<'a>, ColumnValue<'a>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (column_idx, column) = match self.next_column()? {
+                Ok(entry) => entry,
+                Err(e) => return Some(Err(e)),
+            };
+            match self.read_value(column.type_()) {
+                Ok(Some(value)) => return Some(Ok((column_idx, column, value))),
+                Ok(None) => {}
+                Err(e) => {
+                    self.offset = self.bytes.len();
+                    return Some(Err(e));
                 }
             }
         }
-        Ok(finish)
     }
 }
@@ -0,0 +1,909 @@
+//! Serde serialization of feature properties and schema derivation.
+
+use crate::error::{Error, Result, SchemaError};
+use crate::feature_writer::{check_width, convert_value, prop_type, FeatureWriter};
+use crate::file_writer::ColumnDef;
+use crate::header_generated::ColumnType;
+use geozero::{ColumnValue, PropertyProcessor};
+use serde::de::value::{BorrowedStrDeserializer, SeqDeserializer, StrDeserializer};
+use serde::de::{self, DeserializeSeed, IntoDeserializer, MapAccess, Visitor};
+use serde::ser::{self, Impossible, Serialize};
+use serde::{forward_to_deserialize_any, Deserialize};
+
+/// Column declarations derived from the fields of the struct `T`
+pub(crate) fn schema_columns<'de, T: Deserialize<'de>>() -> Result<Vec<ColumnDef>> {
+    let mut columns = Vec::new();
+    T::deserialize(SchemaTracer {
+        columns: &mut columns,
+    })?;
+    Ok(columns)
+}
+
+/// Column declarations derived from the fields of `row` as written by its `Serialize`
+/// implementation
+pub(crate) fn row_columns<T: Serialize + ?Sized>(row: &T) -> Result<Vec<ColumnDef>> {
+    let mut columns = Vec::new();
+    row.serialize(RowTracer {
+        columns: &mut columns,
+    })?;
+    Ok(columns)
+}
+
+/// Write the fields of `row` as properties of the columns declared in `writer`
+pub(crate) fn serialize_properties<T: Serialize + ?Sized>(
+    row: &T,
+    writer: &mut FeatureWriter,
+) -> Result<()> {
+    row.serialize(RowSerializer { writer })
+}
+
+/// Deserializer recording the fields of a struct instead of providing data
+struct SchemaTracer<'c> {
+    columns: &'c mut Vec<ColumnDef>,
+}
+
+impl<'de> de::Deserializer<'de> for SchemaTracer<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(Error::Serde(
+            "Schema derivation requires a struct with named fields".to_string(),
+        ))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_map(FieldsTracer {
+            fields: fields.iter(),
+            field: None,
+            columns: self.columns,
+        })
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct seq tuple tuple_struct map enum
+        identifier ignored_any
+    }
+}
+
+/// Struct fields as map with traced values
+struct FieldsTracer<'c> {
+    fields: std::slice::Iter<'static, &'static str>,
+    /// Name of the last key in map access
+    field: Option<&'static str>,
+    columns: &'c mut Vec<ColumnDef>,
+}
+
+impl<'de> MapAccess<'de> for FieldsTracer<'_> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        match self.fields.next() {
+            Some(name) => {
+                self.field = Some(name);
+                seed.deserialize(BorrowedStrDeserializer::new(name))
+                    .map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let name = self
+            .field
+            .take()
+            .ok_or_else(|| Error::Serde("value requested before key".to_string()))?;
+        let mut column = None;
+        let value = seed.deserialize(FieldTracer {
+            name,
+            nullable: false,
+            column: &mut column,
+        })?;
+        let column = column
+            .ok_or_else(|| Error::Serde(format!("Cannot derive column type of field `{name}`")))?;
+        self.columns.push(column);
+        Ok(value)
+    }
+}
+
+/// Deserializer or serializer of a field, declaring its column from the requested or written
+/// type
+struct FieldTracer<'c> {
+    name: &'static str,
+    nullable: bool,
+    column: &'c mut Option<ColumnDef>,
+}
+
+impl FieldTracer<'_> {
+    fn declare(self, type_: ColumnType, width: usize) {
+        *self.column = Some(ColumnDef {
+            name: self.name.to_string(),
+            type_,
+            nullable: self.nullable,
+            width: width as i32,
+        });
+    }
+}
+
+macro_rules! trace_primitive {
+    ($($method:ident => $visit:ident($value:expr), $column_type:ident;)*) => {
+        $(fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+            self.declare(ColumnType::$column_type, 0);
+            visitor.$visit($value)
+        })*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for FieldTracer<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(Error::Serde(format!(
+            "Unsupported type of field `{}`",
+            self.name
+        )))
+    }
+
+    trace_primitive! {
+        deserialize_bool => visit_bool(false), Bool;
+        deserialize_i8 => visit_i8(0), Byte;
+        deserialize_u8 => visit_u8(0), UByte;
+        deserialize_i16 => visit_i16(0), Short;
+        deserialize_u16 => visit_u16(0), UShort;
+        deserialize_i32 => visit_i32(0), Int;
+        deserialize_u32 => visit_u32(0), UInt;
+        deserialize_i64 => visit_i64(0), Long;
+        deserialize_u64 => visit_u64(0), ULong;
+        deserialize_f32 => visit_f32(0.0), Float;
+        deserialize_f64 => visit_f64(0.0), Double;
+        deserialize_str => visit_borrowed_str(""), String;
+        deserialize_string => visit_borrowed_str(""), String;
+        deserialize_bytes => visit_borrowed_bytes(&[]), Binary;
+        deserialize_byte_buf => visit_borrowed_bytes(&[]), Binary;
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.declare(ColumnType::String, 1);
+        visitor.visit_char(' ')
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value> {
+        self.nullable = true;
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    // Byte sequences like `Vec<u8>`
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.declare(ColumnType::Binary, 0);
+        visitor.visit_seq(SeqDeserializer::<_, Error>::new(std::iter::empty::<u8>()))
+    }
+
+    // Fixed size byte arrays like `[u8; 16]`
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        self.declare(ColumnType::Binary, len);
+        visitor.visit_seq(SeqDeserializer::<_, Error>::new(std::iter::repeat_n(
+            0u8, len,
+        )))
+    }
+
+    // Unit variants as strings
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        let name = self.name;
+        let Some(variant) = variants.first() else {
+            return Err(Error::Serde(format!(
+                "Enum of field `{name}` has no variants"
+            )));
+        };
+        self.declare(ColumnType::String, 0);
+        let variant: StrDeserializer<Error> = variant.into_deserializer();
+        visitor.visit_enum(variant)
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 unit unit_struct tuple_struct map struct identifier ignored_any
+    }
+}
+
+macro_rules! reject {
+    ($($method:ident($($arg:ty),*) -> $ok:ty;)*) => {
+        $(fn $method(self, $(_: $arg),*) -> Result<$ok> {
+            Err(unsupported(stringify!($method)))
+        })*
+    };
+}
+
+/// Serializer recording the fields of a row struct instead of writing them
+struct RowTracer<'c> {
+    columns: &'c mut Vec<ColumnDef>,
+}
+
+impl<'c> ser::Serializer for RowTracer<'c> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Impossible<(), Error>;
+    type SerializeTuple = Impossible<(), Error>;
+    type SerializeTupleStruct = Impossible<(), Error>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = FieldsRecorder<'c>;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Ok(FieldsRecorder {
+            columns: self.columns,
+        })
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self)
+    }
+
+    reject! {
+        serialize_bool(bool) -> ();
+        serialize_i8(i8) -> ();
+        serialize_i16(i16) -> ();
+        serialize_i32(i32) -> ();
+        serialize_i64(i64) -> ();
+        serialize_u8(u8) -> ();
+        serialize_u16(u16) -> ();
+        serialize_u32(u32) -> ();
+        serialize_u64(u64) -> ();
+        serialize_f32(f32) -> ();
+        serialize_f64(f64) -> ();
+        serialize_char(char) -> ();
+        serialize_str(&str) -> ();
+        serialize_bytes(&[u8]) -> ();
+        serialize_none() -> ();
+        serialize_unit() -> ();
+        serialize_unit_struct(&'static str) -> ();
+        serialize_unit_variant(&'static str, u32, &'static str) -> ();
+        serialize_seq(Option<usize>) -> Self::SerializeSeq;
+        serialize_tuple(usize) -> Self::SerializeTuple;
+        serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct;
+        serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant;
+        serialize_map(Option<usize>) -> Self::SerializeMap;
+        serialize_struct_variant(&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant;
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _value: &T) -> Result<()> {
+        Err(unsupported("row as option"))
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<()> {
+        Err(unsupported("row as enum"))
+    }
+}
+
+/// Serializer recording the written fields of a row struct as columns
+struct FieldsRecorder<'c> {
+    columns: &'c mut Vec<ColumnDef>,
+}
+
+impl ser::SerializeStruct for FieldsRecorder<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        let mut column = None;
+        value.serialize(FieldTracer {
+            name: key,
+            nullable: false,
+            column: &mut column,
+        })?;
+        let column = column
+            .ok_or_else(|| Error::Serde(format!("Cannot derive column type of field `{key}`")))?;
+        self.columns.push(column);
+        Ok(())
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+macro_rules! trace_value {
+    ($($method:ident($arg:ty) => $column_type:ident;)*) => {
+        $(fn $method(self, _: $arg) -> Result<()> {
+            self.declare(ColumnType::$column_type, 0);
+            Ok(())
+        })*
+    };
+}
+
+impl<'c> ser::Serializer for FieldTracer<'c> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = BinaryTracer<'c>;
+    type SerializeTuple = BinaryTracer<'c>;
+    type SerializeTupleStruct = BinaryTracer<'c>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = Impossible<(), Error>;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    trace_value! {
+        serialize_bool(bool) => Bool;
+        serialize_i8(i8) => Byte;
+        serialize_u8(u8) => UByte;
+        serialize_i16(i16) => Short;
+        serialize_u16(u16) => UShort;
+        serialize_i32(i32) => Int;
+        serialize_u32(u32) => UInt;
+        serialize_i64(i64) => Long;
+        serialize_u64(u64) => ULong;
+        serialize_f32(f32) => Float;
+        serialize_f64(f64) => Double;
+        serialize_str(&str) => String;
+        serialize_bytes(&[u8]) => Binary;
+    }
+
+    fn serialize_char(self, _: char) -> Result<()> {
+        self.declare(ColumnType::String, 1);
+        Ok(())
+    }
+
+    // The type of a null value is unknown
+    fn serialize_none(self) -> Result<()> {
+        Err(Error::Serde(format!(
+            "Cannot derive column type of field `{}` from `None`",
+            self.name
+        )))
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(mut self, value: &T) -> Result<()> {
+        self.nullable = true;
+        value.serialize(self)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<()> {
+        self.declare(ColumnType::String, 0);
+        Ok(())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self)
+    }
+
+    // Byte sequences like `Vec<u8>`
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Ok(BinaryTracer {
+            field: self,
+            width: 0,
+        })
+    }
+
+    // Fixed size byte arrays like `[u8; 16]`
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        Ok(BinaryTracer {
+            field: self,
+            width: len,
+        })
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        self.serialize_tuple(len)
+    }
+
+    reject! {
+        serialize_unit() -> ();
+        serialize_unit_struct(&'static str) -> ();
+        serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant;
+        serialize_map(Option<usize>) -> Self::SerializeMap;
+        serialize_struct(&'static str, usize) -> Self::SerializeStruct;
+        serialize_struct_variant(&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant;
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<()> {
+        Err(unsupported("enum variant with data"))
+    }
+}
+
+/// Serializer of byte sequences, declaring a binary column
+struct BinaryTracer<'c> {
+    field: FieldTracer<'c>,
+    width: usize,
+}
+
+impl ser::SerializeSeq for BinaryTracer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        value.serialize(ByteSerializer)?;
+        Ok(())
+    }
+
+    fn end(self) -> Result<()> {
+        self.field.declare(ColumnType::Binary, self.width);
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for BinaryTracer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for BinaryTracer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+fn unsupported(what: &str) -> Error {
+    Error::Serde(format!("Unsupported property type: {what}"))
+}
+
+/// Serializer of a row struct
+struct RowSerializer<'w, 'a> {
+    writer: &'w mut FeatureWriter<'a>,
+}
+
+impl<'w, 'a> ser::Serializer for RowSerializer<'w, 'a> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Impossible<(), Error>;
+    type SerializeTuple = Impossible<(), Error>;
+    type SerializeTupleStruct = Impossible<(), Error>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = FieldsSerializer<'w, 'a>;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Ok(FieldsSerializer {
+            writer: self.writer,
+        })
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self)
+    }
+
+    reject! {
+        serialize_bool(bool) -> ();
+        serialize_i8(i8) -> ();
+        serialize_i16(i16) -> ();
+        serialize_i32(i32) -> ();
+        serialize_i64(i64) -> ();
+        serialize_u8(u8) -> ();
+        serialize_u16(u16) -> ();
+        serialize_u32(u32) -> ();
+        serialize_u64(u64) -> ();
+        serialize_f32(f32) -> ();
+        serialize_f64(f64) -> ();
+        serialize_char(char) -> ();
+        serialize_str(&str) -> ();
+        serialize_bytes(&[u8]) -> ();
+        serialize_none() -> ();
+        serialize_unit() -> ();
+        serialize_unit_struct(&'static str) -> ();
+        serialize_unit_variant(&'static str, u32, &'static str) -> ();
+        serialize_seq(Option<usize>) -> Self::SerializeSeq;
+        serialize_tuple(usize) -> Self::SerializeTuple;
+        serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct;
+        serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant;
+        serialize_map(Option<usize>) -> Self::SerializeMap;
+        serialize_struct_variant(&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant;
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _value: &T) -> Result<()> {
+        Err(unsupported("row as option"))
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<()> {
+        Err(unsupported("row as enum"))
+    }
+}
+
+/// Serializer of the fields of a row struct as properties
+struct FieldsSerializer<'w, 'a> {
+    writer: &'w mut FeatureWriter<'a>,
+}
+
+impl ser::SerializeStruct for FieldsSerializer<'_, '_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        let idx = self
+            .writer
+            .columns
+            .iter()
+            .position(|column| column.name == key)
+            .ok_or_else(|| Error::UnknownColumn(key.to_string()))?;
+        value.serialize(ValueSerializer {
+            idx,
+            name: key,
+            writer: self.writer,
+        })
+    }
+
+    // Checked before processing the geometry, for missing fields and `None` values
+    fn end(self) -> Result<()> {
+        match self
+            .writer
+            .columns
+            .iter()
+            .enumerate()
+            .find(|(i, column)| !column.nullable && !self.writer.has_value(*i))
+        {
+            Some((_, column)) => Err(Error::MissingValue(column.name.clone())),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Serializer of a property value of a declared column
+struct ValueSerializer<'w, 'a> {
+    idx: usize,
+    name: &'static str,
+    writer: &'w mut FeatureWriter<'a>,
+}
+
+impl ValueSerializer<'_, '_> {
+    fn write(self, value: ColumnValue) -> Result<()> {
+        let column = &self.writer.columns[self.idx];
+        let value_type = prop_type(&value);
+        let value = if value_type == column.type_ {
+            value
+        } else {
+            convert_value(&value, column.type_).ok_or_else(|| SchemaError::ColumnType {
+                column: column.name.clone(),
+                expected: column.type_,
+                found: value_type,
+            })?
+        };
+        check_width(column, &value)?;
+        self.writer
+            .property(self.idx, self.name, &value)
+            .map_err(|e| Error::IO(std::io::Error::new(std::io::ErrorKind::InvalidData, e)))?;
+        Ok(())
+    }
+}
+
+impl<'w, 'a> ser::Serializer for ValueSerializer<'w, 'a> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = BytesSerializer<'w, 'a>;
+    type SerializeTuple = BytesSerializer<'w, 'a>;
+    type SerializeTupleStruct = BytesSerializer<'w, 'a>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = Impossible<(), Error>;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        self.write(ColumnValue::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        self.write(ColumnValue::Byte(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        self.write(ColumnValue::Short(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        self.write(ColumnValue::Int(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        self.write(ColumnValue::Long(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        self.write(ColumnValue::UByte(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        self.write(ColumnValue::UShort(v))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        self.write(ColumnValue::UInt(v))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        self.write(ColumnValue::ULong(v))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        self.write(ColumnValue::Float(v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        self.write(ColumnValue::Double(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<()> {
+        self.write(ColumnValue::String(v.encode_utf8(&mut [0; 4])))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<()> {
+        self.write(ColumnValue::String(v))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        self.write(ColumnValue::Binary(v))
+    }
+
+    // Nulls are encoded by omitting the property
+    fn serialize_none(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<()> {
+        self.write(ColumnValue::String(variant))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Ok(BytesSerializer {
+            bytes: Vec::with_capacity(len.unwrap_or(0)),
+            value: self,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        self.serialize_seq(Some(len))
+    }
+
+    reject! {
+        serialize_unit() -> ();
+        serialize_unit_struct(&'static str) -> ();
+        serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant;
+        serialize_map(Option<usize>) -> Self::SerializeMap;
+        serialize_struct(&'static str, usize) -> Self::SerializeStruct;
+        serialize_struct_variant(&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant;
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<()> {
+        Err(unsupported("enum variant with data"))
+    }
+}
+
+/// Serializer of byte sequences as binary value
+struct BytesSerializer<'w, 'a> {
+    bytes: Vec<u8>,
+    value: ValueSerializer<'w, 'a>,
+}
+
+impl BytesSerializer<'_, '_> {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.bytes.push(value.serialize(ByteSerializer)?);
+        Ok(())
+    }
+}
+
+impl ser::SerializeSeq for BytesSerializer<'_, '_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.value.write(ColumnValue::Binary(&self.bytes))
+    }
+}
+
+impl ser::SerializeTuple for BytesSerializer<'_, '_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<()> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for BytesSerializer<'_, '_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<()> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+/// Serializer of binary sequence elements
+struct ByteSerializer;
+
+impl ser::Serializer for ByteSerializer {
+    type Ok = u8;
+    type Error = Error;
+    type SerializeSeq = Impossible<u8, Error>;
+    type SerializeTuple = Impossible<u8, Error>;
+    type SerializeTupleStruct = Impossible<u8, Error>;
+    type SerializeTupleVariant = Impossible<u8, Error>;
+    type SerializeMap = Impossible<u8, Error>;
+    type SerializeStruct = Impossible<u8, Error>;
+    type SerializeStructVariant = Impossible<u8, Error>;
+
+    fn serialize_u8(self, v: u8) -> Result<u8> {
+        Ok(v)
+    }
+
+    reject! {
+        serialize_bool(bool) -> u8;
+        serialize_i8(i8) -> u8;
+        serialize_i16(i16) -> u8;
+        serialize_i32(i32) -> u8;
+        serialize_i64(i64) -> u8;
+        serialize_u16(u16) -> u8;
+        serialize_u32(u32) -> u8;
+        serialize_u64(u64) -> u8;
+        serialize_f32(f32) -> u8;
+        serialize_f64(f64) -> u8;
+        serialize_char(char) -> u8;
+        serialize_str(&str) -> u8;
+        serialize_bytes(&[u8]) -> u8;
+        serialize_none() -> u8;
+        serialize_unit() -> u8;
+        serialize_unit_struct(&'static str) -> u8;
+        serialize_unit_variant(&'static str, u32, &'static str) -> u8;
+        serialize_seq(Option<usize>) -> Self::SerializeSeq;
+        serialize_tuple(usize) -> Self::SerializeTuple;
+        serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct;
+        serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant;
+        serialize_map(Option<usize>) -> Self::SerializeMap;
+        serialize_struct(&'static str, usize) -> Self::SerializeStruct;
+        serialize_struct_variant(&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant;
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _value: &T) -> Result<u8> {
+        Err(unsupported("optional element of binary sequence"))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<u8> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<u8> {
+        Err(unsupported("enum variant with data"))
+    }
+}
+
+impl ser::Error for Error {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Error::Serde(msg.to_string())
+    }
+}
@@ -0,0 +1,493 @@
+use crate::feature_generated::*;
+use crate::header_generated::*;
+use crate::properties_reader::RawProperties;
+use crate::{Error, Result};
+use geozero::ColumnValue;
+use std::cmp::Ordering;
+
+/// Property filter expression
+///
+/// Expressions are evaluated directly on the encoded properties of each feature, so features
+/// which don't match are skipped without decoding them. Comparisons follow SQL semantics:
+/// a missing (null) property never matches, unless tested with [`Expr::is_null`].
+///
+/// # Usage example:
+///
+/// ```
+/// use flatgeobuf::*;
+/// # use std::fs::File;
+/// # use std::io::BufReader;
+///
+/// # fn read_fgb() -> std::result::Result<(), Box<dyn std::error::Error>> {
+/// let mut filein = BufReader::new(File::open("countries.fgb")?);
+/// let mut fgb = FgbReader::open(&mut filein)?
+///     .select_all()?
+///     .filter_properties(Expr::like("name", "G%").and(Expr::ne("name", "Germany")))?;
+/// while let Some(feature) = fgb.next()? {
+///     println!("{}", feature.property::<String>("name").unwrap());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub enum Expr {
+    /// Compare a column with a value
+    Compare {
+        column: String,
+        op: CompareOp,
+        value: Literal,
+    },
+    /// Column value is equal to one of the values
+    In {
+        column: String,
+        values: Vec<Literal>,
+    },
+    /// SQL `LIKE` pattern match of a string column. `%` matches any sequence of characters,
+    /// `_` matches a single character.
+    Like {
+        column: String,
+        pattern: String,
+    },
+    /// Column value is null
+    IsNull(String),
+    /// Column value is not null
+    IsNotNull(String),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+}
+
+/// Comparison operator
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// Literal value in a property filter expression
+#[derive(Clone, Debug, PartialEq)]
+pub enum Literal {
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    String(String),
+}
+
+macro_rules! impl_from_literal {
+    ($variant:ident, $target:ty, $($t:ty),+) => {
+        $(
+            impl From<$t> for Literal {
+                fn from(value: $t) -> Self {
+                    Literal::$variant(value as $target)
+                }
+            }
+        )+
+    };
+}
+
+impl_from_literal!(Int, i64, i8, i16, i32, i64);
+impl_from_literal!(UInt, u64, u8, u16, u32, u64);
+impl_from_literal!(Float, f64, f32, f64);
+
+impl From<bool> for Literal {
+    fn from(value: bool) -> Self {
+        Literal::Bool(value)
+    }
+}
+
+impl From<&str> for Literal {
+    fn from(value: &str) -> Self {
+        Literal::String(value.to_string())
+    }
+}
+
+impl From<String> for Literal {
+    fn from(value: String) -> Self {
+        Literal::String(value)
+    }
+}
+
+impl Expr {
+    fn compare(column: &str, op: CompareOp, value: impl Into<Literal>) -> Expr {
+        Expr::Compare {
+            column: column.to_string(),
+            op,
+            value: value.into(),
+        }
+    }
+    /// `column = value`
+    pub fn eq(column: &str, value: impl Into<Literal>) -> Expr {
+        Self::compare(column, CompareOp::Eq, value)
+    }
+    /// `column <> value`
+    pub fn ne(column: &str, value: impl Into<Literal>) -> Expr {
+        Self::compare(column, CompareOp::Ne, value)
+    }
+    /// `column < value`
+    pub fn lt(column: &str, value: impl Into<Literal>) -> Expr {
+        Self::compare(column, CompareOp::Lt, value)
+    }
+    /// `column <= value`
+    pub fn le(column: &str, value: impl Into<Literal>) -> Expr {
+        Self::compare(column, CompareOp::Le, value)
+    }
+    /// `column > value`
+    pub fn gt(column: &str, value: impl Into<Literal>) -> Expr {
+        Self::compare(column, CompareOp::Gt, value)
+    }
+    /// `column >= value`
+    pub fn ge(column: &str, value: impl Into<Literal>) -> Expr {
+        Self::compare(column, CompareOp::Ge, value)
+    }
+    /// `column IN (values)`
+    pub fn is_in<V: Into<Literal>>(column: &str, values: impl IntoIterator<Item = V>) -> Expr {
+        Expr::In {
+            column: column.to_string(),
+            values: values.into_iter().map(Into::into).collect(),
+        }
+    }
+    /// `column LIKE pattern`
+    pub fn like(column: &str, pattern: &str) -> Expr {
+        Expr::Like {
+            column: column.to_string(),
+            pattern: pattern.to_string(),
+        }
+    }
+    /// `column IS NULL`
+    pub fn is_null(column: &str) -> Expr {
+        Expr::IsNull(column.to_string())
+    }
+    /// `column IS NOT NULL`
+    pub fn is_not_null(column: &str) -> Expr {
+        Expr::IsNotNull(column.to_string())
+    }
+    /// `self AND other`
+    pub fn and(self, other: Expr) -> Expr {
+        Expr::And(Box::new(self), Box::new(other))
+    }
+    /// `self OR other`
+    pub fn or(self, other: Expr) -> Expr {
+        Expr::Or(Box::new(self), Box::new(other))
+    }
+}
+
+impl std::ops::Not for Expr {
+    type Output = Expr;
+
+    /// `NOT self`
+    fn not(self) -> Expr {
+        Expr::Not(Box::new(self))
+    }
+}
+
+/// Column resolved against the dataset columns
+#[derive(Clone, Debug)]
+struct ColumnRef {
+    /// Dataset column index, `None` if the dataset declares no columns
+    idx: Option<usize>,
+    /// Name for features with their own columns
+    name: String,
+}
+
+/// Expression with column names resolved to column indices
+#[derive(Clone, Debug)]
+enum Compiled {
+    Compare {
+        column: ColumnRef,
+        op: CompareOp,
+        value: Literal,
+    },
+    In {
+        column: ColumnRef,
+        values: Vec<Literal>,
+    },
+    Like {
+        column: ColumnRef,
+        pattern: Vec<char>,
+    },
+    IsNull(ColumnRef),
+    And(Box<Compiled>, Box<Compiled>),
+    Or(Box<Compiled>, Box<Compiled>),
+    Not(Box<Compiled>),
+}
+
+/// Property filter compiled against the columns of a dataset
+#[derive(Clone, Debug)]
+pub(crate) struct PropertyFilter {
+    expr: Compiled,
+}
+
+impl PropertyFilter {
+    /// Resolve the column names of `expr`. Fails with [`Error::UnknownColumn`] for columns not
+    /// in the dataset, unless the dataset declares no columns and features have their own.
+    pub(crate) fn new(expr: Expr, header: Header) -> Result<Self> {
+        Ok(PropertyFilter {
+            expr: compile(expr, header)?,
+        })
+    }
+
+    /// Combine with another filter, both have to match
+    pub(crate) fn and(self, other: PropertyFilter) -> Self {
+        PropertyFilter {
+            expr: Compiled::And(Box::new(self.expr), Box::new(other.expr)),
+        }
+    }
+
+    pub(crate) fn matches(&self, header: Header, feature: Feature) -> Result<bool> {
+        Ok(eval(&self.expr, header, feature)? == Some(true))
+    }
+}
+
+fn column_ref(header: Header, name: &str) -> Result<ColumnRef> {
+    let idx = match header.columns().filter(|columns| !columns.is_empty()) {
+        Some(columns) => Some(
+            columns
+                .iter()
+                .position(|column| column.name() == name)
+                .ok_or_else(|| Error::UnknownColumn(name.to_string()))?,
+        ),
+        None => None,
+    };
+    Ok(ColumnRef {
+        idx,
+        name: name.to_string(),
+    })
+}
+
+fn compile(expr: Expr, header: Header) -> Result<Compiled> {
+    Ok(match expr {
+        Expr::Compare { column, op, value } => Compiled::Compare {
+            column: column_ref(header, &column)?,
+            op,
+            value,
+        },
+        Expr::In { column, values } => Compiled::In {
+            column: column_ref(header, &column)?,
+            values,
+        },
+        Expr::Like { column, pattern } => Compiled::Like {
+            column: column_ref(header, &column)?,
+            pattern: pattern.chars().collect(),
+        },
+        Expr::IsNull(column) => Compiled::IsNull(column_ref(header, &column)?),
+        Expr::IsNotNull(column) => {
+            Compiled::Not(Box::new(Compiled::IsNull(column_ref(header, &column)?)))
+        }
+        Expr::And(a, b) => Compiled::And(
+            Box::new(compile(*a, header)?),
+            Box::new(compile(*b, header)?),
+        ),
+        Expr::Or(a, b) => Compiled::Or(
+            Box::new(compile(*a, header)?),
+            Box::new(compile(*b, header)?),
+        ),
+        Expr::Not(a) => Compiled::Not(Box::new(compile(*a, header)?)),
+    })
+}
+
+/// Find the value of a column by scanning the encoded properties
+fn column_value<'a>(
+    header: Header<'a>,
+    feature: Feature<'a>,
+    column: &ColumnRef,
+) -> Result<Option<ColumnValue<'a>>> {
+    let column_idx = match feature.columns() {
+        Some(columns) => columns.iter().position(|c| c.name() == column.name),
+        None => column.idx,
+    };
+    let (Some(column_idx), Some(properties)) = (column_idx, RawProperties::new(header, feature))
+    else {
+        return Ok(None);
+    };
+    for property in properties {
+        let (idx, _column, value) = property
+            .map_err(|e| Error::IO(std::io::Error::new(std::io::ErrorKind::InvalidData, e)))?;
+        if idx == column_idx {
+            return Ok(Some(value));
+        }
+    }
+    Ok(None)
+}
+
+/// Evaluate with SQL three-valued logic, `None` being unknown
+fn eval(expr: &Compiled, header: Header, feature: Feature) -> Result<Option<bool>> {
+    Ok(match expr {
+        Compiled::Compare { column, op, value } => column_value(header, feature, column)?
+            .and_then(|v| compare(&v, value))
+            .map(|ordering| match op {
+                CompareOp::Eq => ordering == Ordering::Equal,
+                CompareOp::Ne => ordering != Ordering::Equal,
+                CompareOp::Lt => ordering == Ordering::Less,
+                CompareOp::Le => ordering != Ordering::Greater,
+                CompareOp::Gt => ordering == Ordering::Greater,
+                CompareOp::Ge => ordering != Ordering::Less,
+            }),
+        Compiled::In { column, values } => column_value(header, feature, column)?.map(|v| {
+            values
+                .iter()
+                .any(|value| compare(&v, value) == Some(Ordering::Equal))
+        }),
+        Compiled::Like { column, pattern } => match column_value(header, feature, column)? {
+            Some(ColumnValue::String(s) | ColumnValue::DateTime(s) | ColumnValue::Json(s)) => {
+                Some(like(pattern, s))
+            }
+            Some(_) => Some(false),
+            None => None,
+        },
+        Compiled::IsNull(column) => Some(column_value(header, feature, column)?.is_none()),
+        Compiled::And(a, b) => match eval(a, header, feature)? {
+            Some(false) => Some(false),
+            a => match (a, eval(b, header, feature)?) {
+                (_, Some(false)) => Some(false),
+                (Some(true), Some(true)) => Some(true),
+                _ => None,
+            },
+        },
+        Compiled::Or(a, b) => match eval(a, header, feature)? {
+            Some(true) => Some(true),
+            a => match (a, eval(b, header, feature)?) {
+                (_, Some(true)) => Some(true),
+                (Some(false), Some(false)) => Some(false),
+                _ => None,
+            },
+        },
+        Compiled::Not(a) => eval(a, header, feature)?.map(|v| !v),
+    })
+}
+
+/// Numeric value of integer columns
+fn as_int(value: &ColumnValue) -> Option<i128> {
+    Some(match *value {
+        ColumnValue::Byte(v) => v as i128,
+        ColumnValue::UByte(v) => v as i128,
+        ColumnValue::Short(v) => v as i128,
+        ColumnValue::UShort(v) => v as i128,
+        ColumnValue::Int(v) => v as i128,
+        ColumnValue::UInt(v) => v as i128,
+        ColumnValue::Long(v) => v as i128,
+        ColumnValue::ULong(v) => v as i128,
+        _ => return None,
+    })
+}
+
+/// Order of a column value relative to a literal, `None` if not comparable
+fn compare(value: &ColumnValue, literal: &Literal) -> Option<Ordering> {
+    match (value, literal) {
+        (ColumnValue::Bool(v), Literal::Bool(l)) => Some(v.cmp(l)),
+        (
+            ColumnValue::String(v) | ColumnValue::DateTime(v) | ColumnValue::Json(v),
+            Literal::String(l),
+        ) => Some((*v).cmp(l.as_str())),
+        (v, Literal::Int(l)) if as_int(v).is_some() => as_int(v).map(|v| v.cmp(&(*l as i128))),
+        (v, Literal::UInt(l)) if as_int(v).is_some() => as_int(v).map(|v| v.cmp(&(*l as i128))),
+        (ColumnValue::Float(v), l) => as_float(l).and_then(|l| (*v as f64).partial_cmp(&l)),
+        (ColumnValue::Double(v), l) => as_float(l).and_then(|l| v.partial_cmp(&l)),
+        (v, Literal::Float(l)) => as_int(v).and_then(|v| (v as f64).partial_cmp(l)),
+        _ => None,
+    }
+}
+
+fn as_float(literal: &Literal) -> Option<f64> {
+    match *literal {
+        Literal::Int(v) => Some(v as f64),
+        Literal::UInt(v) => Some(v as f64),
+        Literal::Float(v) => Some(v),
+        _ => None,
+    }
+}
+
+/// SQL `LIKE` matching with `%` and `_` wildcards
+fn like(pattern: &[char], text: &str) -> bool {
+    let mut p = 0;
+    let mut t = text;
+    // Position after the last `%` and the text it has to match
+    let mut backtrack: Option<(usize, &str)> = None;
+    loop {
+        let mut chars = t.chars();
+        match (pattern.get(p), chars.next()) {
+            (Some('%'), _) => {
+                p += 1;
+                backtrack = Some((p, t));
+                continue;
+            }
+            (Some('_'), Some(_)) => {
+                p += 1;
+                t = chars.as_str();
+                continue;
+            }
+            (Some(pc), Some(c)) if *pc == c => {
+                p += 1;
+                t = chars.as_str();
+                continue;
+            }
+            (None, None) => return true,
+            _ => {}
+        }
+        // Mismatch: let the last `%` consume one more character
+        let Some((backtrack_p, backtrack_t)) = backtrack else {
+            return false;
+        };
+        let mut chars = backtrack_t.chars();
+        if chars.next().is_none() {
+            return false;
+        }
+        backtrack = Some((backtrack_p, chars.as_str()));
+        p = backtrack_p;
+        t = chars.as_str();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn like_patterns() {
+        let matches = |pattern: &str, text: &str| like(&pattern.chars().collect::<Vec<_>>(), text);
+        assert!(matches("Ger%", "Germany"));
+        assert!(matches("%many", "Germany"));
+        assert!(matches("%erm%", "Germany"));
+        assert!(matches("G_rmany", "Germany"));
+        assert!(matches("%", ""));
+        assert!(matches("Côte d_Ivoire", "Côte d'Ivoire"));
+        assert!(matches("%a%a%", "Panama"));
+        assert!(!matches("Ger", "Germany"));
+        assert!(!matches("_", ""));
+        assert!(!matches("%x%", "Germany"));
+        assert!(!matches("%a", "Germany"));
+    }
+
+    #[test]
+    fn compare_values() {
+        assert_eq!(
+            compare(&ColumnValue::Int(5), &Literal::Int(5)),
+            Some(Ordering::Equal)
+        );
+        assert_eq!(
+            compare(&ColumnValue::ULong(u64::MAX), &Literal::Int(-1)),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(
+            compare(&ColumnValue::Double(1.5), &Literal::Int(2)),
+            Some(Ordering::Less)
+        );
+        assert_eq!(
+            compare(&ColumnValue::Short(2), &Literal::Float(1.5)),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(
+            compare(&ColumnValue::String("b"), &Literal::String("a".to_string())),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(compare(&ColumnValue::String("1"), &Literal::Int(1)), None);
+        assert_eq!(
+            compare(&ColumnValue::Double(f64::NAN), &Literal::Int(1)),
+            None
+        );
+    }
+}
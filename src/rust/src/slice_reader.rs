@@ -0,0 +1,309 @@
+use crate::feature_generated::*;
+use crate::header_generated::*;
+use crate::packed_r_tree::{self, PackedRTree};
+use crate::properties_reader::FgbFeatureRef;
+use crate::{check_magic_bytes, HEADER_MAX_BUFFER_SIZE};
+use crate::{Error, Result};
+use std::io::Cursor;
+
+/// FlatGeobuf dataset reader over a byte slice
+///
+/// Features are handed out as borrowed [`FgbFeatureRef`] views into the slice, so no feature
+/// bytes are copied. This makes it a good fit for memory-mapped files.
+///
+/// # Usage example:
+///
+/// ```
+/// use flatgeobuf::*;
+///
+/// # fn read_fgb() -> std::result::Result<(), Box<dyn std::error::Error>> {
+/// // Any `&[u8]` works, e.g. a memory-mapped file
+/// let buf = std::fs::read("countries.fgb")?;
+/// let fgb = FgbSliceReader::open(&buf)?;
+/// for feature in fgb.select_bbox(8.8, 47.2, 9.5, 55.3)? {
+///     let feature = feature?;
+///     println!("{}", feature.property::<String>("name").unwrap());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct FgbSliceReader<'a> {
+    buf: &'a [u8],
+    /// FlatBuffers verification
+    verify: bool,
+    header: Header<'a>,
+    /// Byte offset of the index section (directly after the header)
+    index_begin: usize,
+}
+
+/// Iterator over features of a [`FgbSliceReader`] selection.
+pub struct SliceFeatureIter<'a> {
+    /// Feature data section
+    buf: &'a [u8],
+    /// FlatBuffers verification
+    verify: bool,
+    header: Header<'a>,
+    /// Selected features or None if no bbox filter
+    item_filter: Option<std::vec::IntoIter<packed_r_tree::SearchResultItem>>,
+    /// Number of selected features (None for undefined feature count)
+    count: Option<usize>,
+    /// Current feature number
+    feat_no: usize,
+    /// Offset within feature section
+    pos: usize,
+}
+
+impl<'a> FgbSliceReader<'a> {
+    /// Open dataset by reading the header information
+    pub fn open(buf: &'a [u8]) -> Result<FgbSliceReader<'a>> {
+        Self::read_header(buf, true)
+    }
+
+    /// Open dataset by reading the header information without FlatBuffers verification
+    ///
+    /// # Safety
+    /// This method is unsafe because it does not verify the FlatBuffers header.
+    /// It is still safe from the Rust safety guarantees perspective, but it may cause
+    /// undefined behavior if the FlatBuffers header is invalid.
+    pub unsafe fn open_unchecked(buf: &'a [u8]) -> Result<FgbSliceReader<'a>> {
+        Self::read_header(buf, false)
+    }
+
+    fn read_header(buf: &'a [u8], verify: bool) -> Result<FgbSliceReader<'a>> {
+        if buf.len() < 12 {
+            return Err(unexpected_eof("header"));
+        }
+        if !check_magic_bytes(&buf[0..8]) {
+            return Err(Error::MissingMagicBytes);
+        }
+        let header_size = u32::from_le_bytes([buf[8], buf[9], buf[10], buf[11]]) as usize;
+        if header_size > HEADER_MAX_BUFFER_SIZE || header_size < 8 {
+            // minimum size check avoids panic in FlatBuffers header decoding
+            return Err(Error::IllegalHeaderSize(header_size));
+        }
+        let index_begin = 12 + header_size;
+        let header_buf = buf.get(8..index_begin).ok_or(unexpected_eof("header"))?;
+        let header = if verify {
+            size_prefixed_root_as_header(header_buf)?
+        } else {
+            // SAFETY: caller opted out of verification, see `open_unchecked`
+            unsafe { size_prefixed_root_as_header_unchecked(header_buf) }
+        };
+
+        Ok(FgbSliceReader {
+            buf,
+            verify,
+            header,
+            index_begin,
+        })
+    }
+
+    /// Header information
+    pub fn header(&self) -> Header<'a> {
+        self.header
+    }
+
+    fn index_size(&self) -> usize {
+        let feat_count = self.header.features_count() as usize;
+        if self.header.index_node_size() > 0 && feat_count > 0 {
+            PackedRTree::index_size(feat_count, self.header.index_node_size())
+        } else {
+            0
+        }
+    }
+
+    fn feature_section(&self) -> Result<&'a [u8]> {
+        if self.header.index_node_size() > 0 {
+            PackedRTree::validate_num_items(self.header.features_count() as usize)?;
+        }
+        self.index_begin
+            .checked_add(self.index_size())
+            .and_then(|begin| self.buf.get(begin..))
+            .ok_or(unexpected_eof("index"))
+    }
+
+    /// Select all features.
+    pub fn select_all(&self) -> Result<SliceFeatureIter<'a>> {
+        let features = self.feature_section()?;
+        Ok(SliceFeatureIter::new(
+            features,
+            self.verify,
+            self.header,
+            None,
+        ))
+    }
+
+    /// Select features within a bounding box.
+    ///
+    /// The index is streamed from the slice, reading only the nodes intersecting the bounding box.
+    pub fn select_bbox(
+        &self,
+        min_x: f64,
+        min_y: f64,
+        max_x: f64,
+        max_y: f64,
+    ) -> Result<SliceFeatureIter<'a>> {
+        let header = self.header;
+        if header.index_node_size() == 0 || header.features_count() == 0 {
+            return Err(Error::NoIndex);
+        }
+        let features = self.feature_section()?;
+        let index = &self.buf[self.index_begin..self.index_begin + self.index_size()];
+        let list = PackedRTree::stream_search(
+            &mut Cursor::new(index),
+            header.features_count() as usize,
+            header.index_node_size(),
+            min_x,
+            min_y,
+            max_x,
+            max_y,
+        )?;
+        Ok(SliceFeatureIter::new(
+            features,
+            self.verify,
+            header,
+            Some(list),
+        ))
+    }
+}
+
+impl<'a> SliceFeatureIter<'a> {
+    fn new(
+        buf: &'a [u8],
+        verify: bool,
+        header: Header<'a>,
+        item_filter: Option<Vec<packed_r_tree::SearchResultItem>>,
+    ) -> SliceFeatureIter<'a> {
+        let count = match &item_filter {
+            Some(list) => Some(list.len()),
+            None => {
+                let feat_count = header.features_count() as usize;
+                if feat_count > 0 {
+                    Some(feat_count)
+                } else if buf.is_empty() {
+                    Some(0)
+                } else {
+                    None
+                }
+            }
+        };
+        SliceFeatureIter {
+            buf,
+            verify,
+            header,
+            item_filter: item_filter.map(|list| list.into_iter()),
+            count,
+            feat_no: 0,
+            pos: 0,
+        }
+    }
+
+    /// Header information
+    pub fn header(&self) -> Header<'a> {
+        self.header
+    }
+
+    /// Number of selected features (None for undefined feature count)
+    pub fn features_count(&self) -> Option<usize> {
+        self.count
+    }
+
+    fn read_feature(&mut self) -> Result<FgbFeatureRef<'a>> {
+        let size_buf = self
+            .pos
+            .checked_add(4)
+            .and_then(|end| self.buf.get(self.pos..end))
+            .ok_or(unexpected_eof("feature size"))?;
+        let feature_size =
+            u32::from_le_bytes([size_buf[0], size_buf[1], size_buf[2], size_buf[3]]) as usize;
+        let end = (self.pos + 4)
+            .checked_add(feature_size)
+            .ok_or(unexpected_eof("feature"))?;
+        let feature_buf = self
+            .buf
+            .get(self.pos..end)
+            .ok_or(unexpected_eof("feature"))?;
+        let feature = if self.verify {
+            size_prefixed_root_as_feature(feature_buf)?
+        } else {
+            // SAFETY: caller opted out of verification, see `FgbSliceReader::open_unchecked`
+            unsafe { size_prefixed_root_as_feature_unchecked(feature_buf) }
+        };
+        self.pos = end;
+        self.feat_no += 1;
+        Ok(FgbFeatureRef {
+            header: self.header,
+            feature,
+        })
+    }
+}
+
+impl<'a> Iterator for SliceFeatureIter<'a> {
+    type Item = Result<FgbFeatureRef<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(filter) = &mut self.item_filter {
+            self.pos = filter.next()?.offset;
+        } else {
+            match self.count {
+                Some(count) if self.feat_no >= count => return None,
+                None if self.pos >= self.buf.len() => return None,
+                _ => {}
+            }
+        }
+        let feature = self.read_feature();
+        if feature.is_err() {
+            // Stop after the first error, the feature section can't be trusted anymore
+            self.count = Some(self.feat_no);
+            self.item_filter = None;
+        }
+        Some(feature)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if let Some(count) = self.count {
+            let remaining = count.saturating_sub(self.feat_no);
+            (remaining, Some(remaining))
+        } else {
+            (0, None)
+        }
+    }
+}
+
+fn unexpected_eof(section: &str) -> Error {
+    Error::IO(std::io::Error::new(
+        std::io::ErrorKind::UnexpectedEof,
+        format!("buffer too short for {section}"),
+    ))
+}
+
+mod geozero_api {
+    use crate::SliceFeatureIter;
+    use geozero::error::GeozeroError;
+    use geozero::{FeatureAccess, FeatureProcessor, GeozeroDatasource};
+
+    impl GeozeroDatasource for SliceFeatureIter<'_> {
+        /// Consume and process all selected features.
+        fn process<P: FeatureProcessor>(
+            &mut self,
+            processor: &mut P,
+        ) -> geozero::error::Result<()> {
+            self.process_features(processor)
+        }
+    }
+
+    impl SliceFeatureIter<'_> {
+        /// Read and process all selected features
+        pub fn process_features<W: FeatureProcessor>(
+            &mut self,
+            out: &mut W,
+        ) -> geozero::error::Result<()> {
+            out.dataset_begin(self.header.name())?;
+            for (cnt, feature) in self.by_ref().enumerate() {
+                let feature = feature.map_err(|e| GeozeroError::Feature(e.to_string()))?;
+                feature.process(out, cnt as u64)?;
+            }
+            out.dataset_end()
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use crate::error::{Error, Result};
+use crate::feature_writer::FeatureWriter;
+use crate::file_writer::{create_column, feature_writer, header_args};
+use crate::header_generated::{size_prefixed_root_as_header, ColumnType, GeometryType};
+use crate::{Column, ColumnArgs, FgbWriterOptions, Header, HeaderArgs, MAGIC_BYTES};
+use flatbuffers::FlatBufferBuilder;
+use std::io::{Seek, SeekFrom, Write};
+
+/// Streaming FlatGeobuf dataset writer
+///
+/// Writes an unindexed dataset directly to the output, without buffering features in a
+/// temporary file. The header is written together with the first feature, so columns must be
+/// declared before or within the first feature. The header has no envelope and a feature count
+/// of 0 (unknown), unless patched with [`FgbStreamWriter::finish_with_count`].
+///
+/// # Usage example:
+///
+/// ```
+/// use flatgeobuf::*;
+/// use geozero::geojson::GeoJsonReader;
+/// use geozero::GeozeroDatasource;
+/// # use std::fs::File;
+/// # use std::io::{BufReader, BufWriter};
+///
+/// # fn json_to_fgb() -> std::result::Result<(), Box<dyn std::error::Error>> {
+/// let stdout = BufWriter::new(std::io::stdout().lock());
+/// let mut fgb = FgbStreamWriter::create(stdout, "countries", GeometryType::MultiPolygon)?;
+/// let mut fin = BufReader::new(File::open("countries.geojson")?);
+/// let mut reader = GeoJsonReader(&mut fin);
+/// reader.process(&mut fgb)?;
+/// fgb.finish()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct FgbStreamWriter<'a, W: Write> {
+    out: W,
+    fbb: FlatBufferBuilder<'a>,
+    header_args: HeaderArgs<'a>,
+    columns: Vec<flatbuffers::WIPOffset<Column<'a>>>,
+    feat_writer: FeatureWriter<'a>,
+    /// Offset of the features count field, once the header is written
+    features_count_offset: Option<usize>,
+    /// Number of columns declared in the written header
+    header_columns: usize,
+    /// Number of bytes written to `out`
+    written: u64,
+}
+
+impl<'a, W: Write> FgbStreamWriter<'a, W> {
+    /// Configure a streaming writer with default options
+    pub fn create(out: W, name: &str, geometry_type: GeometryType) -> Result<Self> {
+        FgbStreamWriter::create_with_options(out, name, geometry_type, Default::default())
+    }
+    /// Configure a streaming writer
+    ///
+    /// `options.write_index` is ignored, streamed datasets are never indexed.
+    pub fn create_with_options(
+        out: W,
+        name: &str,
+        geometry_type: GeometryType,
+        options: FgbWriterOptions,
+    ) -> Result<Self> {
+        let mut fbb = FlatBufferBuilder::new();
+        let header_args = header_args(&mut fbb, name, geometry_type, &options, 0);
+        let feat_writer = feature_writer(&header_args, &options);
+
+        Ok(FgbStreamWriter {
+            out,
+            fbb,
+            header_args,
+            columns: Vec::new(),
+            feat_writer,
+            features_count_offset: None,
+            header_columns: 0,
+            written: 0,
+        })
+    }
+
+    /// Add a new column.
+    ///
+    /// Columns have to be added before the first feature is written.
+    /// See [`FgbWriter::add_column`](crate::FgbWriter::add_column).
+    pub fn add_column<F>(&mut self, name: &str, col_type: ColumnType, cfgfn: F)
+    where
+        F: FnOnce(&mut FlatBufferBuilder<'a>, &mut ColumnArgs),
+    {
+        let (column, def) = create_column(&mut self.fbb, name, col_type, cfgfn);
+        self.columns.push(column);
+        self.feat_writer.columns.push(def);
+    }
+
+    fn write_header(&mut self) -> Result<()> {
+        self.out.write_all(&MAGIC_BYTES)?;
+        self.header_args.columns = Some(self.fbb.create_vector(&self.columns));
+        self.header_columns = self.columns.len();
+        self.header_args.geometry_type = self.feat_writer.dataset_type;
+        // Keep the features count in the buffer, even though it has the default value 0
+        self.fbb.force_defaults(true);
+        let header = Header::create(&mut self.fbb, &self.header_args);
+        self.fbb.finish_size_prefixed(header, None);
+        let buf = self.fbb.finished_data();
+        let header = size_prefixed_root_as_header(buf)?;
+        let table = header._tab;
+        let field = table.vtable().get(Header::VT_FEATURES_COUNT) as usize;
+        let table_offset = table.buf().as_ptr() as usize - buf.as_ptr() as usize;
+        self.features_count_offset = Some(MAGIC_BYTES.len() + table_offset + table.loc() + field);
+        self.out.write_all(buf)?;
+        self.written = (MAGIC_BYTES.len() + buf.len()) as u64;
+        Ok(())
+    }
+
+    fn write_feature(&mut self) -> Result<()> {
+        if self.features_count_offset.is_none() {
+            self.write_header()?;
+        } else if self.columns.len() != self.header_columns {
+            self.feat_writer.discard_feature();
+            return Err(Error::IO(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "columns added after the header was written",
+            )));
+        }
+        self.feat_writer.check_properties()?;
+        let feat_buf = self.feat_writer.finish_to_feature();
+        self.out.write_all(&feat_buf)?;
+        self.written += feat_buf.len() as u64;
+        self.header_args.features_count += 1;
+        Ok(())
+    }
+
+    /// Number of features written
+    pub fn features_count(&self) -> u64 {
+        self.header_args.features_count
+    }
+
+    /// Write the header, if no feature was written, and flush the output.
+    pub fn finish(mut self) -> Result<W> {
+        if self.features_count_offset.is_none() {
+            self.write_header()?;
+        }
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+impl<W: Write + Seek> FgbStreamWriter<'_, W> {
+    /// Like [`FgbStreamWriter::finish`], but also patch the features count in the header.
+    ///
+    /// The output position is restored afterwards.
+    pub fn finish_with_count(self) -> Result<W> {
+        let features_count = self.header_args.features_count;
+        let written = self.written;
+        let Some(offset) = self.features_count_offset else {
+            // No feature written, the features count is already 0
+            return self.finish();
+        };
+        let mut out = self.finish()?;
+        let end = out.stream_position()?;
+        let start = end - written;
+        out.seek(SeekFrom::Start(start + offset as u64))?;
+        out.write_all(&features_count.to_le_bytes())?;
+        out.seek(SeekFrom::Start(end))?;
+        out.flush()?;
+        Ok(out)
+    }
+}
+
+mod geozero_api {
+    use crate::feature_writer::{prop_type, FeatureWriter};
+    use crate::file_writer::{delegate_geom_processor, feature_error, SchemaValidation};
+    use crate::FgbStreamWriter;
+    use geozero::{
+        error::Result, ColumnValue, FeatureProcessor, GeozeroDatasource, GeozeroGeometry,
+        PropertyProcessor,
+    };
+    use std::io::Write;
+
+    impl<W: Write> FgbStreamWriter<'_, W> {
+        /// Add a new feature.
+        ///
+        /// See [`FgbWriter::add_feature`](crate::FgbWriter::add_feature).
+        pub fn add_feature(&mut self, mut feature: impl GeozeroDatasource) -> Result<()> {
+            if let Err(e) = feature.process(&mut self.feat_writer) {
+                self.feat_writer.discard_feature();
+                return Err(e);
+            }
+            self.write_feature().map_err(feature_error)
+        }
+
+        /// Add a new feature from a `GeozeroGeometry`.
+        ///
+        /// See [`FgbWriter::add_feature_geom`](crate::FgbWriter::add_feature_geom).
+        pub fn add_feature_geom<F>(&mut self, geom: impl GeozeroGeometry, cfgfn: F) -> Result<()>
+        where
+            F: FnOnce(&mut FeatureWriter),
+        {
+            if let Err(e) = geom.process_geom(&mut self.feat_writer) {
+                self.feat_writer.discard_feature();
+                return Err(e);
+            }
+            cfgfn(&mut self.feat_writer);
+            self.write_feature().map_err(feature_error)
+        }
+    }
+
+    impl<W: Write> FeatureProcessor for FgbStreamWriter<'_, W> {
+        fn feature_end(&mut self, _idx: u64) -> Result<()> {
+            self.write_feature().map_err(feature_error)
+        }
+    }
+
+    impl<W: Write> PropertyProcessor for FgbStreamWriter<'_, W> {
+        fn property(&mut self, i: usize, colname: &str, colval: &ColumnValue) -> Result<bool> {
+            if !self.feat_writer.is_declared(i, colname) {
+                let new_column = i == self.columns.len()
+                    || self.feat_writer.validation == SchemaValidation::Lenient;
+                if new_column && self.features_count_offset.is_none() {
+                    info!(
+                    "Undefined property index {i}, column: `{colname}` - adding column declaration"
+                );
+                    self.add_column(colname, prop_type(colval), |_, _| {});
+                } else {
+                    info!("Undefined property index {i}, column: `{colname}` - skipping");
+                    return Ok(false);
+                }
+            }
+            self.feat_writer.property(i, colname, colval)
+        }
+    }
+
+    // Delegate GeomProcessor to self.feat_writer
+    delegate_geom_processor!([W: Write] FgbStreamWriter<'_, W>);
+}
@@ -0,0 +1,617 @@
+#![cfg(feature = "arrow")]
+
+use arrow_array::cast::AsArray;
+use arrow_array::types::{Float64Type, Int32Type, UInt64Type};
+use arrow_array::{Array, ArrayRef, Float64Array, RecordBatch, StringArray, StructArray};
+use arrow_buffer::NullBuffer;
+use arrow_schema::{DataType, Field, Fields, Schema};
+use flatgeobuf::*;
+use geozero::{ColumnValue, CoordDimensions, PropertyProcessor, ToWkb, ToWkt};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Cursor};
+use std::sync::Arc;
+
+#[test]
+fn countries_native() -> Result<()> {
+    let mut filein = BufReader::new(File::open("../../test/data/countries.fgb")?);
+    let batches = FgbReader::open(&mut filein)?
+        .select_all_seq()?
+        .record_batches(ArrowOptions {
+            batch_size: 50,
+            ..Default::default()
+        })?;
+    let schema = batches.schema();
+    assert_eq!(schema.fields().len(), 3);
+    assert_eq!(schema.field(1).name(), "name");
+    assert_eq!(schema.field(1).data_type(), &DataType::Utf8);
+    let geometry = schema.field(2);
+    assert_eq!(geometry.name(), "geometry");
+    assert_eq!(
+        geometry.metadata()["ARROW:extension:name"],
+        "geoarrow.multipolygon"
+    );
+    assert_eq!(
+        geometry.metadata()["ARROW:extension:metadata"],
+        r#"{"crs":"EPSG:4326","crs_type":"authority_code"}"#
+    );
+
+    let batches = batches.collect::<Result<Vec<_>>>()?;
+    let rows = batches.iter().map(|b| b.num_rows()).collect::<Vec<_>>();
+    assert_eq!(rows, vec![50, 50, 50, 29]);
+    assert!(batches.iter().all(|b| b.schema() == schema));
+
+    let batch = &batches[0];
+    assert_eq!(batch.column(1).as_string::<i32>().value(0), "Antarctica");
+    // First country: polygons -> rings -> vertices
+    let polygons = batch.column(2).as_list::<i32>().value(0);
+    let rings = polygons.as_list::<i32>().value(0);
+    let vertices = rings.as_list::<i32>().value(0);
+    let coords = vertices.as_fixed_size_list();
+    assert_eq!(coords.value_length(), 2);
+    assert_eq!(coords.len(), 13);
+    let xy = coords.value(0);
+    let xy = xy.as_primitive::<Float64Type>();
+    assert_eq!((xy.value(0), xy.value(1)), (-59.572095, -80.040179));
+    Ok(())
+}
+
+#[test]
+fn countries_wkb() -> Result<()> {
+    let mut filein = BufReader::new(File::open("../../test/data/countries.fgb")?);
+    let mut fgb = FgbReader::open(&mut filein)?.select_all()?;
+    let mut expected = Vec::new();
+    while let Some(feature) = fgb.next()? {
+        expected.push(feature.to_wkb(CoordDimensions::xy()).unwrap());
+    }
+
+    let mut filein = BufReader::new(File::open("../../test/data/countries.fgb")?);
+    let batches = FgbReader::open(&mut filein)?
+        .select_all()?
+        .record_batches(ArrowOptions {
+            geometry_encoding: GeometryEncoding::Wkb,
+            ..Default::default()
+        })?;
+    let schema = batches.schema();
+    assert_eq!(schema.field(2).data_type(), &DataType::Binary);
+    assert_eq!(
+        schema.field(2).metadata()["ARROW:extension:name"],
+        "geoarrow.wkb"
+    );
+    let batches = batches.collect::<Result<Vec<_>>>()?;
+    assert_eq!(batches.len(), 1);
+    let wkb = batches[0].column(2).as_binary::<i32>();
+    let wkb = wkb.iter().map(|v| v.unwrap().to_vec()).collect::<Vec<_>>();
+    assert_eq!(wkb, expected);
+    Ok(())
+}
+
+#[test]
+fn all_data_types() -> Result<()> {
+    let mut filein = BufReader::new(File::open("../../test/data/alldatatypes.fgb")?);
+    let batches = FgbReader::open(&mut filein)?
+        .select_all()?
+        .record_batches(ArrowOptions {
+            geometry_encoding: GeometryEncoding::Wkb,
+            ..Default::default()
+        })?;
+    let schema = batches.schema();
+    let types = schema
+        .fields()
+        .iter()
+        .map(|f| f.data_type().clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        types[..15],
+        [
+            DataType::Int8,
+            DataType::UInt8,
+            DataType::Boolean,
+            DataType::Int16,
+            DataType::UInt16,
+            DataType::Int32,
+            DataType::UInt32,
+            DataType::Int64,
+            DataType::UInt64,
+            DataType::Float32,
+            DataType::Float64,
+            DataType::Utf8,
+            DataType::Utf8,
+            DataType::Utf8,
+            DataType::Binary,
+        ]
+    );
+    assert_eq!(
+        schema.field(12).metadata()["ARROW:extension:name"],
+        "arrow.json"
+    );
+    let batches = batches.collect::<Result<Vec<_>>>()?;
+    let batch = &batches[0];
+    assert_eq!(batch.num_rows(), 1);
+    assert_eq!(
+        batch.column(8).as_primitive::<UInt64Type>().value(0),
+        u64::MAX
+    );
+    assert_eq!(
+        batch.column(13).as_string::<i32>().value(0),
+        "2020-02-29T12:34:56Z"
+    );
+    assert_eq!(batch.column(14).as_binary::<i32>().value(0), &[88]);
+    Ok(())
+}
+
+#[test]
+fn projected_columns() -> Result<()> {
+    let mut filein = BufReader::new(File::open("../../test/data/alldatatypes.fgb")?);
+    let batches = FgbReader::open(&mut filein)?
+        .select_all()?
+        .columns(&["binary", "int"])?
+        .record_batches(ArrowOptions {
+            geometry_encoding: GeometryEncoding::Wkb,
+            ..Default::default()
+        })?;
+    let schema = batches.schema();
+    let names = schema
+        .fields()
+        .iter()
+        .map(|f| f.name().as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["binary", "int", "geometry"]);
+    let batches = batches.collect::<Result<Vec<_>>>()?;
+    assert_eq!(batches[0].column(0).as_binary::<i32>().value(0), &[88]);
+    assert_eq!(
+        batches[0].column(1).as_primitive::<Int32Type>().value(0),
+        -1
+    );
+    Ok(())
+}
+
+#[test]
+fn missing_properties_are_null() -> Result<()> {
+    let mut fgb = FgbWriter::create_with_options(
+        "points",
+        GeometryType::Point,
+        FgbWriterOptions {
+            write_index: false,
+            ..Default::default()
+        },
+    )?;
+    fgb.add_column("pop", ColumnType::Int, |_, col| col.nullable = true);
+    fgb.add_feature_geom(
+        geo_types::Geometry::from(geo_types::Point::new(1.0, 2.0)),
+        |feat| {
+            feat.property(0, "pop", &ColumnValue::Int(10)).unwrap();
+        },
+    )
+    .ok();
+    fgb.add_feature_geom(
+        geo_types::Geometry::from(geo_types::Point::new(3.0, 4.0)),
+        |_| {},
+    )
+    .ok();
+    let mut buf = Vec::new();
+    fgb.write(&mut buf)?;
+
+    let batches = FgbReader::open(Cursor::new(buf))?
+        .select_all()?
+        .record_batches(ArrowOptions::default())?;
+    let schema = batches.schema();
+    assert!(schema.field(0).is_nullable());
+    assert_eq!(
+        schema.field(1).metadata()["ARROW:extension:name"],
+        "geoarrow.point"
+    );
+    let batches = batches.collect::<Result<Vec<_>>>()?;
+    let pop = batches[0].column(0).as_primitive::<Int32Type>();
+    assert_eq!(pop.iter().collect::<Vec<_>>(), vec![Some(10), None]);
+    let points = batches[0].column(1).as_fixed_size_list();
+    let coords = points.values().as_primitive::<Float64Type>();
+    assert_eq!(coords.values().to_vec(), vec![1.0, 2.0, 3.0, 4.0]);
+    Ok(())
+}
+
+#[test]
+fn missing_values_of_non_nullable_column() -> Result<()> {
+    let points = |values: &[ColumnValue]| -> Result<Vec<u8>> {
+        let mut fgb = FgbWriter::create_with_options(
+            "points",
+            GeometryType::Point,
+            FgbWriterOptions {
+                write_index: false,
+                feature_columns: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        fgb.add_column("pop", ColumnType::Int, |_, col| col.nullable = false);
+        for value in values {
+            fgb.add_feature_geom(
+                geo_types::Geometry::from(geo_types::Point::new(1.0, 2.0)),
+                |feat| {
+                    feat.property(0, "pop", value).unwrap();
+                },
+            )
+            .unwrap();
+        }
+        // Missing values of non-nullable columns are rejected by the writer
+        assert!(fgb
+            .add_feature_geom(
+                geo_types::Geometry::from(geo_types::Point::new(3.0, 4.0)),
+                |feat| {
+                    feat.property(0, "name", &ColumnValue::String("x")).unwrap();
+                },
+            )
+            .is_err());
+        let mut buf = Vec::new();
+        fgb.write(&mut buf)?;
+        Ok(buf)
+    };
+
+    let buf = points(&[ColumnValue::Int(10), ColumnValue::Int(20)])?;
+    let fgb = FgbReader::open(Cursor::new(buf))?;
+    assert!(!fgb.header().columns().unwrap().get(0).nullable());
+    let batches = fgb.select_all()?.record_batches(ArrowOptions::default())?;
+    assert!(!batches.schema().field(0).is_nullable());
+    let batches = batches.collect::<Result<Vec<_>>>()?;
+    let pop = batches[0].column(0).as_primitive::<Int32Type>();
+    assert_eq!(pop.iter().collect::<Vec<_>>(), vec![Some(10), Some(20)]);
+
+    // Without schema validation, a value not convertible to the column type is written with
+    // its own type and fails the batch
+    let buf = points(&[ColumnValue::Int(10), ColumnValue::String("many")])?;
+    let fgb = FgbReader::open(Cursor::new(buf))?;
+    let mut batches = fgb.select_all()?.record_batches(ArrowOptions::default())?;
+    assert!(matches!(batches.next(), Some(Err(Error::Arrow(_)))));
+    Ok(())
+}
+
+#[test]
+fn mixed_geometry_types() -> Result<()> {
+    let mut filein = BufReader::new(File::open("../../test/data/heterogeneous.fgb")?);
+    let fgb = FgbReader::open(&mut filein)?.select_all()?;
+    assert!(matches!(
+        fgb.record_batches(ArrowOptions::default()),
+        Err(Error::UnsupportedGeometryType(_))
+    ));
+
+    let mut filein = BufReader::new(File::open("../../test/data/heterogeneous.fgb")?);
+    let batches = FgbReader::open(&mut filein)?
+        .select_all()?
+        .record_batches(ArrowOptions {
+            geometry_encoding: GeometryEncoding::Wkb,
+            ..Default::default()
+        })?
+        .collect::<Result<Vec<_>>>()?;
+    assert!(batches[0].num_rows() > 0);
+    Ok(())
+}
+
+fn features_wkt(buf: &[u8]) -> Result<Vec<(String, String)>> {
+    let mut fgb = FgbReader::open(Cursor::new(buf))?.select_all()?;
+    let mut features = Vec::new();
+    while let Some(feature) = fgb.next()? {
+        let props = feature.properties().unwrap();
+        let mut props = props.into_iter().collect::<Vec<_>>();
+        props.sort();
+        features.push((format!("{props:?}"), feature.to_wkt().unwrap()));
+    }
+    features.sort();
+    Ok(features)
+}
+
+#[test]
+fn write_record_batches() -> Result<()> {
+    let original = std::fs::read("../../test/data/countries.fgb")?;
+    for geometry_encoding in [GeometryEncoding::Native, GeometryEncoding::Wkb] {
+        let batches = FgbReader::open(Cursor::new(&original))?
+            .select_all()?
+            .record_batches(ArrowOptions {
+                batch_size: 50,
+                geometry_encoding,
+            })?;
+        let mut fgb = FgbWriter::create("countries", GeometryType::MultiPolygon)?;
+        for batch in batches {
+            fgb.add_record_batch(&batch?)?;
+        }
+        let mut buf = Vec::new();
+        fgb.write(&mut buf)?;
+
+        let reader = FgbReader::open(Cursor::new(&buf))?;
+        let header = reader.header();
+        assert_eq!(header.features_count(), 179);
+        assert_eq!(header.columns().unwrap().len(), 2);
+        assert_eq!(features_wkt(&buf)?, features_wkt(&original)?);
+    }
+    Ok(())
+}
+
+#[test]
+fn write_record_batches_all_data_types() -> Result<()> {
+    let original = std::fs::read("../../test/data/alldatatypes.fgb")?;
+    let batches = FgbReader::open(Cursor::new(&original))?
+        .select_all()?
+        .record_batches(ArrowOptions {
+            geometry_encoding: GeometryEncoding::Wkb,
+            ..Default::default()
+        })?;
+    let mut fgb = FgbWriter::create("alldatatypes", GeometryType::Unknown)?;
+    for batch in batches {
+        fgb.add_record_batch(&batch?)?;
+    }
+    let mut buf = Vec::new();
+    fgb.write(&mut buf)?;
+
+    let reader = FgbReader::open(Cursor::new(&buf))?;
+    let types = reader
+        .header()
+        .columns()
+        .unwrap()
+        .iter()
+        .map(|col| col.type_())
+        .collect::<Vec<_>>();
+    let original_reader = FgbReader::open(Cursor::new(&original))?;
+    let expected = original_reader
+        .header()
+        .columns()
+        .unwrap()
+        .iter()
+        .map(|col| match col.type_() {
+            // Date time values are exported as strings
+            ColumnType::DateTime => ColumnType::String,
+            col_type => col_type,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(types, expected);
+    assert_eq!(features_wkt(&buf)?, features_wkt(&original)?);
+    Ok(())
+}
+
+#[test]
+fn write_separated_points_with_nulls() -> Result<()> {
+    let coords = Fields::from(vec![
+        Field::new("x", DataType::Float64, false),
+        Field::new("y", DataType::Float64, false),
+    ]);
+    let points = StructArray::new(
+        coords.clone(),
+        vec![
+            Arc::new(Float64Array::from(vec![1.0, 3.0, 0.0])) as ArrayRef,
+            Arc::new(Float64Array::from(vec![2.0, 4.0, 0.0])),
+        ],
+        Some(NullBuffer::from(vec![true, true, false])),
+    );
+    let schema = Schema::new(vec![
+        Field::new("name", DataType::Utf8, true),
+        Field::new("geom", DataType::Struct(coords), true).with_metadata(HashMap::from([(
+            "ARROW:extension:name".to_string(),
+            "geoarrow.point".to_string(),
+        )])),
+    ]);
+    let batch = RecordBatch::try_new(
+        Arc::new(schema),
+        vec![
+            Arc::new(StringArray::from(vec![Some("a"), None, Some("c")])),
+            Arc::new(points),
+        ],
+    )?;
+
+    let mut fgb = FgbWriter::create_with_options(
+        "points",
+        GeometryType::Point,
+        FgbWriterOptions {
+            write_index: false,
+            ..Default::default()
+        },
+    )?;
+    fgb.add_record_batch(&batch)?;
+    let mut buf = Vec::new();
+    fgb.write(&mut buf)?;
+
+    let mut fgb = FgbReader::open(Cursor::new(buf))?.select_all()?;
+    assert!(fgb.header().columns().unwrap().get(0).nullable());
+    let feature = fgb.next()?.unwrap();
+    assert_eq!(
+        feature.property::<String>("name").ok(),
+        Some("a".to_string())
+    );
+    assert_eq!(feature.to_wkt().unwrap(), "POINT(1 2)");
+    let feature = fgb.next()?.unwrap();
+    assert!(feature.property::<String>("name").is_err());
+    assert_eq!(feature.to_wkt().unwrap(), "POINT(3 4)");
+    let feature = fgb.next()?.unwrap();
+    assert_eq!(
+        feature.property::<String>("name").ok(),
+        Some("c".to_string())
+    );
+    assert!(feature.geometry().is_none());
+    assert!(fgb.next()?.is_none());
+    Ok(())
+}
+
+#[test]
+fn write_record_batch_without_geometry() -> Result<()> {
+    let schema = Schema::new(vec![Field::new("name", DataType::Utf8, true)]);
+    let batch = RecordBatch::try_new(
+        Arc::new(schema),
+        vec![Arc::new(StringArray::from(vec!["a"]))],
+    )?;
+    let mut fgb = FgbWriter::create("names", GeometryType::Point)?;
+    assert!(matches!(fgb.add_record_batch(&batch), Err(Error::Arrow(_))));
+    Ok(())
+}
+
+/// Batch of GeoArrow points with separated ordinates and a property field
+fn point_batch(ordinates: &[(&str, Vec<f64>)], property: Field, values: ArrayRef) -> RecordBatch {
+    let coords = ordinates
+        .iter()
+        .map(|(name, _)| Field::new(*name, DataType::Float64, false))
+        .collect::<Fields>();
+    let points = StructArray::new(
+        coords.clone(),
+        ordinates
+            .iter()
+            .map(|(_, values)| Arc::new(Float64Array::from(values.clone())) as ArrayRef)
+            .collect(),
+        None,
+    );
+    let schema = Schema::new(vec![
+        property,
+        Field::new("geom", DataType::Struct(coords), true).with_metadata(HashMap::from([(
+            "ARROW:extension:name".to_string(),
+            "geoarrow.point".to_string(),
+        )])),
+    ]);
+    RecordBatch::try_new(Arc::new(schema), vec![values, Arc::new(points)]).unwrap()
+}
+
+#[test]
+fn write_record_batches_with_changed_column_type() -> Result<()> {
+    use arrow_array::{Int32Array, Int64Array};
+
+    let xy = |n: usize| [("x", vec![1.0; n]), ("y", vec![2.0; n])];
+    let mut fgb = FgbWriter::create("points", GeometryType::Point)?;
+    fgb.add_record_batch(&point_batch(
+        &xy(2),
+        Field::new("value", DataType::Int32, true),
+        Arc::new(Int32Array::from(vec![1, 2])),
+    ))?;
+    // Converted to the column type
+    fgb.add_record_batch(&point_batch(
+        &xy(1),
+        Field::new("value", DataType::Int64, true),
+        Arc::new(Int64Array::from(vec![3])),
+    ))?;
+    assert!(matches!(
+        fgb.add_record_batch(&point_batch(
+            &xy(1),
+            Field::new("value", DataType::Int64, true),
+            Arc::new(Int64Array::from(vec![i64::MAX])),
+        )),
+        Err(Error::Schema(SchemaError::ColumnType {
+            expected: ColumnType::Int,
+            found: ColumnType::Long,
+            ..
+        }))
+    ));
+    assert!(matches!(
+        fgb.add_record_batch(&point_batch(
+            &xy(1),
+            Field::new("value", DataType::Utf8, true),
+            Arc::new(StringArray::from(vec!["4"])),
+        )),
+        Err(Error::Schema(SchemaError::ColumnType { .. }))
+    ));
+    assert!(matches!(
+        fgb.add_record_batch(&point_batch(
+            &xy(1),
+            Field::new("other", DataType::Int32, true),
+            Arc::new(Int32Array::from(vec![5])),
+        )),
+        Err(Error::Schema(SchemaError::ColumnName { .. }))
+    ));
+    let mut buf = Vec::new();
+    fgb.write(&mut buf)?;
+
+    let mut fgb = FgbReader::open(Cursor::new(buf))?.select_all()?;
+    let mut values = Vec::new();
+    while let Some(feature) = fgb.next()? {
+        values.push(feature.property::<i32>("value").unwrap());
+    }
+    assert_eq!(values, vec![1, 2, 3]);
+    Ok(())
+}
+
+#[test]
+fn write_record_batch_discards_failed_row() -> Result<()> {
+    use arrow_array::{BinaryArray, Int32Array};
+
+    let wkb_batch = |wkbs: Vec<&[u8]>, values: Vec<i32>| {
+        let schema = Schema::new(vec![
+            Field::new("value", DataType::Int32, true),
+            Field::new("geometry", DataType::Binary, true),
+        ]);
+        RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(Int32Array::from(values)),
+                Arc::new(BinaryArray::from(wkbs)),
+            ],
+        )
+        .unwrap()
+    };
+    let wkb = |wkt: &str| {
+        geozero::wkt::Wkt(wkt)
+            .to_wkb(CoordDimensions::xy())
+            .unwrap()
+    };
+    let point = wkb("GEOMETRYCOLLECTION(POINT(1 2))");
+    // Truncated in the second point of the collection
+    let collection = wkb("GEOMETRYCOLLECTION(POINT(5 6),POINT(9 9))");
+    let broken = &collection[..collection.len() - 4];
+    let next = wkb("GEOMETRYCOLLECTION(POINT(7 8))");
+
+    let mut fgb = FgbWriter::create("points", GeometryType::Unknown)?;
+    assert!(fgb
+        .add_record_batch(&wkb_batch(vec![&point, broken], vec![1, 2]))
+        .is_err());
+    fgb.add_record_batch(&wkb_batch(vec![&next], vec![3]))?;
+    let mut buf = Vec::new();
+    fgb.write(&mut buf)?;
+
+    let fgb = FgbReader::open(Cursor::new(&buf))?;
+    let envelope = fgb.header().envelope().unwrap().iter().collect::<Vec<_>>();
+    assert_eq!(envelope, vec![1.0, 2.0, 7.0, 8.0]);
+    let mut fgb = fgb.select_all()?;
+    let mut features = Vec::new();
+    while let Some(feature) = fgb.next()? {
+        features.push((
+            feature.to_wkt().unwrap(),
+            feature.property_opt::<i32>("value").unwrap(),
+        ));
+    }
+    features.sort_by_key(|f| f.1);
+    assert_eq!(
+        features,
+        vec![
+            ("GEOMETRYCOLLECTION(POINT(1 2))".to_string(), Some(1)),
+            ("GEOMETRYCOLLECTION(POINT(7 8))".to_string(), Some(3)),
+        ]
+    );
+    // No feature near the failed collection
+    let fgb = FgbReader::open(Cursor::new(&buf))?.select_bbox(4.5, 5.5, 5.5, 6.5)?;
+    assert_eq!(fgb.features_count(), Some(0));
+    Ok(())
+}
+
+#[test]
+fn write_record_batch_dimensions() -> Result<()> {
+    let batch = point_batch(
+        &[("x", vec![1.0]), ("y", vec![2.0]), ("z", vec![3.0])],
+        Field::new("name", DataType::Utf8, true),
+        Arc::new(StringArray::from(vec!["a"])),
+    );
+    for has_z in [false, true] {
+        let mut fgb = FgbWriter::create_with_options(
+            "points",
+            GeometryType::Point,
+            FgbWriterOptions {
+                has_z,
+                ..Default::default()
+            },
+        )?;
+        fgb.add_record_batch(&batch)?;
+        let mut buf = Vec::new();
+        fgb.write(&mut buf)?;
+
+        let mut fgb = FgbReader::open(Cursor::new(buf))?.select_all()?;
+        assert_eq!(fgb.header().has_z(), has_z);
+        let feature = fgb.next()?.unwrap();
+        let z = feature
+            .geometry()
+            .unwrap()
+            .z()
+            .map(|z| z.iter().collect::<Vec<_>>());
+        assert_eq!(z, has_z.then(|| vec![3.0]));
+    }
+    Ok(())
+}
@@ -0,0 +1,190 @@
+#[cfg(feature = "tokio")]
+mod async_read {
+
+    use flatgeobuf::*;
+    use std::fs::File;
+    use std::io::BufReader;
+    use tokio::io::BufReader as AsyncBufReader;
+
+    type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+    const COUNTRIES: &str = "../../test/data/countries.fgb";
+
+    async fn open(path: &str) -> Result<AsyncFgbReader<AsyncBufReader<tokio::fs::File>>> {
+        let file = tokio::fs::File::open(path).await?;
+        Ok(AsyncFgbReader::open(AsyncBufReader::new(file)).await?)
+    }
+
+    async fn feature_names(
+        mut fgb: AsyncFgbFeatureIter<AsyncBufReader<tokio::fs::File>>,
+    ) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        while let Some(feature) = fgb.next().await? {
+            names.push(feature.property::<String>("name")?);
+        }
+        Ok(names)
+    }
+
+    fn sync_names(bbox: Option<(f64, f64, f64, f64)>) -> Result<Vec<String>> {
+        let mut filein = BufReader::new(File::open(COUNTRIES)?);
+        let fgb = FgbReader::open(&mut filein)?;
+        let mut fgb = match bbox {
+            Some((min_x, min_y, max_x, max_y)) => fgb.select_bbox(min_x, min_y, max_x, max_y)?,
+            None => fgb.select_all()?,
+        };
+        let mut names = Vec::new();
+        while let Some(feature) = fgb.next()? {
+            names.push(feature.property::<String>("name")?);
+        }
+        Ok(names)
+    }
+
+    #[tokio::test]
+    async fn async_read_all() -> Result<()> {
+        let fgb = open(COUNTRIES).await?;
+        assert_eq!(fgb.header().geometry_type(), GeometryType::MultiPolygon);
+        assert_eq!(fgb.header().features_count(), 179);
+        let fgb = fgb.select_all().await?;
+        assert_eq!(fgb.features_count(), Some(179));
+        let names = feature_names(fgb).await?;
+        assert_eq!(names.len(), 179);
+        assert_eq!(names, sync_names(None)?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn async_read_bbox() -> Result<()> {
+        let bbox = (8.8, 47.2, 9.5, 55.3);
+        let fgb = open(COUNTRIES)
+            .await?
+            .select_bbox(bbox.0, bbox.1, bbox.2, bbox.3)
+            .await?;
+        assert_eq!(fgb.features_count(), Some(6));
+        assert_eq!(feature_names(fgb).await?, sync_names(Some(bbox))?);
+
+        // Last feature in file
+        let bbox = (-61.2, -51.85, -60.0, -51.25);
+        let fgb = open(COUNTRIES)
+            .await?
+            .select_bbox(bbox.0, bbox.1, bbox.2, bbox.3)
+            .await?;
+        let names = feature_names(fgb).await?;
+        assert_eq!(names.len(), 2);
+        assert_eq!(names, sync_names(Some(bbox))?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn async_read_bbox_with_index() -> Result<()> {
+        use geozero::geojson::GeoJsonReader;
+        use geozero::GeozeroDatasource;
+
+        let mut fgb = FgbWriter::create_with_options(
+            "countries",
+            GeometryType::MultiPolygon,
+            FgbWriterOptions {
+                write_index: false,
+                ..Default::default()
+            },
+        )?;
+        let mut fin = BufReader::new(File::open("../../test/data/countries.geojson")?);
+        GeoJsonReader(&mut fin).process(&mut fgb)?;
+        let mut data = Vec::new();
+        fgb.write(&mut data)?;
+
+        let bbox = (8.8, 47.2, 9.5, 55.3);
+        let fgb = AsyncFgbReader::open(std::io::Cursor::new(&data)).await?;
+        assert!(matches!(
+            fgb.select_bbox(bbox.0, bbox.1, bbox.2, bbox.3).await,
+            Err(Error::NoIndex)
+        ));
+
+        let index = FgbIndex::build(&data[..], 16)?;
+        let mut fgb = AsyncFgbReader::open(std::io::Cursor::new(&data))
+            .await?
+            .with_index(index)?
+            .select_bbox(bbox.0, bbox.1, bbox.2, bbox.3)
+            .await?;
+        assert_eq!(fgb.features_count(), Some(6));
+        let mut names = Vec::new();
+        while let Some(feature) = fgb.next().await? {
+            names.push(feature.property::<String>("name")?);
+        }
+        let mut expected = sync_names(Some(bbox))?;
+        expected.sort();
+        names.sort();
+        assert_eq!(names, expected);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn async_read_filtered() -> Result<()> {
+        let fgb = open(COUNTRIES)
+            .await?
+            .select_bbox(8.8, 47.2, 9.5, 55.3)
+            .await?
+            .filter_properties(!Expr::eq("name", "France"))?
+            .columns(&["name"])?;
+        assert_eq!(fgb.features_count(), None);
+        let names = feature_names(fgb).await?;
+        assert!(!names.is_empty());
+        assert!(!names.contains(&"France".to_string()));
+
+        let mut fgb = open(COUNTRIES).await?.select_all().await?;
+        let mut json = Vec::new();
+        fgb.process_features(&mut geozero::geojson::GeoJsonWriter::new(&mut json))
+            .await?;
+        assert!(String::from_utf8(json)?.contains(r#""name": "Antarctica""#));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn async_read_bbox_node_size_one() -> Result<()> {
+        let fgb = open("../../test/data/node_size_one.fgb").await?;
+        assert_eq!(fgb.header().index_node_size(), 1);
+        let mut fgb = fgb.select_bbox(1.5, 1.5, 3.5, 3.5).await?;
+        assert_eq!(fgb.features_count(), Some(2));
+        let mut count = 0;
+        while fgb.next().await?.is_some() {
+            count += 1;
+        }
+        assert_eq!(count, 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn async_read_without_index() -> Result<()> {
+        let mut fgb = FgbWriter::create_with_options(
+            "points",
+            GeometryType::Point,
+            FgbWriterOptions {
+                write_index: false,
+                ..Default::default()
+            },
+        )?;
+        for x in 0..3 {
+            let json = format!(r#"{{"type": "Point", "coordinates": [{x}, 1]}}"#);
+            fgb.add_feature_geom(geozero::geojson::GeoJson(&json), |_| {})?;
+        }
+        let file = tempfile::NamedTempFile::new()?;
+        fgb.write(&mut std::io::BufWriter::new(file.reopen()?))?;
+        let path = file.path().to_str().unwrap();
+
+        let fgb = open(path).await?;
+        assert_eq!(fgb.header().index_node_size(), 0);
+        let mut fgb = fgb.select_all().await?;
+        let mut count = 0;
+        while let Some(feature) = fgb.next().await? {
+            assert!(feature.geometry().is_some());
+            count += 1;
+        }
+        assert_eq!(count, 3);
+
+        let fgb = open(path).await?;
+        assert!(matches!(
+            fgb.select_bbox(0.0, 0.0, 1.0, 1.0).await,
+            Err(Error::NoIndex)
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,117 @@
+#[cfg(feature = "rayon")]
+mod par_read {
+
+    use flatgeobuf::*;
+    use std::fs::File;
+    use std::io::BufReader;
+
+    type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+    const COUNTRIES: &str = "../../test/data/countries.fgb";
+
+    fn open() -> Result<FgbReader<BufReader<File>>> {
+        Ok(FgbReader::open(BufReader::new(File::open(COUNTRIES)?))?)
+    }
+
+    fn sync_names(bbox: Option<(f64, f64, f64, f64)>) -> Result<Vec<String>> {
+        let fgb = open()?;
+        let mut fgb = match bbox {
+            Some((min_x, min_y, max_x, max_y)) => fgb.select_bbox(min_x, min_y, max_x, max_y)?,
+            None => fgb.select_all()?,
+        };
+        let mut names = Vec::new();
+        while let Some(feature) = fgb.next()? {
+            names.push(feature.property::<String>("name")?);
+        }
+        Ok(names)
+    }
+
+    fn name(feature: &OwnedFeature) -> String {
+        feature.property::<String>("name").unwrap()
+    }
+
+    #[test]
+    fn par_read_all() -> Result<()> {
+        let fgb = open()?.par_select_all()?;
+        assert_eq!(fgb.features_count(), Some(179));
+        // Small chunks to spread the features over many tasks
+        let names = fgb
+            .chunk_size(4096)
+            .map(name)
+            .collect::<flatgeobuf::Result<Vec<_>>>()?;
+        assert_eq!(names, sync_names(None)?);
+
+        let mut names = open()?
+            .par_select_all()?
+            .chunk_size(4096)
+            .unordered()
+            .map(name)
+            .collect::<flatgeobuf::Result<Vec<_>>>()?;
+        let mut expected = sync_names(None)?;
+        names.sort();
+        expected.sort();
+        assert_eq!(names, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn par_read_in_thread_pool() -> Result<()> {
+        // Iterating on the only worker thread of the pool must not block its tasks
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(1).build()?;
+        for ordered in [true, false] {
+            let fgb = open()?.par_select_all()?.chunk_size(4096);
+            let fgb = if ordered { fgb } else { fgb.unordered() };
+            let mut names =
+                pool.install(|| fgb.map(name).collect::<flatgeobuf::Result<Vec<_>>>())?;
+            let mut expected = sync_names(None)?;
+            if !ordered {
+                names.sort();
+                expected.sort();
+            }
+            assert_eq!(names, expected);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn par_read_bbox() -> Result<()> {
+        let bbox = (8.8, 47.2, 9.5, 55.3);
+        let names = open()?
+            .par_select_bbox(bbox.0, bbox.1, bbox.2, bbox.3)?
+            .map(name)
+            .collect::<flatgeobuf::Result<Vec<_>>>()?;
+        assert_eq!(names.len(), 6);
+        assert_eq!(names, sync_names(Some(bbox))?);
+        Ok(())
+    }
+
+    #[test]
+    fn par_read_filtered() -> Result<()> {
+        let fgb = open()?
+            .par_select_all()?
+            .filter_properties(Expr::like("name", "G%"))?
+            .columns(&["name"])?;
+        assert_eq!(fgb.features_count(), None);
+        let features = fgb
+            .chunk_size(1)
+            .map(|feature| (name(feature), feature.property_opt::<String>("iso_a3")))
+            .collect::<flatgeobuf::Result<Vec<_>>>()?;
+        let names = features.iter().map(|(name, _)| name).collect::<Vec<_>>();
+        assert_eq!(&names[..3], ["Georgia", "Germany", "Greece"]);
+        assert!(features
+            .iter()
+            .all(|(_, iso_a3)| matches!(iso_a3, Err(Error::UnknownColumn(_)))));
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "feature panic")]
+    fn par_read_panic() {
+        let _ = open()
+            .unwrap()
+            .par_select_all()
+            .unwrap()
+            .map(|_| -> () { panic!("feature panic") })
+            .collect::<Vec<_>>();
+    }
+}
@@ -154,6 +154,376 @@ fn read_bbox_including_first_feature() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn read_bbox_many() -> Result<()> {
+    let bboxes = [
+        NodeItem::bounds(8.8, 47.2, 9.5, 55.3),
+        NodeItem::bounds(-80.0, -70.0, -70.0, -50.0),
+        NodeItem::bounds(5.0, 45.0, 10.0, 50.0),
+        NodeItem::bounds(500.0, 500.0, 510.0, 510.0),
+    ];
+    let mut filein = BufReader::new(File::open("../../test/data/countries.fgb")?);
+    let selection = FgbReader::open(&mut filein)?.select_bbox_many(&bboxes)?;
+    assert_eq!(selection.len(), 4);
+    for (n, bbox) in bboxes.iter().enumerate() {
+        let mut filein = BufReader::new(File::open("../../test/data/countries.fgb")?);
+        let expected = feature_names(
+            FgbReader::open(&mut filein)?
+                .select_bbox(bbox.min_x, bbox.min_y, bbox.max_x, bbox.max_y)?,
+        )?;
+        let names = selection
+            .group(n)
+            .map(|feature| feature.property::<String>("name"))
+            .collect::<geozero::error::Result<Vec<_>>>()?;
+        assert_eq!(names, expected);
+    }
+    assert_eq!(selection.group(0).len(), 6);
+    assert_eq!(selection.group(3).len(), 0);
+
+    // Features within overlapping boxes are read once
+    let total = (0..3).map(|n| selection.group(n).len()).sum::<usize>();
+    assert!(selection.features().len() < total);
+    let germany = selection
+        .features()
+        .iter()
+        .position(|feature| feature.property::<String>("name").unwrap() == "Germany")
+        .unwrap();
+    assert!(selection.group_indices(0).contains(&germany));
+    assert!(selection.group_indices(2).contains(&germany));
+
+    let mut filein = BufReader::new(File::open("../../test/data/countries.fgb")?);
+    let selection = FgbReader::open(&mut filein)?.select_bbox_many(&[])?;
+    assert!(selection.is_empty());
+    assert!(selection.features().is_empty());
+    Ok(())
+}
+
+fn feature_names<R: Read + Seek>(mut fgb: FeatureIter<R, Seekable>) -> Result<Vec<String>> {
+    let mut names = Vec::new();
+    while let Some(feature) = fgb.next()? {
+        names.push(feature.property::<String>("name")?);
+    }
+    Ok(names)
+}
+
+#[test]
+fn read_intersecting() -> Result<()> {
+    let line = geo_types::LineString::from(vec![(6.0, 47.5), (10.0, 47.5)]);
+    let mut filein = BufReader::new(File::open("../../test/data/countries.fgb")?);
+    let fgb = FgbReader::open(&mut filein)?.select_intersecting(&line)?;
+    assert_eq!(fgb.features_count(), None);
+    assert_eq!(
+        feature_names(fgb)?,
+        vec!["Austria", "Switzerland", "France"]
+    );
+
+    // The bbox selection contains additional candidates
+    let mut filein = BufReader::new(File::open("../../test/data/countries.fgb")?);
+    let fgb = FgbReader::open(&mut filein)?.select_bbox(6.0, 47.5, 10.0, 47.5)?;
+    assert_eq!(fgb.features_count(), Some(5));
+    Ok(())
+}
+
+#[test]
+fn read_within() -> Result<()> {
+    let rect = geo_types::Rect::new((2.0, 49.0), (8.0, 54.0));
+    let mut filein = BufReader::new(File::open("../../test/data/countries.fgb")?);
+    let fgb = FgbReader::open(&mut filein)?.select_within(&rect.to_polygon())?;
+    assert_eq!(
+        feature_names(fgb)?,
+        vec!["Netherlands", "Luxembourg", "Belgium"]
+    );
+
+    let mut filein = BufReader::new(File::open("../../test/data/countries.fgb")?);
+    let point = geo_types::Point::new(8.5, 47.4);
+    let fgb = FgbReader::open(&mut filein)?.select_within(&point);
+    assert!(matches!(fgb.err(), Some(Error::UnsupportedGeometryType(_))));
+    Ok(())
+}
+
+#[test]
+fn read_containing() -> Result<()> {
+    let point = geo_types::Point::new(8.5, 47.4);
+    let mut filein = BufReader::new(File::open("../../test/data/countries.fgb")?);
+    let fgb = FgbReader::open(&mut filein)?.select_containing(&point)?;
+    assert_eq!(feature_names(fgb)?, vec!["Switzerland"]);
+
+    // Point in the ocean
+    let point = geo_types::Point::new(-30.0, 30.0);
+    let mut filein = BufReader::new(File::open("../../test/data/countries.fgb")?);
+    let fgb = FgbReader::open(&mut filein)?.select_containing(&point)?;
+    assert!(feature_names(fgb)?.is_empty());
+
+    // Empty point
+    let point = geo_types::Point::new(f64::NAN, f64::NAN);
+    let mut filein = BufReader::new(File::open("../../test/data/countries.fgb")?);
+    assert!(FgbReader::open(&mut filein)?
+        .select_containing(&point)
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn read_filter_properties() -> Result<()> {
+    let mut filein = BufReader::new(File::open("../../test/data/countries.fgb")?);
+    let mut fgb = FgbReader::open(&mut filein)?.select_all()?;
+    let mut expected = Vec::new();
+    while let Some(feature) = fgb.next()? {
+        let name = feature.property::<String>("name")?;
+        if name.starts_with('G') && name != "Germany" {
+            expected.push(name);
+        }
+    }
+    assert!(expected.len() > 5);
+
+    let filter = Expr::like("name", "G%").and(Expr::ne("name", "Germany"));
+    let mut filein = BufReader::new(File::open("../../test/data/countries.fgb")?);
+    let fgb = FgbReader::open(&mut filein)?
+        .select_all()?
+        .filter_properties(filter.clone())?;
+    assert_eq!(fgb.features_count(), None);
+    assert_eq!(feature_names(fgb)?, expected);
+
+    // Sequential reading
+    let mut filein = BufReader::new(File::open("../../test/data/countries.fgb")?);
+    let mut fgb = FgbReader::open(&mut filein)?
+        .select_all_seq()?
+        .filter_properties(filter)?;
+    let mut names = Vec::new();
+    while let Some(feature) = fgb.next()? {
+        names.push(feature.property::<String>("name")?);
+    }
+    assert_eq!(names, expected);
+
+    // Combined with bbox selection and repeated filters
+    let mut filein = BufReader::new(File::open("../../test/data/countries.fgb")?);
+    let fgb = FgbReader::open(&mut filein)?
+        .select_bbox(8.8, 47.2, 9.5, 55.3)?
+        .filter_properties(Expr::is_in("name", ["Germany", "France", "Spain"]))?
+        .filter_properties(!Expr::eq("name", "France"))?;
+    assert_eq!(feature_names(fgb)?, vec!["Germany"]);
+
+    let mut filein = BufReader::new(File::open("../../test/data/countries.fgb")?);
+    let fgb = FgbReader::open(&mut filein)?
+        .select_all()?
+        .filter_properties(Expr::eq("population", 1));
+    assert!(matches!(fgb.err(), Some(Error::UnknownColumn(name)) if name == "population"));
+    Ok(())
+}
+
+#[test]
+fn read_filter_numeric_properties() -> Result<()> {
+    let mut filein = BufReader::new(File::open("../../test/data/topp_states.fgb")?);
+    let mut fgb = FgbReader::open(&mut filein)?.select_all()?;
+    let mut expected = Vec::new();
+    while let Some(feature) = fgb.next()? {
+        let persons = feature.property::<f64>("PERSONS")?;
+        let region = feature.property::<String>("SUB_REGION")?;
+        if persons > 5_000_000.0 && (region == "Pacific" || region == "Mid Atl") {
+            expected.push(feature.property::<String>("STATE_NAME")?);
+        }
+    }
+    assert!(expected.len() > 2);
+
+    let mut filein = BufReader::new(File::open("../../test/data/topp_states.fgb")?);
+    let mut fgb = FgbReader::open(&mut filein)?
+        .select_all()?
+        .filter_properties(
+            Expr::gt("PERSONS", 5_000_000)
+                .and(Expr::eq("SUB_REGION", "Pacific").or(Expr::eq("SUB_REGION", "Mid Atl"))),
+        )?;
+    let mut names = Vec::new();
+    while let Some(feature) = fgb.next()? {
+        names.push(feature.property::<String>("STATE_NAME")?);
+    }
+    assert_eq!(names, expected);
+
+    let mut filein = BufReader::new(File::open("../../test/data/alldatatypes.fgb")?);
+    let fgb = FgbReader::open(&mut filein)?
+        .select_all()?
+        .filter_properties(
+            Expr::is_not_null("string")
+                .and(Expr::eq("ulong", u64::MAX))
+                .and(Expr::lt("long", 0))
+                .and(Expr::is_in("datetime", ["2020-02-29T12:34:56Z"])),
+        )?;
+    assert_eq!(fgb.count()?, 1);
+    let mut filein = BufReader::new(File::open("../../test/data/alldatatypes.fgb")?);
+    let fgb = FgbReader::open(&mut filein)?
+        .select_all()?
+        .filter_properties(Expr::is_null("string").or(Expr::gt("double", 1e100)))?;
+    assert_eq!(fgb.count()?, 0);
+    Ok(())
+}
+
+fn write_countries(index_node_size: u16, memory_budget: Option<usize>) -> Result<Vec<u8>> {
+    use geozero::geojson::GeoJsonReader;
+    use geozero::GeozeroDatasource;
+
+    let mut fgb = FgbWriter::create_with_options(
+        "countries",
+        GeometryType::MultiPolygon,
+        FgbWriterOptions {
+            index_node_size,
+            memory_budget,
+            ..Default::default()
+        },
+    )?;
+    let mut fin = BufReader::new(File::open("../../test/data/countries.geojson")?);
+    GeoJsonReader(&mut fin).process(&mut fgb)?;
+    let mut out = Vec::new();
+    fgb.write(&mut out)?;
+    Ok(out)
+}
+
+#[test]
+fn read_index_node_sizes() -> Result<()> {
+    let bbox = (8.8, 47.2, 9.5, 55.3);
+    let default = write_countries(PackedRTree::DEFAULT_NODE_SIZE, None)?;
+    let fgb = FgbReader::open(std::io::Cursor::new(&default))?;
+    let expected = feature_names(fgb.select_bbox(bbox.0, bbox.1, bbox.2, bbox.3)?)?;
+    assert_eq!(expected.len(), 6);
+    let fgb = FgbReader::open(std::io::Cursor::new(&default))?;
+    let all_names = feature_names(fgb.select_all()?)?;
+
+    for node_size in [2, 4, 64, 256] {
+        let buf = write_countries(node_size, None)?;
+        assert_eq!(write_countries(node_size, Some(1024))?, buf);
+
+        let fgb = FgbReader::open(std::io::Cursor::new(&buf))?;
+        assert_eq!(fgb.header().index_node_size(), node_size);
+        assert_eq!(
+            feature_names(fgb.select_bbox(bbox.0, bbox.1, bbox.2, bbox.3)?)?,
+            expected
+        );
+
+        let mut fgb =
+            FgbReader::open(buf.as_slice())?.select_bbox_seq(bbox.0, bbox.1, bbox.2, bbox.3)?;
+        let mut names = Vec::new();
+        while let Some(feature) = fgb.next()? {
+            names.push(feature.property::<String>("name")?);
+        }
+        assert_eq!(names, expected);
+
+        let fgb = FgbSliceReader::open(&buf)?;
+        let mut names = Vec::new();
+        for feature in fgb.select_bbox(bbox.0, bbox.1, bbox.2, bbox.3)? {
+            names.push(feature?.property::<String>("name")?);
+        }
+        assert_eq!(names, expected);
+
+        let mut fgb = FgbReader::open(std::io::Cursor::new(&buf))?;
+        for n in [0, 1, 100, 178] {
+            let feature = fgb.feature(n)?.unwrap();
+            assert_eq!(feature.property::<String>("name")?, all_names[n]);
+        }
+        assert!(fgb.feature(179)?.is_none());
+    }
+    Ok(())
+}
+
+#[test]
+fn read_feature_by_number() -> Result<()> {
+    let mut filein = BufReader::new(File::open("../../test/data/countries.fgb")?);
+    let mut expected = FgbReader::open(&mut filein)?.select_all()?;
+
+    let mut filein = BufReader::new(File::open("../../test/data/countries.fgb")?);
+    let mut fgb = FgbReader::open(&mut filein)?;
+    let mut names = Vec::new();
+    for n in 0..179 {
+        let expected = expected.next()?.unwrap();
+        let feature = fgb.feature(n)?.unwrap();
+        assert_eq!(feature.to_wkt()?, expected.to_wkt()?);
+        names.push(feature.property::<String>("name")?);
+    }
+    assert!(fgb.feature(179)?.is_none());
+
+    // Random order
+    for n in [42, 3, 178, 0, 100] {
+        let feature = fgb.feature(n)?.unwrap();
+        assert_eq!(feature.property::<String>("name")?, names[n]);
+    }
+
+    // Selections still work afterwards
+    let fgb = fgb.select_bbox(8.8, 47.2, 9.5, 55.3)?;
+    assert_eq!(fgb.count()?, 6);
+
+    let mut filein = BufReader::new(File::open("../../test/data/unknown_feature_count.fgb")?);
+    let mut fgb = FgbReader::open(&mut filein)?;
+    assert!(matches!(fgb.feature(0).err(), Some(Error::NoIndex)));
+    Ok(())
+}
+
+#[test]
+fn read_slice() -> Result<()> {
+    let buf = std::fs::read("../../test/data/countries.fgb")?;
+    let fgb = FgbSliceReader::open(&buf)?;
+    assert_eq!(fgb.header().features_count(), 179);
+
+    let features = fgb.select_all()?;
+    assert_eq!(features.features_count(), Some(179));
+    let mut cnt = 0;
+    for feature in features {
+        let feature = feature?;
+        let _props = feature.properties()?;
+        assert!(feature.geometry_trait()?.is_some());
+        cnt += 1;
+    }
+    assert_eq!(cnt, 179);
+
+    let features = fgb.select_bbox(8.8, 47.2, 9.5, 55.3)?;
+    assert_eq!(features.features_count(), Some(6));
+    let features = features.collect::<std::result::Result<Vec<_>, _>>()?;
+    assert_eq!(features.len(), 6);
+    assert_eq!(
+        features[0].property("name").ok(),
+        Some("Denmark".to_string())
+    );
+    // Borrowed geometries outlive the iterator
+    let geom = features[0].geometry_trait()?.unwrap();
+    assert!(matches!(
+        geo_traits::GeometryTrait::as_type(&geom),
+        geo_traits::GeometryType::MultiPolygon(_)
+    ));
+
+    let mut features = fgb.select_bbox(8.8, 47.2, 9.5, 55.3)?;
+    let mut filein = BufReader::new(File::open("../../test/data/countries.fgb")?);
+    let mut fgb = FgbReader::open(&mut filein)?.select_bbox(8.8, 47.2, 9.5, 55.3)?;
+    while let Some(feature) = fgb.next()? {
+        assert_eq!(feature.to_wkt()?, features.next().unwrap()?.to_wkt()?);
+    }
+    assert!(features.next().is_none());
+
+    Ok(())
+}
+
+#[test]
+fn read_slice_errors() -> Result<()> {
+    let buf = std::fs::read("../../test/data/countries.fgb")?;
+    assert!(matches!(
+        FgbSliceReader::open(&buf[..10]).err(),
+        Some(Error::IO(_))
+    ));
+    let states = std::fs::read("../../test/data/states.geojson")?;
+    assert!(matches!(
+        FgbSliceReader::open(&states).err(),
+        Some(Error::MissingMagicBytes)
+    ));
+
+    // Truncated feature section
+    let fgb = FgbSliceReader::open(&buf[..buf.len() - 100])?;
+    let results = fgb.select_all()?.collect::<Vec<_>>();
+    assert_eq!(results.len(), 179);
+    assert!(results.last().unwrap().is_err());
+
+    let buf = std::fs::read("../../test/data/unknown_feature_count.fgb")?;
+    let fgb = FgbSliceReader::open(&buf)?;
+    let features = fgb.select_all()?;
+    assert_eq!(features.features_count(), None);
+    assert_eq!(features.count(), 1);
+    Ok(())
+}
+
 #[test]
 fn read_empty_dataset() -> Result<()> {
     let mut filein = BufReader::new(File::open("../../test/data/empty.fgb")?);
@@ -177,6 +547,32 @@ fn read_empty_dataset() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn read_bbox_node_size_one() -> Result<()> {
+    // Header claims node size 1, index was written with node size 2
+    let mut filein = BufReader::new(File::open("../../test/data/node_size_one.fgb")?);
+    let fgb = FgbReader::open(&mut filein)?;
+    assert_eq!(fgb.header().index_node_size(), 1);
+    assert_eq!(fgb.select_bbox(1.5, 1.5, 3.5, 3.5)?.count()?, 2);
+
+    let mut filein = BufReader::new(File::open("../../test/data/node_size_one.fgb")?);
+    let fgb = FgbReader::open(&mut filein)?.select_bbox_seq(1.5, 1.5, 3.5, 3.5)?;
+    assert_eq!(fgb.count()?, 2);
+
+    Ok(())
+}
+
+#[test]
+fn read_slice_node_size_one() -> Result<()> {
+    let buf = std::fs::read("../../test/data/node_size_one.fgb")?;
+    let fgb = FgbSliceReader::open(&buf)?;
+    assert_eq!(fgb.header().index_node_size(), 1);
+    let features = fgb.select_bbox(1.5, 1.5, 3.5, 3.5)?;
+    assert_eq!(features.features_count(), Some(2));
+    assert_eq!(features.count(), 2);
+    Ok(())
+}
+
 #[test]
 fn read_unknown_feature_count() -> Result<()> {
     let mut filein = BufReader::new(File::open("../../test/data/unknown_feature_count.fgb")?);
@@ -645,6 +1041,55 @@ fn property_types() -> Result<()> {
     Ok(())
 }
 
+struct PropCollector(Vec<(usize, String, String)>);
+
+impl PropertyProcessor for PropCollector {
+    fn property(&mut self, i: usize, name: &str, v: &ColumnValue) -> geozero::error::Result<bool> {
+        self.0.push((i, name.to_string(), v.to_string()));
+        Ok(false)
+    }
+}
+
+#[test]
+fn property_projection() -> Result<()> {
+    let mut filein = BufReader::new(File::open("../../test/data/alldatatypes.fgb")?);
+    let mut fgb = FgbReader::open(&mut filein)?
+        .select_all()?
+        .columns(&["binary", "int", "datetime", "int"])?;
+    let feature = fgb.next()?.unwrap();
+    let mut collector = PropCollector(Vec::new());
+    feature.process_properties(&mut collector)?;
+    // Values in file order, indices in projection order
+    assert_eq!(
+        collector.0,
+        vec![
+            (1, "int".to_string(), "-1".to_string()),
+            (
+                2,
+                "datetime".to_string(),
+                "2020-02-29T12:34:56Z".to_string()
+            ),
+            (0, "binary".to_string(), "[BINARY]".to_string()),
+        ]
+    );
+    assert_eq!(feature.properties()?.len(), 3);
+    assert!(fgb.next()?.is_none());
+
+    let mut filein = BufReader::new(File::open("../../test/data/countries.fgb")?);
+    let fgb = FgbReader::open(&mut filein)?
+        .select_bbox(8.8, 47.2, 9.5, 55.3)?
+        .columns(&["name"])?
+        .filter_properties(Expr::eq("id", "DEU"))?;
+    assert_eq!(feature_names(fgb)?, vec!["Germany"]);
+
+    let mut filein = BufReader::new(File::open("../../test/data/countries.fgb")?);
+    let fgb = FgbReader::open(&mut filein)?
+        .select_all()?
+        .columns(&["name", "population"]);
+    assert!(matches!(fgb.err(), Some(Error::UnknownColumn(name)) if name == "population"));
+    Ok(())
+}
+
 #[test]
 fn json_to_fgb_geometry_only() -> Result<()> {
     let mut filein = BufReader::new(File::open("../../test/data/no_properties.fgb")?);
@@ -768,3 +1213,51 @@ fn select_bbox_seekable_rejects_implausible_features_count() {
     let r = fgb.select_bbox(0.0, 0.0, 1.0, 1.0);
     assert!(r.is_err(), "select_bbox must Err, got {:?}", r.err());
 }
+
+#[test]
+fn read_owned_features() -> Result<()> {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<OwnedFeature>();
+
+    let mut filein = BufReader::new(File::open("../../test/data/countries.fgb")?);
+    let fgb = FgbReader::open(&mut filein)?.select_all()?;
+    let expected = feature_names(fgb)?;
+
+    let mut filein = BufReader::new(File::open("../../test/data/countries.fgb")?);
+    let iter = FgbReader::open(&mut filein)?
+        .select_all()?
+        .into_iter_owned();
+    assert_eq!(iter.size_hint(), (179, Some(179)));
+    let features = iter.collect::<flatgeobuf::Result<Vec<_>>>()?;
+    assert_eq!(features.len(), 179);
+    // Features stay valid and can be processed on other threads
+    let names = std::thread::spawn(move || {
+        features
+            .iter()
+            .map(|feature| feature.property::<String>("name").unwrap())
+            .collect::<Vec<_>>()
+    })
+    .join()
+    .unwrap();
+    assert_eq!(names, expected);
+
+    // Sequential reading with bbox filter and projection
+    let mut filein = BufReader::new(File::open("../../test/data/countries.fgb")?);
+    let features = FgbReader::open(&mut filein)?
+        .select_bbox_seq(8.8, 47.2, 9.5, 55.3)?
+        .columns(&["name"])?
+        .into_iter_owned()
+        .collect::<flatgeobuf::Result<Vec<_>>>()?;
+    let mut filein = BufReader::new(File::open("../../test/data/countries.fgb")?);
+    let fgb = FgbReader::open(&mut filein)?.select_bbox(8.8, 47.2, 9.5, 55.3)?;
+    for (feature, name) in features.iter().zip(feature_names(fgb)?) {
+        assert_eq!(feature.property_opt::<String>("name")?, Some(name));
+        assert!(feature.geometry().is_some());
+        assert!(matches!(
+            feature.property_opt::<String>("iso_a3"),
+            Err(Error::UnknownColumn(_))
+        ));
+    }
+    assert_eq!(features[0].to_wkt()?.get(..12), Some("MULTIPOLYGON"));
+    Ok(())
+}
@@ -0,0 +1,496 @@
+#![cfg(feature = "serde")]
+
+use flatgeobuf::*;
+use geozero::{ColumnValue, PropertyProcessor};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Cursor};
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Country<'a> {
+    id: &'a str,
+    name: Option<String>,
+}
+
+#[test]
+fn deserialize_countries() -> Result<()> {
+    let mut filein = BufReader::new(File::open("../../test/data/countries.fgb")?);
+    let mut fgb = FgbReader::open(&mut filein)?.select_all()?;
+    let feature = fgb.next()?.unwrap();
+    let country: Country = feature.deserialize()?;
+    assert_eq!(
+        country,
+        Country {
+            id: "ATA",
+            name: Some("Antarctica".to_string())
+        }
+    );
+    let (id, name): (String, &str) = feature.deserialize()?;
+    assert_eq!((id.as_str(), name), ("ATA", "Antarctica"));
+    let props: HashMap<&str, &str> = feature.deserialize()?;
+    assert_eq!(props["name"], "Antarctica");
+    assert_eq!(feature.as_feature_ref().deserialize::<Country>()?, country);
+
+    let mut cnt = 0;
+    while let Some(feature) = fgb.next()? {
+        let _country: Country = feature.deserialize()?;
+        cnt += 1;
+    }
+    assert_eq!(cnt, 178);
+    Ok(())
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct AllTypes<'a> {
+    byte: i8,
+    ubyte: u8,
+    bool: bool,
+    short: i16,
+    ushort: u16,
+    int: i32,
+    uint: u32,
+    long: i64,
+    ulong: u64,
+    float: f32,
+    double: f64,
+    string: &'a str,
+    json: String,
+    datetime: &'a str,
+    binary: &'a [u8],
+}
+
+#[test]
+fn deserialize_all_data_types() -> Result<()> {
+    let mut filein = BufReader::new(File::open("../../test/data/alldatatypes.fgb")?);
+    let mut fgb = FgbReader::open(&mut filein)?.select_all()?;
+    let feature = fgb.next()?.unwrap();
+    let row: AllTypes = feature.deserialize()?;
+    assert_eq!(
+        row,
+        AllTypes {
+            byte: -1,
+            ubyte: 255,
+            bool: true,
+            short: -1,
+            ushort: 65535,
+            int: -1,
+            uint: 4294967295,
+            long: -1,
+            ulong: u64::MAX,
+            float: 0.0,
+            double: 0.0,
+            string: "X",
+            json: "X".to_string(),
+            datetime: "2020-02-29T12:34:56Z",
+            binary: &[88],
+        }
+    );
+    // Integer widening
+    #[derive(Deserialize)]
+    struct Wide {
+        short: i64,
+        ubyte: f64,
+    }
+    let wide: Wide = feature.deserialize()?;
+    assert_eq!((wide.short, wide.ubyte), (-1, 255.0));
+    // Type mismatch
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    struct Mismatch {
+        string: i32,
+    }
+    assert!(matches!(
+        feature.deserialize::<Mismatch>(),
+        Err(Error::Serde(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn deserialize_projected_columns() -> Result<()> {
+    #[derive(Deserialize)]
+    struct Projected {
+        datetime: String,
+        int: i32,
+        string: Option<String>,
+    }
+    let mut filein = BufReader::new(File::open("../../test/data/alldatatypes.fgb")?);
+    let mut fgb = FgbReader::open(&mut filein)?
+        .select_all()?
+        .columns(&["int", "datetime"])?;
+    let feature = fgb.next()?.unwrap();
+    let row: Projected = feature.deserialize()?;
+    assert_eq!(row.datetime, "2020-02-29T12:34:56Z");
+    assert_eq!(row.int, -1);
+    assert_eq!(row.string, None);
+    // Projection order
+    let values: (i32, &str) = feature.deserialize()?;
+    assert_eq!(values, (-1, "2020-02-29T12:34:56Z"));
+    Ok(())
+}
+
+#[test]
+fn deserialize_missing_values() -> Result<()> {
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Row {
+        name: String,
+        pop: Option<i64>,
+    }
+    let mut fgb = FgbWriter::create_with_options(
+        "points",
+        GeometryType::Point,
+        FgbWriterOptions {
+            write_index: false,
+            ..Default::default()
+        },
+    )?;
+    fgb.add_column("name", ColumnType::String, |_, _| {});
+    fgb.add_column("pop", ColumnType::Int, |_, _| {});
+    fgb.add_feature_geom(
+        geo_types::Geometry::from(geo_types::Point::new(1.0, 2.0)),
+        |feat| {
+            feat.property(0, "name", &ColumnValue::String("a")).unwrap();
+            feat.property(1, "pop", &ColumnValue::Int(10)).unwrap();
+        },
+    )
+    .ok();
+    fgb.add_feature_geom(
+        geo_types::Geometry::from(geo_types::Point::new(3.0, 4.0)),
+        |feat| {
+            feat.property(0, "name", &ColumnValue::String("b")).unwrap();
+        },
+    )
+    .ok();
+    let mut buf = Vec::new();
+    fgb.write(&mut buf)?;
+
+    let mut fgb = FgbReader::open(Cursor::new(buf))?.select_all()?;
+    let feature = fgb.next()?.unwrap();
+    assert_eq!(
+        feature.deserialize::<Row>()?,
+        Row {
+            name: "a".to_string(),
+            pop: Some(10)
+        }
+    );
+    let feature = fgb.next()?.unwrap();
+    assert_eq!(
+        feature.deserialize::<Row>()?,
+        Row {
+            name: "b".to_string(),
+            pop: None
+        }
+    );
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+enum Kind {
+    City,
+    Village,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Place<'a> {
+    id: u64,
+    name: &'a str,
+    population: Option<i32>,
+    area: f64,
+    capital: bool,
+    kind: Kind,
+    code: char,
+    hash: [u8; 4],
+    data: Vec<u8>,
+}
+
+#[test]
+fn serialize_rows() -> Result<()> {
+    let mut fgb = FgbWriter::from_schema::<Place>(
+        "places",
+        GeometryType::Point,
+        FgbWriterOptions {
+            write_index: false,
+            ..Default::default()
+        },
+    )?;
+    let places = [
+        Place {
+            id: 1,
+            name: "Zürich",
+            population: Some(421_878),
+            area: 87.88,
+            capital: false,
+            kind: Kind::City,
+            code: 'Z',
+            hash: [1, 2, 3, 4],
+            data: vec![42],
+        },
+        Place {
+            id: 2,
+            name: "Soglio",
+            population: None,
+            area: 3.5,
+            capital: false,
+            kind: Kind::Village,
+            code: 'S',
+            hash: [0; 4],
+            data: Vec::new(),
+        },
+    ];
+    for (i, place) in places.iter().enumerate() {
+        let geom = geo_types::Geometry::from(geo_types::Point::new(i as f64, 1.0));
+        fgb.add_row(&geom, place)?;
+    }
+    let mut buf = Vec::new();
+    fgb.write(&mut buf)?;
+
+    let mut fgb = FgbReader::open(Cursor::new(buf))?.select_all()?;
+    let columns = fgb.header().columns().unwrap();
+    let column_defs = columns
+        .iter()
+        .map(|col| (col.name(), col.type_(), col.nullable(), col.width()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        column_defs,
+        vec![
+            ("id", ColumnType::ULong, false, 0),
+            ("name", ColumnType::String, false, 0),
+            ("population", ColumnType::Int, true, 0),
+            ("area", ColumnType::Double, false, 0),
+            ("capital", ColumnType::Bool, false, 0),
+            ("kind", ColumnType::String, false, 0),
+            ("code", ColumnType::String, false, 1),
+            ("hash", ColumnType::Binary, false, 4),
+            ("data", ColumnType::Binary, false, 0),
+        ]
+    );
+    for place in &places {
+        let feature = fgb.next()?.unwrap();
+        assert_eq!(&feature.deserialize::<Place>()?, place);
+    }
+    assert!(fgb.next()?.is_none());
+    Ok(())
+}
+
+#[test]
+fn serialize_rows_from_sample() -> Result<()> {
+    let place = Place {
+        id: 1,
+        name: "Zürich",
+        population: Some(421_878),
+        area: 87.88,
+        capital: false,
+        kind: Kind::City,
+        code: 'Z',
+        hash: [1, 2, 3, 4],
+        data: vec![42],
+    };
+    let options = || FgbWriterOptions {
+        write_index: false,
+        ..Default::default()
+    };
+    let column_defs = |fgb: FgbWriter| -> Result<Vec<(String, ColumnType, bool, i32)>> {
+        let mut buf = Vec::new();
+        fgb.write(&mut buf)?;
+        let fgb = FgbReader::open(Cursor::new(buf))?;
+        Ok(fgb
+            .header()
+            .columns()
+            .unwrap()
+            .iter()
+            .map(|col| {
+                (
+                    col.name().to_string(),
+                    col.type_(),
+                    col.nullable(),
+                    col.width(),
+                )
+            })
+            .collect())
+    };
+    let from_row = FgbWriter::from_row("places", GeometryType::Point, options(), &place)?;
+    let from_schema = FgbWriter::from_schema::<Place>("places", GeometryType::Point, options())?;
+    assert_eq!(column_defs(from_row)?, column_defs(from_schema)?);
+
+    // The type of `None` is unknown
+    let place = Place {
+        population: None,
+        ..place
+    };
+    assert!(matches!(
+        FgbWriter::from_row("places", GeometryType::Point, options(), &place),
+        Err(Error::Serde(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn serialize_rows_asymmetric_attributes() -> Result<()> {
+    #[derive(Serialize, Deserialize, Default)]
+    struct Row {
+        #[serde(rename(serialize = "label"))]
+        name: String,
+        #[serde(skip_serializing)]
+        #[allow(dead_code)]
+        cache: u32,
+        #[serde(skip_deserializing)]
+        rank: i32,
+    }
+    let row = Row {
+        name: "a".to_string(),
+        cache: 1,
+        rank: 2,
+    };
+    let geom = geo_types::Geometry::from(geo_types::Point::new(1.0, 2.0));
+    let options = || FgbWriterOptions {
+        write_index: false,
+        ..Default::default()
+    };
+
+    // Columns of the deserialized fields don't match the written ones
+    let mut fgb = FgbWriter::from_schema::<Row>("rows", GeometryType::Point, options())?;
+    assert!(matches!(
+        fgb.add_row(&geom, &row),
+        Err(Error::UnknownColumn(name)) if name == "label"
+    ));
+
+    let mut fgb = FgbWriter::from_row("rows", GeometryType::Point, options(), &row)?;
+    fgb.add_row(&geom, &row)?;
+    let mut buf = Vec::new();
+    fgb.write(&mut buf)?;
+    let mut fgb = FgbReader::open(Cursor::new(buf))?.select_all()?;
+    let names = fgb
+        .header()
+        .columns()
+        .unwrap()
+        .iter()
+        .map(|col| col.name())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["label", "rank"]);
+    let feature = fgb.next()?.unwrap();
+    assert_eq!(
+        feature.deserialize::<(String, i32)>()?,
+        ("a".to_string(), 2)
+    );
+    Ok(())
+}
+
+#[test]
+fn serialize_rows_checked() -> Result<()> {
+    let mut fgb = FgbWriter::create_with_options(
+        "points",
+        GeometryType::Point,
+        FgbWriterOptions {
+            write_index: false,
+            ..Default::default()
+        },
+    )?;
+    fgb.add_column("name", ColumnType::String, |_, col| {
+        col.nullable = false;
+        col.width = 3;
+    });
+    fgb.add_column("pop", ColumnType::Int, |_, col| {
+        col.nullable = true;
+    });
+    let geom = geo_types::Geometry::from(geo_types::Point::new(1.0, 2.0));
+
+    #[derive(Serialize)]
+    struct Row<'a> {
+        name: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pop: Option<i64>,
+    }
+    // Lossless conversion to declared type
+    fgb.add_row(
+        &geom,
+        &Row {
+            name: "abc",
+            pop: Some(10),
+        },
+    )?;
+    // Skipped nullable field
+    fgb.add_row(
+        &geom,
+        &Row {
+            name: "def",
+            pop: None,
+        },
+    )?;
+    // Out of range
+    assert!(matches!(
+        fgb.add_row(
+            &geom,
+            &Row {
+                name: "ghi",
+                pop: Some(i64::MAX),
+            },
+        ),
+        Err(Error::Schema(SchemaError::ColumnType { .. }))
+    ));
+    // Width exceeded
+    assert!(matches!(
+        fgb.add_row(
+            &geom,
+            &Row {
+                name: "abcd",
+                pop: None,
+            },
+        ),
+        Err(Error::Schema(SchemaError::Width {
+            width: 3,
+            len: 4,
+            ..
+        }))
+    ));
+
+    #[derive(Serialize)]
+    struct Unknown {
+        name: &'static str,
+        area: f64,
+    }
+    assert!(matches!(
+        fgb.add_row(
+            &geom,
+            &Unknown {
+                name: "x",
+                area: 1.0
+            }
+        ),
+        Err(Error::UnknownColumn(name)) if name == "area"
+    ));
+
+    #[derive(Serialize)]
+    struct Incomplete {
+        pop: i32,
+    }
+    assert!(matches!(
+        fgb.add_row(&geom, &Incomplete { pop: 1 }),
+        Err(Error::MissingValue(name)) if name == "name"
+    ));
+
+    let mut buf = Vec::new();
+    fgb.write(&mut buf)?;
+    let mut fgb = FgbReader::open(Cursor::new(buf))?.select_all()?;
+    assert_eq!(fgb.features_count(), Some(2));
+    let feature = fgb.next()?.unwrap();
+    assert_eq!(
+        feature.deserialize::<(&str, Option<i32>)>()?,
+        ("abc", Some(10))
+    );
+    let feature = fgb.next()?.unwrap();
+    assert_eq!(feature.deserialize::<(&str, Option<i32>)>()?, ("def", None));
+    Ok(())
+}
+
+#[test]
+fn schema_requires_struct() {
+    assert!(matches!(
+        FgbWriter::from_schema::<HashMap<String, i32>>(
+            "",
+            GeometryType::Point,
+            FgbWriterOptions::default()
+        ),
+        Err(Error::Serde(_))
+    ));
+}
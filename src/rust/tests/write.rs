@@ -1,8 +1,13 @@
 use crate::FgbWriter;
+use flatgeobuf::packed_r_tree::PackedRTree;
 use flatgeobuf::*;
 use geo_types::{line_string, LineString};
+use geozero::error::GeozeroError;
 use geozero::geojson::{GeoJson, GeoJsonReader};
-use geozero::{ColumnValue, GeozeroDatasource, PropertyProcessor};
+use geozero::{
+    ColumnValue, FeatureProcessor, GeomProcessor, GeozeroDatasource, GeozeroGeometry,
+    PropertyProcessor, ToWkt,
+};
 use std::fs::File;
 use std::io::{BufReader, BufWriter, Write};
 use tempfile::{tempfile, NamedTempFile};
@@ -297,3 +302,1148 @@ fn test_save_fgb_and_load() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn stream_to_fgb() -> Result<()> {
+    let mut fgb = FgbWriter::create_with_options(
+        "countries",
+        GeometryType::MultiPolygon,
+        FgbWriterOptions {
+            write_index: false,
+            ..Default::default()
+        },
+    )?;
+    let mut fin = BufReader::new(File::open("../../test/data/countries.geojson")?);
+    GeoJsonReader(&mut fin).process(&mut fgb)?;
+    let mut expected = Vec::new();
+    fgb.write(&mut expected)?;
+
+    // Output without Seek
+    let mut fgb = FgbStreamWriter::create(Vec::new(), "countries", GeometryType::MultiPolygon)?;
+    let mut fin = BufReader::new(File::open("../../test/data/countries.geojson")?);
+    GeoJsonReader(&mut fin).process(&mut fgb)?;
+    assert_eq!(fgb.features_count(), 179);
+    let streamed = fgb.finish()?;
+
+    // Output with Seek
+    let mut fgb = FgbStreamWriter::create(
+        std::io::Cursor::new(Vec::new()),
+        "countries",
+        GeometryType::MultiPolygon,
+    )?;
+    let mut fin = BufReader::new(File::open("../../test/data/countries.geojson")?);
+    GeoJsonReader(&mut fin).process(&mut fgb)?;
+    let patched = fgb.finish_with_count()?.into_inner();
+
+    let mut expected = FgbReader::open(expected.as_slice())?.select_all_seq()?;
+    let mut streamed = FgbReader::open(streamed.as_slice())?.select_all_seq()?;
+    assert_eq!(streamed.header().features_count(), 0);
+    assert_eq!(streamed.header().columns().unwrap().len(), 1);
+    assert_eq!(streamed.features_count(), None);
+    let mut patched = FgbReader::open(patched.as_slice())?.select_all_seq()?;
+    assert_eq!(patched.features_count(), Some(179));
+    let mut cnt = 0;
+    while let Some(feature) = expected.next()? {
+        let streamed = streamed.next()?.unwrap();
+        assert_eq!(streamed.to_wkt()?, feature.to_wkt()?);
+        assert_eq!(streamed.properties()?, feature.properties()?);
+        let patched = patched.next()?.unwrap();
+        assert_eq!(patched.to_wkt()?, feature.to_wkt()?);
+        cnt += 1;
+    }
+    assert_eq!(cnt, 179);
+    assert!(streamed.next()?.is_none());
+    assert!(patched.next()?.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn stream_columns_after_header() -> Result<()> {
+    let mut fgb = FgbStreamWriter::create(Vec::new(), "points", GeometryType::Point)?;
+    fgb.add_column("fid", ColumnType::ULong, |_, _| {});
+    fgb.add_feature_geom(
+        GeoJson(r#"{"type": "Point", "coordinates": [1, 1]}"#),
+        |feat| {
+            feat.property(0, "fid", &ColumnValue::ULong(1)).unwrap();
+        },
+    )?;
+    fgb.add_column("name", ColumnType::String, |_, _| {});
+    let result = fgb.add_feature_geom(
+        GeoJson(r#"{"type": "Point", "coordinates": [2, 2]}"#),
+        |_| {},
+    );
+    assert!(result.is_err());
+    Ok(())
+}
+
+#[test]
+fn write_with_memory_budget() -> Result<()> {
+    let write = |memory_budget| -> Result<Vec<u8>> {
+        let mut fgb = FgbWriter::create_with_options(
+            "countries",
+            GeometryType::MultiPolygon,
+            FgbWriterOptions {
+                memory_budget,
+                ..Default::default()
+            },
+        )?;
+        let mut fin = BufReader::new(File::open("../../test/data/countries.geojson")?);
+        GeoJsonReader(&mut fin).process(&mut fgb)?;
+        let mut out = Vec::new();
+        fgb.write(&mut out)?;
+        Ok(out)
+    };
+    let expected = write(None)?;
+    // In memory, spilled to a few runs and spilled to many runs
+    for memory_budget in [1 << 20, 2048, 100] {
+        assert_eq!(write(Some(memory_budget))?, expected);
+    }
+
+    let mut reader =
+        FgbReader::open(std::io::Cursor::new(expected))?.select_bbox(8.8, 47.2, 9.5, 55.3)?;
+    let mut cnt = 0;
+    while let Some(_feature) = reader.next()? {
+        cnt += 1;
+    }
+    assert_eq!(cnt, 6);
+    Ok(())
+}
+
+fn write_countries_file(write_index: bool) -> Result<NamedTempFile> {
+    let mut fgb = FgbWriter::create_with_options(
+        "countries",
+        GeometryType::MultiPolygon,
+        FgbWriterOptions {
+            write_index,
+            ..Default::default()
+        },
+    )?;
+    let mut fin = BufReader::new(File::open("../../test/data/countries.geojson")?);
+    GeoJsonReader(&mut fin).process(&mut fgb)?;
+    let file = NamedTempFile::new()?;
+    fgb.write(BufWriter::new(file.as_file()))?;
+    Ok(file)
+}
+
+fn read_names(path: &std::path::Path) -> Result<Vec<String>> {
+    let mut fgb = FgbReader::open(BufReader::new(File::open(path)?))?.select_all()?;
+    let mut names = Vec::new();
+    while let Some(feature) = fgb.next()? {
+        names.push(feature.property::<String>("name")?);
+    }
+    Ok(names)
+}
+
+const ATLANTIS: &str = r#"{"type": "Feature", "properties": {"name": "Atlantis"}, "geometry": {"type": "Polygon", "coordinates": [[[-31, 29], [-29, 29], [-29, 31], [-31, 29]]]}}"#;
+const MU: &str = r#"{"type": "Feature", "properties": {"name": "Mu", "area": 1}, "geometry": {"type": "MultiPolygon", "coordinates": [[[[-179, -89], [-178, -89], [-178, -88], [-179, -89]]]]}}"#;
+
+#[test]
+fn append_unindexed() -> Result<()> {
+    let file = write_countries_file(false)?;
+    let names = read_names(file.path())?;
+
+    let mut fgb = FgbWriter::append(file.path())?;
+    fgb.add_feature(GeoJson(ATLANTIS))?;
+    fgb.add_feature(GeoJson(MU))?;
+    assert_eq!(fgb.features_count(), 2);
+    fgb.finish()?;
+
+    let reader = FgbReader::open(BufReader::new(File::open(file.path())?))?;
+    let header = reader.header();
+    assert_eq!(header.features_count(), 181);
+    let envelope = header.envelope().unwrap();
+    assert_eq!(envelope.get(1), -89.0);
+    assert_eq!(header.columns().unwrap().len(), 1);
+    let mut fgb = reader.select_all()?;
+    assert_eq!(fgb.features_count(), Some(181));
+    let mut appended = Vec::new();
+    let mut cnt = 0;
+    while let Some(feature) = fgb.next()? {
+        if cnt >= 179 {
+            appended.push((feature.property::<String>("name")?, feature.to_wkt()?));
+        } else {
+            assert_eq!(feature.property::<String>("name")?, names[cnt]);
+        }
+        cnt += 1;
+    }
+    assert_eq!(cnt, 181);
+    assert_eq!(
+        appended,
+        vec![
+            (
+                "Atlantis".to_string(),
+                "MULTIPOLYGON(((-31 29,-29 29,-29 31,-31 29)))".to_string()
+            ),
+            (
+                "Mu".to_string(),
+                "MULTIPOLYGON(((-179 -89,-178 -89,-178 -88,-179 -89)))".to_string()
+            ),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn append_indexed() -> Result<()> {
+    let file = write_countries_file(true)?;
+    let names = read_names(file.path())?;
+
+    let mut fgb = FgbWriter::append(file.path())?;
+    fgb.add_feature(GeoJson(MU))?;
+    fgb.add_feature(GeoJson(ATLANTIS))?;
+    fgb.finish()?;
+
+    let mut reader = FgbReader::open(BufReader::new(File::open(file.path())?))?;
+    let header = reader.header();
+    assert_eq!(header.features_count(), 181);
+    assert_eq!(header.index_node_size(), PackedRTree::DEFAULT_NODE_SIZE);
+    assert_eq!(header.name(), Some("countries"));
+    assert_eq!(header.envelope().unwrap().get(1), -89.0);
+    assert_eq!(
+        reader.feature(179)?.unwrap().property::<String>("name")?,
+        "Atlantis"
+    );
+
+    // Existing features keep their order
+    let all_names = read_names(file.path())?;
+    assert_eq!(all_names[..179], names[..]);
+    assert_eq!(all_names[179..], ["Atlantis", "Mu"]);
+
+    let mut fgb = FgbReader::open(BufReader::new(File::open(file.path())?))?
+        .select_bbox(-30.5, 29.5, -30.0, 30.0)?;
+    let feature = fgb.next()?.unwrap();
+    assert_eq!(feature.property::<String>("name")?, "Atlantis");
+    assert!(fgb.next()?.is_none());
+    let fgb = FgbReader::open(BufReader::new(File::open(file.path())?))?
+        .select_bbox(8.8, 47.2, 9.5, 55.3)?;
+    assert_eq!(fgb.count()?, 6);
+    Ok(())
+}
+
+#[test]
+fn append_converts_values() -> Result<()> {
+    let file = NamedTempFile::new()?;
+    let mut fgb = FgbWriter::create("points", GeometryType::Point)?;
+    fgb.add_column("name", ColumnType::String, |_, _| {});
+    fgb.add_column("pop", ColumnType::Int, |_, _| {});
+    fgb.add_feature_geom(
+        GeoJson(r#"{"type": "Point", "coordinates": [1, 1]}"#),
+        |feat| {
+            feat.property(0, "name", &ColumnValue::String("a")).unwrap();
+            feat.property(1, "pop", &ColumnValue::Int(1)).unwrap();
+        },
+    )?;
+    fgb.write(BufWriter::new(file.as_file()))?;
+
+    let mut fgb = FgbWriter::append(file.path())?;
+    // Properties in other order, JSON numbers are Long
+    fgb.add_feature(GeoJson(
+        r#"{"type": "Feature", "properties": {"pop": 42, "name": "b"}, "geometry": {"type": "Point", "coordinates": [2, 2]}}"#,
+    ))?;
+    assert!(fgb
+        .add_feature(GeoJson(
+            r#"{"type": "Feature", "properties": {"pop": "many"}, "geometry": {"type": "Point", "coordinates": [3, 3]}}"#,
+        ))
+        .is_err());
+    // Failed features are discarded
+    assert!(fgb
+        .add_feature(GeoJson(
+            r#"{"type": "Feature", "properties": {"name": "c"}, "geometry": {"type": "LineString", "coordinates": [[3, 3], [4, 4]]}}"#,
+        ))
+        .is_err());
+    fgb.add_feature(GeoJson(
+        r#"{"type": "Feature", "properties": {"pop": 4}, "geometry": {"type": "Point", "coordinates": [4, 4]}}"#,
+    ))?;
+    fgb.finish()?;
+
+    let mut fgb = FgbReader::open(BufReader::new(File::open(file.path())?))?.select_all()?;
+    assert_eq!(fgb.features_count(), Some(3));
+    let mut features = Vec::new();
+    while let Some(feature) = fgb.next()? {
+        features.push((
+            feature.property::<String>("name").ok(),
+            feature.property::<i32>("pop")?,
+            feature.to_wkt()?,
+        ));
+    }
+    features.sort();
+    assert_eq!(
+        features,
+        [
+            (None, 4, "POINT(4 4)".to_string()),
+            (Some("a".to_string()), 1, "POINT(1 1)".to_string()),
+            (Some("b".to_string()), 42, "POINT(2 2)".to_string()),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn append_indexed_without_envelope() -> Result<()> {
+    let file = write_countries_file(true)?;
+    let envelope = {
+        let reader = FgbReader::open(BufReader::new(File::open(file.path())?))?;
+        let envelope = reader.header().envelope().unwrap();
+        envelope.iter().collect::<Vec<_>>()
+    };
+
+    // Replace header with one without envelope
+    let data = std::fs::read(file.path())?;
+    let header_end = 12 + u32::from_le_bytes(data[8..12].try_into()?) as usize;
+    let mut fbb = flatbuffers::FlatBufferBuilder::new();
+    let column_args = ColumnArgs {
+        name: Some(fbb.create_string("name")),
+        type_: ColumnType::String,
+        ..Default::default()
+    };
+    let column = Column::create(&mut fbb, &column_args);
+    let header_args = HeaderArgs {
+        name: Some(fbb.create_string("countries")),
+        geometry_type: GeometryType::MultiPolygon,
+        columns: Some(fbb.create_vector(&[column])),
+        features_count: 179,
+        index_node_size: PackedRTree::DEFAULT_NODE_SIZE,
+        ..Default::default()
+    };
+    let header = Header::create(&mut fbb, &header_args);
+    fbb.finish_size_prefixed(header, None);
+    let mut out = BufWriter::new(File::create(file.path())?);
+    out.write_all(&data[..8])?;
+    out.write_all(fbb.finished_data())?;
+    out.write_all(&data[header_end..])?;
+    out.flush()?;
+    drop(out);
+
+    let mut fgb = FgbWriter::append(file.path())?;
+    fgb.add_feature(GeoJson(ATLANTIS))?;
+    fgb.finish()?;
+
+    let reader = FgbReader::open(BufReader::new(File::open(file.path())?))?;
+    assert_eq!(reader.header().features_count(), 180);
+    assert_eq!(
+        reader
+            .header()
+            .envelope()
+            .unwrap()
+            .iter()
+            .collect::<Vec<_>>(),
+        envelope
+    );
+    let fgb = reader.select_bbox(8.8, 47.2, 9.5, 55.3)?;
+    assert_eq!(fgb.count()?, 6);
+    Ok(())
+}
+
+#[test]
+fn append_empty_indexed() -> Result<()> {
+    let file = NamedTempFile::new()?;
+    let mut fgb = FgbWriter::create("points", GeometryType::Point)?;
+    fgb.add_column("name", ColumnType::String, |_, _| {});
+    fgb.write(BufWriter::new(file.as_file()))?;
+    let reader = FgbReader::open(BufReader::new(File::open(file.path())?))?;
+    assert_eq!(reader.header().features_count(), 0);
+    assert_eq!(
+        reader.header().index_node_size(),
+        PackedRTree::DEFAULT_NODE_SIZE
+    );
+
+    let mut fgb = FgbWriter::append(file.path())?;
+    fgb.add_feature(GeoJson(
+        r#"{"type": "Feature", "properties": {"name": "a"}, "geometry": {"type": "Point", "coordinates": [1, 1]}}"#,
+    ))?;
+    fgb.add_feature(GeoJson(
+        r#"{"type": "Feature", "properties": {"name": "b"}, "geometry": {"type": "Point", "coordinates": [2, 3]}}"#,
+    ))?;
+    fgb.finish()?;
+
+    let reader = FgbReader::open(BufReader::new(File::open(file.path())?))?;
+    let header = reader.header();
+    assert_eq!(header.features_count(), 2);
+    assert_eq!(
+        header.envelope().unwrap().iter().collect::<Vec<_>>(),
+        [1.0, 1.0, 2.0, 3.0]
+    );
+    let mut fgb = reader.select_bbox(1.5, 2.5, 2.5, 3.5)?;
+    assert_eq!(fgb.features_count(), Some(1));
+    let feature = fgb.next()?.unwrap();
+    assert_eq!(feature.property::<String>("name")?, "b");
+    Ok(())
+}
+
+#[test]
+fn index_sidecar() -> Result<()> {
+    let file = write_countries_file(false)?;
+    let open = || -> Result<FgbReader<BufReader<File>>> {
+        Ok(FgbReader::open(BufReader::new(File::open(file.path())?))?)
+    };
+    assert!(matches!(
+        open()?.select_bbox(8.8, 47.2, 9.5, 55.3),
+        Err(Error::NoIndex)
+    ));
+
+    let index = FgbIndex::build(BufReader::new(File::open(file.path())?), 16)?;
+    assert_eq!(index.features_count(), 179);
+    index.write_sidecar(file.path())?;
+    let sidecar = FgbIndex::sidecar_path(file.path());
+    assert_eq!(sidecar.extension().unwrap(), "idx");
+    let index = FgbIndex::read_sidecar(file.path());
+    std::fs::remove_file(&sidecar)?;
+    let index = index?;
+    assert_eq!(index.node_size(), 16);
+
+    let mut fgb = open()?
+        .with_index(index)?
+        .select_bbox(8.8, 47.2, 9.5, 55.3)?;
+    let mut names = Vec::new();
+    while let Some(feature) = fgb.next()? {
+        names.push(feature.property::<String>("name")?);
+    }
+    // Features in file order
+    let expected = read_names(file.path())?
+        .into_iter()
+        .filter(|name| names.contains(name))
+        .collect::<Vec<_>>();
+    assert_eq!(names, expected);
+    assert_eq!(names.len(), 6);
+
+    // Sequential reading
+    let index = FgbIndex::build(BufReader::new(File::open(file.path())?), 16)?;
+    let fgb = open()?
+        .with_index(index)?
+        .select_bbox_seq(8.8, 47.2, 9.5, 55.3)?;
+    assert_eq!(fgb.count()?, 6);
+
+    // Several bounding boxes
+    let index = FgbIndex::build(BufReader::new(File::open(file.path())?), 16)?;
+    let selection = open()?.with_index(index)?.select_bbox_many(&[
+        packed_r_tree::NodeItem::bounds(8.8, 47.2, 9.5, 55.3),
+        packed_r_tree::NodeItem::bounds(500.0, 500.0, 510.0, 510.0),
+    ])?;
+    let group = selection
+        .group(0)
+        .map(|feature| feature.property::<String>("name"))
+        .collect::<geozero::error::Result<Vec<_>>>()?;
+    assert_eq!(group, names);
+    assert_eq!(selection.group(1).len(), 0);
+
+    // Index of another dataset
+    let mut data = Vec::new();
+    let mut fgb = FgbWriter::create_with_options(
+        "atlantis",
+        GeometryType::Polygon,
+        FgbWriterOptions {
+            write_index: false,
+            ..Default::default()
+        },
+    )?;
+    fgb.add_feature(GeoJson(ATLANTIS))?;
+    fgb.write(&mut data)?;
+    let index = FgbIndex::build(&data[..], 16)?;
+    let mut written = Vec::new();
+    index.write(&mut written)?;
+    let index = FgbIndex::read(&written[..])?;
+    assert!(open()?.with_index(index).is_err());
+    Ok(())
+}
+
+#[test]
+fn rewrite_indexed() -> Result<()> {
+    let file = write_countries_file(false)?;
+    let mut out = Vec::new();
+    FgbIndex::rewrite_indexed(BufReader::new(File::open(file.path())?), &mut out, 16)?;
+
+    let fgb = FgbReader::open(std::io::Cursor::new(&out))?;
+    assert_eq!(fgb.header().features_count(), 179);
+    assert_eq!(fgb.header().index_node_size(), 16);
+    let mut fgb = fgb.select_bbox(8.8, 47.2, 9.5, 55.3)?;
+    assert_eq!(fgb.features_count(), Some(6));
+
+    // Index and features as written by FgbWriter
+    fn after_header(data: &[u8]) -> &[u8] {
+        let header_size = u32::from_le_bytes(data[8..12].try_into().unwrap()) as usize;
+        &data[12 + header_size..]
+    }
+    let expected = std::fs::read(write_countries_file(true)?.path())?;
+    assert_eq!(after_header(&out), after_header(&expected));
+    assert!(fgb.next()?.is_some());
+    Ok(())
+}
+
+#[test]
+fn null_values_round_trip() -> Result<()> {
+    let mut fgb = FgbWriter::create_with_options(
+        "alldatatypes",
+        GeometryType::Point,
+        FgbWriterOptions {
+            write_index: false,
+            ..Default::default()
+        },
+    )?;
+    fgb.add_column("id", ColumnType::Int, |_, col| {
+        col.nullable = false;
+    });
+    let columns = [
+        ("byte", ColumnType::Byte),
+        ("ubyte", ColumnType::UByte),
+        ("bool", ColumnType::Bool),
+        ("short", ColumnType::Short),
+        ("ushort", ColumnType::UShort),
+        ("int", ColumnType::Int),
+        ("uint", ColumnType::UInt),
+        ("long", ColumnType::Long),
+        ("ulong", ColumnType::ULong),
+        ("float", ColumnType::Float),
+        ("double", ColumnType::Double),
+        ("string", ColumnType::String),
+        ("json", ColumnType::Json),
+        ("datetime", ColumnType::DateTime),
+        ("binary", ColumnType::Binary),
+    ];
+    for (name, column_type) in columns {
+        fgb.add_column(name, column_type, |_, _| {});
+    }
+    let values = [
+        ColumnValue::Byte(-1),
+        ColumnValue::UByte(255),
+        ColumnValue::Bool(true),
+        ColumnValue::Short(-1),
+        ColumnValue::UShort(65535),
+        ColumnValue::Int(-1),
+        ColumnValue::UInt(4294967295),
+        ColumnValue::Long(-1),
+        ColumnValue::ULong(u64::MAX),
+        ColumnValue::Float(0.5),
+        ColumnValue::Double(0.5),
+        ColumnValue::String("X"),
+        ColumnValue::Json("[1]"),
+        ColumnValue::DateTime("2020-02-29T12:34:56Z"),
+        ColumnValue::Binary(&[88]),
+    ];
+    let geom = || GeoJson(r#"{"type": "Point", "coordinates": [1, 1]}"#);
+    fgb.add_feature_geom(geom(), |feat| {
+        feat.property(0, "id", &ColumnValue::Int(1)).unwrap();
+        for (i, value) in values.iter().enumerate() {
+            feat.property(i + 1, columns[i].0, value).unwrap();
+        }
+    })?;
+    // All nullable columns null
+    fgb.add_feature_geom(geom(), |feat| {
+        feat.property(0, "id", &ColumnValue::Int(2)).unwrap();
+    })?;
+    // Missing value of non-nullable column
+    assert!(fgb
+        .add_feature_geom(geom(), |feat| {
+            feat.property(1, "byte", &ColumnValue::Byte(1)).unwrap();
+        })
+        .is_err());
+    let mut output = vec![];
+    fgb.write(&mut output)?;
+
+    let mut fgb = FgbReader::open(&*output)?.select_all_seq()?;
+    assert_eq!(fgb.features_count(), Some(2));
+    let feature = fgb.next()?.unwrap();
+    assert_eq!(feature.property_opt::<i8>("byte")?, Some(-1));
+    assert_eq!(feature.property_opt::<u64>("ulong")?, Some(u64::MAX));
+    assert_eq!(
+        feature.property_opt::<String>("string")?.as_deref(),
+        Some("X")
+    );
+    let feature = fgb.next()?.unwrap();
+    assert_eq!(feature.property_opt::<i32>("id")?, Some(2));
+    assert_eq!(feature.property_opt::<i8>("byte")?, None);
+    assert_eq!(feature.property_opt::<f64>("double")?, None);
+    assert_eq!(feature.property_opt::<String>("datetime")?, None);
+    assert!(matches!(
+        feature.property_opt::<i32>("unknown"),
+        Err(Error::UnknownColumn(_))
+    ));
+
+    // GeoJSON output
+    let mut fgb = FgbReader::open(&*output)?.select_all_seq()?;
+    let mut json = Vec::new();
+    fgb.process_features(&mut geozero::geojson::GeoJsonWriter::new(&mut json))?;
+    let json = String::from_utf8(json)?;
+    assert!(json.contains(r#""properties": {"id": 2}"#));
+
+    // Nulls with column type
+    let mut fgb = FgbReader::open(&*output)?
+        .select_all_seq()?
+        .columns(&["string", "id", "double"])?;
+    assert_eq!(
+        properties_with_nulls(fgb.next()?.unwrap())?,
+        ["string: X", "id: 1", "double: 0.5"]
+    );
+    assert_eq!(
+        properties_with_nulls(fgb.next()?.unwrap())?,
+        ["string: null String", "id: 2", "double: null Double"]
+    );
+
+    let mut fgb = FgbReader::open(&*output)?
+        .select_all_seq()?
+        .columns(&["string", "id", "double"])?
+        .explicit_nulls();
+    let mut json = Vec::new();
+    fgb.process_features(&mut geozero::geojson::GeoJsonWriter::new(&mut json))?;
+    let json = String::from_utf8(json)?;
+    assert!(json.contains(r#""properties": {"string": "X", "id": 1, "double": 0.5}"#));
+    assert!(json.contains(r#""properties": {"string": null, "id": 2, "double": null}"#));
+    Ok(())
+}
+
+/// Properties of `feature` including columns without value
+fn properties_with_nulls(feature: &FgbFeature) -> Result<Vec<String>> {
+    struct Properties(Vec<String>);
+    impl PropertyProcessor for Properties {
+        fn property(
+            &mut self,
+            _i: usize,
+            name: &str,
+            value: &ColumnValue,
+        ) -> geozero::error::Result<bool> {
+            self.0.push(format!("{name}: {value}"));
+            Ok(false)
+        }
+    }
+    impl NullPropertyProcessor for Properties {
+        fn null_property(
+            &mut self,
+            _i: usize,
+            name: &str,
+            column_type: Option<ColumnType>,
+        ) -> geozero::error::Result<bool> {
+            let column_type = column_type.map_or("unknown".to_string(), |t| format!("{t:?}"));
+            self.0.push(format!("{name}: null {column_type}"));
+            Ok(false)
+        }
+    }
+    let mut properties = Properties(Vec::new());
+    feature.process_properties_with_nulls(&mut properties)?;
+    Ok(properties.0)
+}
+
+#[test]
+fn stream_missing_value() -> Result<()> {
+    let mut fgb = FgbStreamWriter::create(Vec::new(), "points", GeometryType::Point)?;
+    fgb.add_column("name", ColumnType::String, |_, col| {
+        col.nullable = false;
+    });
+    let geom = GeoJson(r#"{"type": "Point", "coordinates": [1, 1]}"#);
+    assert!(fgb.add_feature_geom(geom, |_| {}).is_err());
+    assert_eq!(fgb.features_count(), 0);
+    Ok(())
+}
+
+/// Point geometry failing after its first coordinate
+struct BrokenPoint;
+
+impl GeozeroGeometry for BrokenPoint {
+    fn process_geom<P: GeomProcessor>(&self, processor: &mut P) -> geozero::error::Result<()> {
+        processor.point_begin(0)?;
+        processor.xy(5.0, 5.0, 0)?;
+        Err(GeozeroError::Geometry("broken point".to_string()))
+    }
+}
+
+/// Feature failing in its geometry, after its `name` property
+struct BrokenFeature;
+
+impl GeozeroDatasource for BrokenFeature {
+    fn process<P: FeatureProcessor>(&mut self, processor: &mut P) -> geozero::error::Result<()> {
+        processor.feature_begin(0)?;
+        processor.properties_begin()?;
+        processor.property(0, "name", &ColumnValue::String("broken"))?;
+        processor.properties_end()?;
+        processor.geometry_begin()?;
+        BrokenPoint.process_geom(processor)
+    }
+}
+
+/// Geometry and `name` property of all features
+fn features_wkt_name(buf: &[u8]) -> Result<Vec<(String, Option<String>)>> {
+    let mut fgb = FgbReader::open(buf)?.select_all_seq()?;
+    let mut features = Vec::new();
+    while let Some(feature) = fgb.next()? {
+        features.push((feature.to_wkt()?, feature.property_opt::<String>("name")?));
+    }
+    Ok(features)
+}
+
+#[test]
+fn failed_feature_is_discarded() -> Result<()> {
+    let point = || GeoJson(r#"{"type": "Point", "coordinates": [1, 1]}"#);
+    let expected = vec![("POINT(1 1)".to_string(), None); 2];
+
+    let mut fgb = FgbWriter::create_with_options(
+        "points",
+        GeometryType::Point,
+        FgbWriterOptions {
+            write_index: false,
+            ..Default::default()
+        },
+    )?;
+    fgb.add_column("name", ColumnType::String, |_, _| {});
+    assert!(fgb.add_feature(BrokenFeature).is_err());
+    fgb.add_feature_geom(point(), |_| {})?;
+    assert!(fgb.add_feature_geom(BrokenPoint, |_| {}).is_err());
+    fgb.add_feature_geom(point(), |_| {})?;
+    let mut output = vec![];
+    fgb.write(&mut output)?;
+    assert_eq!(features_wkt_name(&output)?, expected);
+
+    let mut fgb = FgbStreamWriter::create(Vec::new(), "points", GeometryType::Point)?;
+    fgb.add_column("name", ColumnType::String, |_, _| {});
+    assert!(fgb.add_feature(BrokenFeature).is_err());
+    fgb.add_feature_geom(point(), |_| {})?;
+    assert!(fgb.add_feature_geom(BrokenPoint, |_| {}).is_err());
+    fgb.add_feature_geom(point(), |_| {})?;
+    let output = fgb.finish()?;
+    assert_eq!(features_wkt_name(&output)?, expected);
+    Ok(())
+}
+
+fn schema_error(result: geozero::error::Result<()>) -> SchemaError {
+    match result {
+        Err(geozero::error::GeozeroError::IoError(e)) => e
+            .get_ref()
+            .and_then(|e| e.downcast_ref::<SchemaError>())
+            .expect("schema error")
+            .clone(),
+        other => panic!("expected schema error, got {other:?}"),
+    }
+}
+
+#[test]
+fn strict_schema_validation() -> Result<()> {
+    let mut fgb = FgbWriter::create_with_options(
+        "points",
+        GeometryType::Point,
+        FgbWriterOptions {
+            write_index: false,
+            schema_validation: SchemaValidation::Strict,
+            ..Default::default()
+        },
+    )?;
+    fgb.add_column("name", ColumnType::String, |_, col| {
+        col.width = 3;
+    });
+    fgb.add_column("pop", ColumnType::Long, |_, _| {});
+    let geom = || GeoJson(r#"{"type": "Point", "coordinates": [1, 1]}"#);
+
+    fgb.add_feature_geom(geom(), |feat| {
+        feat.property(0, "name", &ColumnValue::String("abc"))
+            .unwrap();
+        feat.property(1, "pop", &ColumnValue::Long(1)).unwrap();
+    })?;
+    let error = schema_error(fgb.add_feature_geom(geom(), |feat| {
+        feat.property(0, "nam", &ColumnValue::String("a")).unwrap();
+    }));
+    assert_eq!(
+        error,
+        SchemaError::ColumnName {
+            index: 0,
+            expected: "name".to_string(),
+            found: "nam".to_string()
+        }
+    );
+    let error = schema_error(fgb.add_feature_geom(geom(), |feat| {
+        feat.property(1, "pop", &ColumnValue::Int(1)).unwrap();
+    }));
+    assert_eq!(
+        error,
+        SchemaError::ColumnType {
+            column: "pop".to_string(),
+            expected: ColumnType::Long,
+            found: ColumnType::Int
+        }
+    );
+    let error = schema_error(fgb.add_feature_geom(geom(), |feat| {
+        feat.property(1, "pop", &ColumnValue::Long(1)).unwrap();
+        feat.property(0, "name", &ColumnValue::String("a")).unwrap();
+    }));
+    assert_eq!(
+        error,
+        SchemaError::OutOfOrder {
+            column: "name".to_string()
+        }
+    );
+    let error = schema_error(fgb.add_feature_geom(geom(), |feat| {
+        feat.property(0, "name", &ColumnValue::String("a")).unwrap();
+        feat.property(0, "name", &ColumnValue::String("b")).unwrap();
+    }));
+    assert_eq!(
+        error,
+        SchemaError::Duplicate {
+            column: "name".to_string()
+        }
+    );
+    let error = schema_error(fgb.add_feature_geom(geom(), |feat| {
+        feat.property(0, "name", &ColumnValue::String("äbcd"))
+            .unwrap();
+    }));
+    assert_eq!(
+        error,
+        SchemaError::Width {
+            column: "name".to_string(),
+            width: 3,
+            len: 4
+        }
+    );
+    // Columns are not declared automatically
+    let error = schema_error(fgb.add_feature(GeoJson(
+        r#"{"type": "Feature", "properties": {"name": "a", "pop": 1, "zone": 1.5}, "geometry": {"type": "Point", "coordinates": [2, 2]}}"#,
+    )));
+    assert_eq!(
+        error,
+        SchemaError::UnknownColumn {
+            index: 2,
+            name: "zone".to_string()
+        }
+    );
+    // Valid feature after failures
+    fgb.add_feature(GeoJson(
+        r#"{"type": "Feature", "properties": {"name": "b", "pop": 2}, "geometry": {"type": "Point", "coordinates": [2, 2]}}"#,
+    ))?;
+
+    let mut output = vec![];
+    fgb.write(&mut output)?;
+    let mut fgb = FgbReader::open(&*output)?.select_all_seq()?;
+    assert_eq!(fgb.features_count(), Some(2));
+    let feature = fgb.next()?.unwrap();
+    assert_eq!(feature.property::<String>("name")?, "abc");
+    let feature = fgb.next()?.unwrap();
+    assert_eq!(feature.property::<String>("name")?, "b");
+    assert_eq!(feature.property::<i64>("pop")?, 2);
+    Ok(())
+}
+
+#[test]
+fn lenient_schema_validation() -> Result<()> {
+    let mut fgb = FgbWriter::create_with_options(
+        "points",
+        GeometryType::Point,
+        FgbWriterOptions {
+            write_index: false,
+            schema_validation: SchemaValidation::Lenient,
+            ..Default::default()
+        },
+    )?;
+    fgb.add_column("pop", ColumnType::Long, |_, _| {});
+    fgb.add_column("area", ColumnType::Float, |_, _| {});
+    let geom = || GeoJson(r#"{"type": "Point", "coordinates": [1, 1]}"#);
+
+    // Numeric widening and assignment by name
+    fgb.add_feature_geom(geom(), |feat| {
+        feat.property(0, "area", &ColumnValue::Int(3)).unwrap();
+        feat.property(1, "pop", &ColumnValue::Int(42)).unwrap();
+    })?;
+    // Lossy conversion
+    let error = schema_error(fgb.add_feature_geom(geom(), |feat| {
+        feat.property(1, "area", &ColumnValue::Double(0.1)).unwrap();
+    }));
+    assert_eq!(
+        error,
+        SchemaError::ColumnType {
+            column: "area".to_string(),
+            expected: ColumnType::Float,
+            found: ColumnType::Double
+        }
+    );
+    // Columns declared on first sight
+    GeoJson(
+        r#"{"type": "Feature", "properties": {"pop": 7, "area": 1.5, "name": "b"}, "geometry": {"type": "Point", "coordinates": [2, 2]}}"#,
+    )
+    .process(&mut fgb)?;
+
+    let mut output = vec![];
+    fgb.write(&mut output)?;
+    let mut fgb = FgbReader::open(&*output)?.select_all_seq()?;
+    assert_eq!(fgb.features_count(), Some(2));
+    let feature = fgb.next()?.unwrap();
+    assert_eq!(feature.property::<i64>("pop")?, 42);
+    assert_eq!(feature.property::<f32>("area")?, 3.0);
+    let feature = fgb.next()?.unwrap();
+    assert_eq!(feature.property::<i64>("pop")?, 7);
+    assert_eq!(feature.property::<String>("name")?, "b");
+    Ok(())
+}
+
+#[test]
+fn feature_columns_round_trip() -> Result<()> {
+    let mut fgb = FgbWriter::create_with_options(
+        "places",
+        GeometryType::Point,
+        FgbWriterOptions {
+            write_index: false,
+            feature_columns: true,
+            ..Default::default()
+        },
+    )?;
+    let geojson = r#"{"type": "FeatureCollection", "features": [
+        {"type": "Feature", "properties": {"name": "a", "pop": 7}, "geometry": {"type": "Point", "coordinates": [1, 1]}},
+        {"type": "Feature", "properties": {"area": 1.5, "name": 5}, "geometry": {"type": "Point", "coordinates": [2, 2]}},
+        {"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [3, 3]}}
+    ]}"#;
+    GeoJson(geojson).process(&mut fgb)?;
+    let mut output = vec![];
+    fgb.write(&mut output)?;
+
+    let mut fgb = FgbReader::open(&*output)?.select_all_seq()?;
+    assert_eq!(fgb.header().columns().map_or(0, |columns| columns.len()), 0);
+    let feature = fgb.next()?.unwrap();
+    assert_eq!(feature.fbs_feature().columns().unwrap().len(), 2);
+    assert_eq!(feature.property::<String>("name")?, "a");
+    assert_eq!(feature.property::<i64>("pop")?, 7);
+    assert_eq!(feature.property_opt::<f64>("area")?, None);
+    let feature = fgb.next()?.unwrap();
+    assert_eq!(feature.property::<i64>("name")?, 5);
+    assert_eq!(feature.property::<f64>("area")?, 1.5);
+    let feature = fgb.next()?.unwrap();
+    assert!(feature.fbs_feature().columns().is_none());
+    assert_eq!(feature.property_opt::<String>("name")?, None);
+
+    // GeoJSON output
+    let mut fgb = FgbReader::open(&*output)?.select_all_seq()?;
+    let mut json = Vec::new();
+    fgb.process_features(&mut geozero::geojson::GeoJsonWriter::new(&mut json))?;
+    let json = String::from_utf8(json)?;
+    assert!(json.contains(r#""properties": {"name": "a", "pop": 7}"#));
+    assert!(json.contains(r#""properties": {"area": 1.5, "name": 5}"#));
+
+    // Projection and property filter by name
+    let mut fgb = FgbReader::open(&*output)?
+        .select_all_seq()?
+        .columns(&["name", "area"])?;
+    assert_eq!(
+        properties_with_nulls(fgb.next()?.unwrap())?,
+        ["name: a", "area: null unknown"]
+    );
+    assert_eq!(
+        properties_with_nulls(fgb.next()?.unwrap())?,
+        ["name: 5", "area: 1.5"]
+    );
+    assert_eq!(
+        properties_with_nulls(fgb.next()?.unwrap())?,
+        ["name: null unknown", "area: null unknown"]
+    );
+
+    let mut fgb = FgbReader::open(&*output)?
+        .select_all_seq()?
+        .columns(&["name", "area"])?
+        .explicit_nulls();
+    let mut json = Vec::new();
+    fgb.process_features(&mut geozero::geojson::GeoJsonWriter::new(&mut json))?;
+    let json = String::from_utf8(json)?;
+    assert!(json.contains(r#""properties": {"name": "a", "area": null}"#));
+    assert!(json.contains(r#""properties": {"name": 5, "area": 1.5}"#));
+    assert!(json.contains(r#""properties": {"name": null, "area": null}"#));
+
+    let mut fgb = FgbReader::open(&*output)?
+        .select_all_seq()?
+        .filter_properties(Expr::gt("area", 1))?;
+    let feature = fgb.next()?.unwrap();
+    assert_eq!(feature.property::<i64>("name")?, 5);
+    assert!(fgb.next()?.is_none());
+    Ok(())
+}
+
+#[test]
+fn feature_columns_with_dataset_columns() -> Result<()> {
+    let mut fgb = FgbWriter::create_with_options(
+        "places",
+        GeometryType::Point,
+        FgbWriterOptions {
+            write_index: false,
+            feature_columns: true,
+            schema_validation: SchemaValidation::Lenient,
+            ..Default::default()
+        },
+    )?;
+    fgb.add_column("pop", ColumnType::Long, |_, col| {
+        col.nullable = false;
+    });
+    let geom = || GeoJson(r#"{"type": "Point", "coordinates": [1, 1]}"#);
+    // Missing value of non-nullable dataset column
+    let result = fgb.add_feature_geom(geom(), |feat| {
+        feat.property(0, "name", &ColumnValue::String("a")).unwrap();
+    });
+    assert!(matches!(
+        result,
+        Err(geozero::error::GeozeroError::Feature(msg))
+            if msg == Error::MissingValue("pop".to_string()).to_string()
+    ));
+    // Value not convertible to the dataset column type
+    let result = fgb.add_feature_geom(geom(), |feat| {
+        feat.property(0, "name", &ColumnValue::String("b")).unwrap();
+        feat.property(1, "pop", &ColumnValue::String("many"))
+            .unwrap();
+    });
+    assert!(matches!(
+        schema_error(result),
+        SchemaError::ColumnType { .. }
+    ));
+    // Value converted to the dataset column type
+    fgb.add_feature_geom(geom(), |feat| {
+        feat.property(0, "name", &ColumnValue::String("c")).unwrap();
+        feat.property(1, "pop", &ColumnValue::Int(7)).unwrap();
+    })?;
+    let mut output = vec![];
+    fgb.write(&mut output)?;
+
+    let mut fgb = FgbReader::open(&*output)?.select_all_seq()?;
+    assert_eq!(fgb.features_count(), Some(1));
+    let feature = fgb.next()?.unwrap();
+    assert_eq!(properties_with_nulls(feature)?, ["name: c", "pop: 7"]);
+    let columns = feature.fbs_feature().columns().unwrap();
+    assert_eq!(columns.get(1).type_(), ColumnType::Long);
+    Ok(())
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn par_add_features() -> Result<()> {
+    let mut features = (0..10_000)
+        .map(|i| {
+            let (x, y) = ((i * 7919 % 360) as f64 - 180.0, (i * 104_729 % 180) as f64 - 90.0);
+            format!(
+                r#"{{"type": "Feature", "properties": {{"fid": {i}, "name": "feature {i}"}}, "geometry": {{"type": "Point", "coordinates": [{x}, {y}]}}}}"#
+            )
+        })
+        .collect::<Vec<_>>();
+    let write =
+        |features: &[String], parallel: bool, memory_budget: Option<usize>| -> Result<Vec<u8>> {
+            let mut fgb = FgbWriter::create_with_options(
+                "points",
+                GeometryType::Unknown,
+                FgbWriterOptions {
+                    memory_budget,
+                    ..Default::default()
+                },
+            )?;
+            fgb.add_column("fid", ColumnType::Long, |_, _| {});
+            fgb.add_column("name", ColumnType::String, |_, _| {});
+            if parallel {
+                fgb.par_add_features(features.iter().map(|f| GeoJson(f)))?;
+            } else {
+                for feature in features {
+                    fgb.add_feature(GeoJson(feature))?;
+                }
+            }
+            let mut out = Vec::new();
+            fgb.write(&mut out)?;
+            Ok(out)
+        };
+    let serial = write(&features, false, None)?;
+    assert_eq!(write(&features, true, None)?, serial);
+    assert_eq!(write(&features, true, Some(100_000))?, serial);
+
+    let fgb = FgbReader::open(std::io::Cursor::new(&serial))?;
+    assert_eq!(fgb.header().geometry_type(), GeometryType::Point);
+    let mut fgb = fgb.select_bbox(-180.0, -90.0, -179.5, -89.5)?;
+    let feature = fgb.next()?.unwrap();
+    assert_eq!(feature.property::<String>("name")?, "feature 0");
+
+    // Features preceding a failed one are added
+    let mut fgb = FgbWriter::create_with_options(
+        "points",
+        GeometryType::Point,
+        FgbWriterOptions {
+            schema_validation: SchemaValidation::Strict,
+            ..Default::default()
+        },
+    )?;
+    fgb.add_column("fid", ColumnType::Long, |_, _| {});
+    fgb.add_column("name", ColumnType::String, |_, _| {});
+    let mut invalid = features.clone();
+    invalid[5000] = invalid[5000].replace(r#""fid": 5000"#, r#""fid": "5000""#);
+    let error = schema_error(fgb.par_add_features(invalid.iter().map(|f| GeoJson(f))));
+    assert!(matches!(error, SchemaError::ColumnType { .. }));
+    let mut out = Vec::new();
+    fgb.write(&mut out)?;
+    let fgb = FgbReader::open(std::io::Cursor::new(&out))?;
+    assert_eq!(fgb.header().features_count(), 5000);
+
+    // Geometry type detected after features without geometry
+    for feature in &mut features[..10] {
+        *feature = feature.replace(
+            &feature[feature.find(r#""geometry""#).unwrap()..],
+            r#""geometry": null}"#,
+        );
+    }
+    let serial = write(&features, false, None)?;
+    assert_eq!(write(&features, true, None)?, serial);
+    let fgb = FgbReader::open(std::io::Cursor::new(&serial))?;
+    assert_eq!(fgb.header().geometry_type(), GeometryType::Point);
+
+    // Mixed geometry types are rejected
+    features[6000] = features[6000].replace("Point", "MultiPoint");
+    let write_mixed = |parallel: bool| {
+        let mut fgb = FgbWriter::create("points", GeometryType::Unknown)?;
+        fgb.add_column("fid", ColumnType::Long, |_, _| {});
+        fgb.add_column("name", ColumnType::String, |_, _| {});
+        let result = if parallel {
+            fgb.par_add_features(features.iter().map(|f| GeoJson(f)))
+        } else {
+            features
+                .iter()
+                .try_for_each(|feature| fgb.add_feature(GeoJson(feature)))
+        };
+        assert!(result.is_err());
+        let mut out = Vec::new();
+        fgb.write(&mut out)?;
+        Ok::<_, Error>(out)
+    };
+    assert_eq!(write_mixed(true)?, write_mixed(false)?);
+    Ok(())
+}
+
+#[test]
+fn sort_orders() -> Result<()> {
+    let bbox_names = |data: &[u8]| -> Result<Vec<String>> {
+        let mut fgb =
+            FgbReader::open(std::io::Cursor::new(data))?.select_bbox(8.8, 47.2, 9.5, 55.3)?;
+        let mut names = Vec::new();
+        while let Some(feature) = fgb.next()? {
+            names.push(feature.property::<String>("name")?);
+        }
+        names.sort();
+        Ok(names)
+    };
+    let write = |sort: SortOrder, memory_budget: Option<usize>| -> Result<Vec<u8>> {
+        let mut fgb = FgbWriter::create_with_options(
+            "countries",
+            GeometryType::MultiPolygon,
+            FgbWriterOptions {
+                sort,
+                memory_budget,
+                ..Default::default()
+            },
+        )?;
+        let mut fin = BufReader::new(File::open("../../test/data/countries.geojson")?);
+        GeoJsonReader(&mut fin).process(&mut fgb)?;
+        let mut out = Vec::new();
+        fgb.write(&mut out)?;
+        Ok(out)
+    };
+    let hilbert = write(SortOrder::Hilbert, None)?;
+    let expected = bbox_names(&hilbert)?;
+    assert_eq!(expected.len(), 6);
+    for sort in [
+        SortOrder::ZOrder,
+        SortOrder::Str,
+        SortOrder::Input,
+        SortOrder::Key(|node, _| (node.width() * node.height()) as u64),
+    ] {
+        let data = write(sort, None)?;
+        assert_ne!(data, hilbert, "{sort:?}");
+        assert_eq!(bbox_names(&data)?, expected, "{sort:?}");
+        assert_eq!(write(sort, Some(1000))?, data, "{sort:?}");
+    }
+
+    // Features keep the input order of an unindexed dataset
+    let unindexed = write_countries_file(false)?;
+    let indexed = NamedTempFile::new()?;
+    std::fs::write(indexed.path(), write(SortOrder::Input, None)?)?;
+    assert_eq!(read_names(indexed.path())?, read_names(unindexed.path())?);
+    Ok(())
+}